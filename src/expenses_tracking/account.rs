@@ -0,0 +1,296 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::currency::Currency;
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::name_matching::NameMatching;
+use crate::transaction::{ClearedState, Transaction, TransactionId};
+
+/// What an account is, which only helps the user tell accounts apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountKind {
+    #[default]
+    Current,
+    Savings,
+    CreditCard,
+    Cash,
+}
+
+/// An account transactions are made with, such as a current account, a credit card or cash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub name: String,
+    #[serde(default)]
+    pub kind: AccountKind,
+    /// Currency of the balance, which every transaction of the account must use.
+    pub currency: Currency,
+    /// Balance at the start of the opening date, before any of its transactions.
+    pub opening_balance: Money,
+    /// Date from which the transactions of the account are tracked. Earlier transactions are
+    /// already part of the opening balance.
+    pub opening_date: NaiveDate,
+    /// Closing date of the last reconciled statement, up to which no transaction can be added.
+    #[serde(default)]
+    pub reconciled_until: Option<NaiveDate>,
+}
+
+/// The balance of an account after one of its transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningBalance {
+    pub transaction_id: TransactionId,
+    pub date: NaiveDate,
+    /// Amount added to the account, which is the opposite of the amount of the transaction if the
+    /// account receives a transfer.
+    pub amount: Money,
+    pub balance: Money,
+}
+
+impl Account {
+    pub fn new(
+        name: &str,
+        kind: AccountKind,
+        currency: Currency,
+        opening_balance: Money,
+        opening_date: NaiveDate,
+    ) -> Self {
+        Account {
+            name: name.trim().to_string(),
+            kind,
+            currency,
+            opening_balance,
+            opening_date,
+            reconciled_until: None,
+        }
+    }
+
+    /// Returns whether a date is in a period which was reconciled with a statement.
+    pub fn is_reconciled_on(&self, date: NaiveDate) -> bool {
+        self.reconciled_until
+            .is_some_and(|reconciled_until| date <= reconciled_until)
+    }
+
+    /// Returns whether the account makes or receives a transaction.
+    pub fn is_part_of(&self, transaction: &Transaction, matching: NameMatching) -> bool {
+        [&transaction.account, &transaction.transfer_account]
+            .into_iter()
+            .flatten()
+            .any(|account_name| matching.matches(account_name, &self.name))
+    }
+
+    fn is_named(&self, account_name: &Option<String>, matching: NameMatching) -> bool {
+        account_name
+            .as_deref()
+            .is_some_and(|account_name| matching.matches(account_name, &self.name))
+    }
+
+    /// Returns the state of a transaction on the statements of the account, or `None` if the
+    /// account is not part of it. Each side of a transfer has its own state.
+    pub fn cleared_state(
+        &self,
+        transaction: &Transaction,
+        matching: NameMatching,
+    ) -> Option<ClearedState> {
+        if self.is_named(&transaction.account, matching) {
+            Some(transaction.cleared)
+        } else if self.is_named(&transaction.transfer_account, matching) {
+            Some(transaction.transfer_cleared)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the state of a transaction on the statements of the account, leaving the other side
+    /// of a transfer as it is. Returns `false` if the account is not part of the transaction.
+    pub fn set_cleared_state(
+        &self,
+        transaction: &mut Transaction,
+        cleared: ClearedState,
+        matching: NameMatching,
+    ) -> bool {
+        if self.is_named(&transaction.account, matching) {
+            transaction.cleared = cleared;
+        } else if self.is_named(&transaction.transfer_account, matching) {
+            transaction.transfer_cleared = cleared;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Returns the amount a transaction adds to the account, or `None` if the account is not
+    /// part of it. An account receiving a transfer gets the opposite of its amount.
+    pub fn amount_of(
+        &self,
+        transaction: &Transaction,
+        matching: NameMatching,
+    ) -> Result<Option<Money>, ExpenseTrackerError> {
+        if self.is_named(&transaction.account, matching) {
+            Ok(Some(transaction.amount))
+        } else if self.is_named(&transaction.transfer_account, matching) {
+            transaction
+                .amount
+                .checked_neg()
+                .map(Some)
+                .ok_or_else(|| self.balance_out_of_range())
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn balance_out_of_range(&self) -> ExpenseTrackerError {
+        ExpenseTrackerError::AmountOutOfRange(format!("balance of '{}'", self.name))
+    }
+
+    /// Returns the balance after every transaction of the account from the opening date, in
+    /// order of date. Transactions of the same date keep their order, and transactions of other
+    /// accounts are left out.
+    ///
+    /// Fails if a transaction of the account is in another currency than the account.
+    pub fn running_balances<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+        matching: NameMatching,
+    ) -> Result<Vec<RunningBalance>, ExpenseTrackerError> {
+        let mut movements: Vec<(&Transaction, Money)> = transactions
+            .into_iter()
+            .filter(|transaction| transaction.date >= self.opening_date)
+            .filter_map(|transaction| {
+                self.amount_of(transaction, matching)
+                    .transpose()
+                    .map(|amount| Ok((transaction, amount?)))
+            })
+            .collect::<Result<_, ExpenseTrackerError>>()?;
+        movements.sort_by_key(|(transaction, _)| transaction.date);
+
+        let mut balance = self.opening_balance;
+        movements
+            .into_iter()
+            .map(|(transaction, amount)| {
+                if transaction.currency != self.currency {
+                    return Err(ExpenseTrackerError::MixedCurrencies {
+                        currency: self.currency,
+                        other_currencies: vec![transaction.currency],
+                    });
+                }
+                balance = balance
+                    .checked_add(amount)
+                    .ok_or_else(|| self.balance_out_of_range())?;
+                Ok(RunningBalance {
+                    transaction_id: transaction.id,
+                    date: transaction.date,
+                    amount,
+                    balance,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the balance at the end of a date, or `None` if the date is before the opening
+    /// date.
+    pub fn balance_on<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+        date: NaiveDate,
+        matching: NameMatching,
+    ) -> Result<Option<Money>, ExpenseTrackerError> {
+        if date < self.opening_date {
+            return Ok(None);
+        }
+        let running_balances = self.running_balances(
+            transactions
+                .into_iter()
+                .filter(|transaction| transaction.date <= date),
+            matching,
+        )?;
+        Ok(Some(
+            running_balances
+                .last()
+                .map_or(self.opening_balance, |running_balance| {
+                    running_balance.balance
+                }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(day: u32, amount: i64, currency: Currency) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2023, 2, day).unwrap(),
+            amount: Money::from_minor_units(amount),
+            currency,
+            category_name: "Nourriture".to_string(),
+            account: Some("Compte courant".to_string()),
+            ..Transaction::new()
+        }
+    }
+
+    fn transfer(day: u32, amount: i64, from: &str, to: &str) -> Transaction {
+        Transaction {
+            category_name: String::new(),
+            account: Some(from.to_string()),
+            transfer_account: Some(to.to_string()),
+            ..transaction(day, amount, Currency::CHF)
+        }
+    }
+
+    #[test]
+    fn balances_from_opening_date() {
+        let account = Account::new(
+            "Compte courant",
+            AccountKind::Current,
+            Currency::CHF,
+            Money::from_minor_units(100_000),
+            NaiveDate::from_ymd_opt(2023, 2, 5).unwrap(),
+        );
+        let transactions = [
+            transaction(9, -2_500, Currency::CHF),
+            // Already part of the opening balance
+            transaction(3, -1_000, Currency::CHF),
+            transaction(6, 50_000, Currency::CHF),
+            transaction(6, -790, Currency::CHF),
+            // The account receives the opposite of the amount of a transfer to it
+            transfer(8, -10_000, "Épargne", "compte courant"),
+            transfer(8, -5_000, "Épargne", "Carte de crédit"),
+        ];
+        let matching = NameMatching::default();
+
+        let running_balances = account.running_balances(&transactions, matching).unwrap();
+        let balances: Vec<i64> = running_balances
+            .iter()
+            .map(|running_balance| running_balance.balance.minor_units())
+            .collect();
+        assert_eq!(balances, [150_000, 149_210, 159_210, 156_710]);
+        assert_eq!(running_balances[0].transaction_id, transactions[2].id);
+        assert_eq!(running_balances[2].amount, Money::from_minor_units(10_000));
+
+        let balance_on = |day: u32| {
+            account
+                .balance_on(
+                    &transactions,
+                    NaiveDate::from_ymd_opt(2023, 2, day).unwrap(),
+                    matching,
+                )
+                .unwrap()
+                .map(|balance| balance.minor_units())
+        };
+        assert_eq!(balance_on(4), None);
+        assert_eq!(balance_on(5), Some(100_000));
+        assert_eq!(balance_on(7), Some(149_210));
+        assert_eq!(balance_on(28), Some(156_710));
+
+        assert!(matches!(
+            account.running_balances(&[transaction(7, -500, Currency::EUR)], matching),
+            Err(ExpenseTrackerError::MixedCurrencies { .. })
+        ));
+        assert_eq!(
+            account.running_balances(&[transaction(7, i64::MAX, Currency::CHF)], matching),
+            Err(ExpenseTrackerError::AmountOutOfRange(
+                "balance of 'Compte courant'".to_string()
+            ))
+        );
+    }
+}
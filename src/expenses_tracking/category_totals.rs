@@ -0,0 +1,90 @@
+use crate::currency::{Currency, Totals};
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::transaction::{join_path, Category, SubCategory};
+
+/// The totals of a category or sub-category, which include the totals of every sub-category
+/// below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryTotal {
+    /// Display name of the category or sub-category.
+    pub name: String,
+    /// Path from the top-level category, such as "Voyages/Nourriture".
+    pub path: String,
+    /// Sum of the amounts of the transactions, kept separately for each currency.
+    pub totals: Totals,
+    pub n_transactions: usize,
+    /// Totals of the sub-categories one level below.
+    pub subcategories: Vec<CategoryTotal>,
+}
+
+impl CategoryTotal {
+    /// Creates empty totals for a category and its tree of sub-categories.
+    pub(crate) fn from_category(category: &Category) -> Self {
+        CategoryTotal::new(
+            &category.name,
+            category.name.clone(),
+            &category.subcategories,
+        )
+    }
+
+    fn new<'a>(
+        name: &str,
+        path: String,
+        subcategories: impl IntoIterator<Item = &'a SubCategory>,
+    ) -> Self {
+        let subcategories = subcategories
+            .into_iter()
+            .map(|subcategory| {
+                CategoryTotal::new(
+                    &subcategory.name,
+                    join_path([path.as_str(), subcategory.name.as_str()]),
+                    &subcategory.subcategories,
+                )
+            })
+            .collect();
+        CategoryTotal {
+            name: name.to_string(),
+            path,
+            totals: Totals::new(),
+            n_transactions: 0,
+            subcategories,
+        }
+    }
+
+    /// Adds the sum of the amounts of `n_transactions` transactions to these totals and to the
+    /// totals of the sub-categories along `subcategory_names`.
+    pub(crate) fn add(
+        &mut self,
+        subcategory_names: &[&str],
+        amount: Money,
+        currency: Currency,
+        n_transactions: usize,
+    ) -> Result<(), ExpenseTrackerError> {
+        self.totals.add(amount, currency)?;
+        self.n_transactions += n_transactions;
+        if let Some((name, other_names)) = subcategory_names.split_first() {
+            if let Some(subcategory_total) = self
+                .subcategories
+                .iter_mut()
+                .find(|subcategory_total| subcategory_total.name == *name)
+            {
+                subcategory_total.add(other_names, amount, currency, n_transactions)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the totals of the sub-category below this one along `subcategory_names`, given by
+    /// their display names, or these totals if `subcategory_names` is empty.
+    pub fn get(&self, subcategory_names: &[&str]) -> Option<&CategoryTotal> {
+        match subcategory_names.split_first() {
+            None => Some(self),
+            Some((name, other_names)) => self
+                .subcategories
+                .iter()
+                .find(|subcategory_total| subcategory_total.name == *name)?
+                .get(other_names),
+        }
+    }
+}
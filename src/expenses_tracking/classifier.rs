@@ -0,0 +1,336 @@
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::ExpenseTrackerError;
+use crate::money::MINOR_UNITS_PER_MAJOR;
+use crate::transaction::Transaction;
+
+/// A category and sub-category suggested for a transaction by a `CategoryClassifier`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySuggestion {
+    pub category_name: String,
+    pub subcategory_name: Option<String>,
+    /// Probability of the suggestion according to the classifier, between 0 and 1.
+    pub confidence: f64,
+}
+
+/// What the classifier learned about the transactions of a category and sub-category.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct ClassStatistics {
+    category_name: String,
+    subcategory_name: Option<String>,
+    n_transactions: u32,
+    /// Number of occurrences of every feature in the transactions.
+    feature_counts: BTreeMap<String, u32>,
+    /// Sum of `feature_counts`.
+    n_features: u32,
+}
+
+/// A naive Bayes classifier suggesting the category and sub-category of a transaction from the
+/// categories of previous ones.
+///
+/// The features of a transaction are the words of its note and tag, the order of magnitude of its
+/// amount and its weekday. The classifier only keeps counts, such that it can learn or forget a
+/// transaction without being trained again on the whole history.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryClassifier {
+    classes: Vec<ClassStatistics>,
+    /// Every feature seen in a transaction, used to smooth the probabilities.
+    vocabulary: BTreeSet<String>,
+}
+
+/// Returns the features of a transaction.
+fn features(transaction: &Transaction) -> Vec<String> {
+    let words = |prefix: &str, text: &Option<String>| -> Vec<String> {
+        text.as_deref()
+            .unwrap_or_default()
+            .split(|c: char| !c.is_alphanumeric())
+            // Digits are mostly dates and references, which do not tell much about the category
+            .filter(|word| word.chars().count() > 1 && !word.chars().all(|c| c.is_numeric()))
+            .map(|word| format!("{prefix}:{}", word.to_lowercase()))
+            .collect()
+    };
+
+    let mut features = words("note", &transaction.note);
+    features.extend(words("tag", &transaction.tag));
+
+    // Amounts are bucketed by sign and number of digits of their major units
+    let sign = if transaction.amount.is_negative() {
+        "out"
+    } else {
+        "in"
+    };
+    let major_units =
+        transaction.amount.minor_units().unsigned_abs() / MINOR_UNITS_PER_MAJOR as u64;
+    let n_digits = major_units.checked_ilog10().map_or(0, |log| log + 1);
+    features.push(format!("amount:{sign}:{n_digits}"));
+    features.push(format!("weekday:{}", transaction.date.weekday()));
+
+    features
+}
+
+impl CategoryClassifier {
+    pub fn new() -> Self {
+        CategoryClassifier::default()
+    }
+
+    /// Trains a classifier on categorized transactions.
+    pub fn train<'a>(transactions: impl IntoIterator<Item = &'a Transaction>) -> Self {
+        let mut classifier = CategoryClassifier::new();
+        for transaction in transactions {
+            classifier.learn(transaction);
+        }
+        classifier
+    }
+
+    /// Returns whether the classifier has not learned any transaction.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Returns the number of transactions learned.
+    pub fn n_transactions(&self) -> u32 {
+        self.classes.iter().map(|class| class.n_transactions).sum()
+    }
+
+    /// Returns the number of transactions learned with a category and sub-category.
+    pub fn n_transactions_in(&self, category_name: &str, subcategory_name: Option<&str>) -> u32 {
+        self.classes
+            .iter()
+            .find(|class| {
+                class.category_name == category_name
+                    && class.subcategory_name.as_deref() == subcategory_name
+            })
+            .map_or(0, |class| class.n_transactions)
+    }
+
+    fn class_index(&self, transaction: &Transaction) -> Option<usize> {
+        self.classes.iter().position(|class| {
+            class.category_name == transaction.category_name
+                && class.subcategory_name == transaction.subcategory_name
+        })
+    }
+
+    /// Learns the category and sub-category of a transaction, e.g. after a suggestion was
+    /// corrected. Transactions without a category are ignored.
+    pub fn learn(&mut self, transaction: &Transaction) {
+        if transaction.category_name.trim().is_empty() {
+            return;
+        }
+        let index = self.class_index(transaction).unwrap_or_else(|| {
+            self.classes.push(ClassStatistics {
+                category_name: transaction.category_name.clone(),
+                subcategory_name: transaction.subcategory_name.clone(),
+                ..ClassStatistics::default()
+            });
+            self.classes.len() - 1
+        });
+
+        let class = &mut self.classes[index];
+        class.n_transactions += 1;
+        for feature in features(transaction) {
+            *class.feature_counts.entry(feature.clone()).or_default() += 1;
+            class.n_features += 1;
+            self.vocabulary.insert(feature);
+        }
+    }
+
+    /// Forgets a transaction learned before, e.g. because its category was changed.
+    ///
+    /// The vocabulary is kept, which only slightly smooths the probabilities.
+    pub fn forget(&mut self, transaction: &Transaction) {
+        let Some(index) = self.class_index(transaction) else {
+            return;
+        };
+
+        let class = &mut self.classes[index];
+        class.n_transactions = class.n_transactions.saturating_sub(1);
+        for feature in features(transaction) {
+            if let Some(count) = class.feature_counts.get_mut(&feature) {
+                *count -= 1;
+                class.n_features -= 1;
+                if *count == 0 {
+                    class.feature_counts.remove(&feature);
+                }
+            }
+        }
+        if class.n_transactions == 0 {
+            self.classes.remove(index);
+        }
+    }
+
+    /// Suggests a category and sub-category for a transaction, or `None` if nothing was learned.
+    pub fn suggest(&self, transaction: &Transaction) -> Option<CategorySuggestion> {
+        let features = features(transaction);
+        let n_transactions = self.n_transactions() as f64;
+        let vocabulary_size = self.vocabulary.len() as f64;
+
+        // Log-probabilities with Laplace smoothing, such that unseen features do not rule out a
+        // class
+        let log_probabilities: Vec<f64> = self
+            .classes
+            .iter()
+            .map(|class| {
+                let prior = (class.n_transactions as f64 / n_transactions).ln();
+                let denominator = class.n_features as f64 + vocabulary_size;
+                features.iter().fold(prior, |log_probability, feature| {
+                    let count = class.feature_counts.get(feature).copied().unwrap_or(0);
+                    log_probability + ((count as f64 + 1.0) / denominator).ln()
+                })
+            })
+            .collect();
+
+        let (best_index, best_log_probability) = log_probabilities
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        let normalization: f64 = log_probabilities
+            .iter()
+            .map(|log_probability| (log_probability - best_log_probability).exp())
+            .sum();
+
+        let class = &self.classes[best_index];
+        Some(CategorySuggestion {
+            category_name: class.category_name.clone(),
+            subcategory_name: class.subcategory_name.clone(),
+            confidence: 1.0 / normalization,
+        })
+    }
+
+    /// Loads a classifier saved with `save_to_file`.
+    pub fn load_from_file(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let file = File::open(file_path).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to open classifier file: {e}"))
+        })?;
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to read classifier file: {e}"))
+        })
+    }
+
+    /// Saves the classifier, replacing the file if it exists.
+    pub fn save_to_file(&self, file_path: &Path) -> Result<(), ExpenseTrackerError> {
+        let file = File::create(file_path).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to create classifier file: {e}"))
+        })?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to write classifier file: {e}"))
+        })?;
+        writer.flush().map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to write classifier file: {e}"))
+        })
+    }
+}
+
+/// Returns the path of the classifier saved next to a project file, e.g.
+/// `expenseTrackerConfig.classifier.json` next to `expenseTrackerConfig.json`.
+pub fn classifier_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    config_path.with_file_name(format!("{stem}.classifier.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use chrono::NaiveDate;
+
+    fn transaction(
+        day: u32,
+        amount: i64,
+        category_name: &str,
+        subcategory_name: Option<&str>,
+        note: Option<&str>,
+    ) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2023, 2, day).unwrap(),
+            amount: Money::from_minor_units(amount),
+            category_name: category_name.to_string(),
+            subcategory_name: subcategory_name.map(str::to_string),
+            note: note.map(str::to_string),
+            ..Transaction::new()
+        }
+    }
+
+    #[test]
+    fn learn_and_forget_categories() {
+        let history = [
+            transaction(
+                6,
+                -790,
+                "Nourriture",
+                Some("Lunch"),
+                Some("Migros Take Away"),
+            ),
+            transaction(
+                7,
+                -1055,
+                "Nourriture",
+                Some("Lunch"),
+                Some("Coop Take Away"),
+            ),
+            transaction(9, -3240, "Nourriture", Some("Courses"), Some("Migros")),
+            transaction(7, -1370, "Transports", None, Some("CFF Mobile")),
+        ];
+        let mut classifier = CategoryClassifier::train(&history);
+        assert_eq!(classifier.n_transactions(), 4);
+
+        let suggestion = classifier
+            .suggest(&transaction(8, -1490, "", None, Some("Migros take away")))
+            .unwrap();
+        assert_eq!(suggestion.category_name, "Nourriture");
+        assert_eq!(suggestion.subcategory_name.as_deref(), Some("Lunch"));
+        assert!(suggestion.confidence > 0.5 && suggestion.confidence <= 1.0);
+
+        let ticket = transaction(10, -2740, "Transports", None, Some("CFF Mobile Ticket"));
+        assert_eq!(
+            classifier.suggest(&ticket).unwrap().category_name,
+            "Transports"
+        );
+
+        // Correcting a suggestion only updates the counts
+        let corrected = transaction(10, -2740, "Voyages", Some("Transports"), Some("CFF"));
+        classifier.learn(&corrected);
+        assert_eq!(classifier.n_transactions(), 5);
+        classifier.forget(&corrected);
+        classifier.forget(&history[3]);
+        assert_eq!(classifier.n_transactions(), 3);
+        assert_ne!(
+            classifier.suggest(&ticket).unwrap().category_name,
+            "Transports"
+        );
+
+        assert!(CategoryClassifier::new().suggest(&ticket).is_none());
+    }
+
+    #[test]
+    fn classifier_is_saved_next_to_config() {
+        assert_eq!(
+            classifier_path(Path::new("data/expenseTrackerConfig.json")),
+            PathBuf::from("data/expenseTrackerConfig.classifier.json")
+        );
+
+        let classifier = CategoryClassifier::train(&[transaction(
+            6,
+            -850,
+            "Abonnements",
+            Some("Spotify"),
+            None,
+        )]);
+        let tmp_dir = tempdir::TempDir::new("classifier").unwrap();
+        let file_path = tmp_dir.path().join("classifier.json");
+        classifier.save_to_file(&file_path).unwrap();
+        assert_eq!(
+            CategoryClassifier::load_from_file(&file_path).unwrap(),
+            classifier
+        );
+    }
+}
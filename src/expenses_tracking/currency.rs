@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+
+/// An ISO 4217 currency code such as `CHF` or `EUR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const CHF: Currency = Currency(*b"CHF");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const USD: Currency = Currency(*b"USD");
+
+    /// Returns the three-letter code of the currency.
+    pub fn code(&self) -> &str {
+        // Safe to unwrap because the code is only ever built from ASCII letters
+        std::str::from_utf8(&self.0).unwrap()
+    }
+}
+
+// All amounts used to be in CHF before currencies were introduced, so it remains the default
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::CHF
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Box<dyn Error>;
+
+    /// Parses a currency code made of three ASCII letters, e.g. `chf` or `EUR`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_ascii_uppercase();
+        let bytes: [u8; 3] = code
+            .as_bytes()
+            .try_into()
+            .map_err(|_| format!("Currency code '{s}' does not have three letters"))?;
+        if !bytes.iter().all(|b| b.is_ascii_uppercase()) {
+            return Err(format!("Currency code '{s}' must only contain letters").into());
+        }
+        Ok(Currency(bytes))
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = Box<dyn Error>;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.code().to_string()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Sums of amounts kept separately for each currency, such that amounts in different currencies
+/// are never added together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Totals {
+    by_currency: BTreeMap<Currency, Money>,
+}
+
+impl Totals {
+    /// Creates empty totals.
+    pub fn new() -> Self {
+        Totals {
+            by_currency: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an amount to the total of its currency, failing if the total is out of range.
+    pub fn add(&mut self, amount: Money, currency: Currency) -> Result<(), ExpenseTrackerError> {
+        let total = self.by_currency.entry(currency).or_default();
+        *total = total
+            .checked_add(amount)
+            .ok_or_else(|| ExpenseTrackerError::AmountOutOfRange(format!("total in {currency}")))?;
+        Ok(())
+    }
+
+    /// Returns the total of a given currency, which is zero if no amount in that currency exists.
+    pub fn get(&self, currency: Currency) -> Money {
+        self.by_currency.get(&currency).copied().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the total of each currency, ordered by currency code.
+    pub fn iter(&self) -> impl Iterator<Item = (Currency, Money)> + '_ {
+        self.by_currency
+            .iter()
+            .map(|(currency, amount)| (*currency, *amount))
+    }
+
+    /// Returns true if no amount was added.
+    pub fn is_empty(&self) -> bool {
+        self.by_currency.is_empty()
+    }
+
+    /// Returns the total in a given currency, or an error if amounts in other currencies exist.
+    pub fn total_in(&self, currency: Currency) -> Result<Money, ExpenseTrackerError> {
+        let other_currencies: Vec<Currency> = self
+            .by_currency
+            .keys()
+            .filter(|other| **other != currency)
+            .copied()
+            .collect();
+        if !other_currencies.is_empty() {
+            return Err(ExpenseTrackerError::MixedCurrencies {
+                currency,
+                other_currencies,
+            });
+        }
+        Ok(self.get(currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_currency() {
+        assert_eq!(Currency::from_str("eur").unwrap(), Currency::EUR);
+        assert_eq!(Currency::from_str(" CHF ").unwrap().to_string(), "CHF");
+        for invalid in ["", "EU", "EURO", "E1R", "€€€"] {
+            assert!(Currency::from_str(invalid).is_err(), "'{invalid}'");
+        }
+    }
+
+    #[test]
+    fn totals_never_mix_currencies() {
+        let mut totals = Totals::new();
+        totals
+            .add(Money::from_minor_units(1000), Currency::CHF)
+            .unwrap();
+        totals
+            .add(Money::from_minor_units(250), Currency::CHF)
+            .unwrap();
+        assert_eq!(
+            totals.total_in(Currency::CHF).unwrap(),
+            Money::from_minor_units(1250)
+        );
+
+        totals
+            .add(Money::from_minor_units(500), Currency::EUR)
+            .unwrap();
+        assert_eq!(
+            totals.total_in(Currency::CHF),
+            Err(ExpenseTrackerError::MixedCurrencies {
+                currency: Currency::CHF,
+                other_currencies: vec![Currency::EUR]
+            })
+        );
+        assert_eq!(totals.get(Currency::EUR), Money::from_minor_units(500));
+
+        // A total out of range is an error, and the total is left unchanged
+        assert_eq!(
+            totals.add(Money::from_minor_units(i64::MAX), Currency::EUR),
+            Err(ExpenseTrackerError::AmountOutOfRange(
+                "total in EUR".to_string()
+            ))
+        );
+        assert_eq!(totals.get(Currency::EUR), Money::from_minor_units(500));
+    }
+}
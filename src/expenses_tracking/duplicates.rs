@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+use crate::name_matching::NameMatching;
+use crate::transaction::Transaction;
+
+/// What to do with an imported row which looks like a transaction already there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicatePolicy {
+    /// The row is not imported.
+    #[default]
+    Skip,
+    /// The row is imported anyway.
+    Keep,
+    /// The row is not imported, and the user decides what to do from the `ImportReport`.
+    Ask,
+}
+
+/// The fields which must be equal, besides the date, for two transactions to be duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateKey {
+    /// Compare the amounts and their currencies.
+    pub amount: bool,
+    /// Compare the notes, ignoring case and surrounding whitespace.
+    pub note: bool,
+    /// Compare the categories and sub-categories.
+    pub category: bool,
+    /// Compare the tags, ignoring case and surrounding whitespace.
+    pub tag: bool,
+}
+
+impl Default for DuplicateKey {
+    fn default() -> Self {
+        DuplicateKey {
+            amount: true,
+            note: true,
+            category: false,
+            tag: false,
+        }
+    }
+}
+
+/// How imported rows are compared to the existing transactions to detect duplicates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateDetection {
+    pub key: DuplicateKey,
+    /// Largest number of days between the dates of duplicates, since banks may book a
+    /// transaction a few days after it happened.
+    pub date_tolerance_days: u32,
+    pub policy: DuplicatePolicy,
+}
+
+impl DuplicateDetection {
+    /// Returns whether two transactions are duplicates according to the key and the date
+    /// tolerance. Transactions with the same ID are always duplicates.
+    pub fn are_duplicates(
+        &self,
+        transaction: &Transaction,
+        other: &Transaction,
+        matching: NameMatching,
+    ) -> bool {
+        let same_text = |a: &Option<String>, b: &Option<String>| {
+            let normalize =
+                |text: &Option<String>| text.as_deref().unwrap_or_default().trim().to_lowercase();
+            normalize(a) == normalize(b)
+        };
+
+        if transaction.id == other.id {
+            return true;
+        }
+
+        (transaction.date - other.date).num_days().unsigned_abs()
+            <= u64::from(self.date_tolerance_days)
+            && (!self.key.amount
+                || (transaction.amount == other.amount && transaction.currency == other.currency))
+            && (!self.key.note || same_text(&transaction.note, &other.note))
+            && (!self.key.category
+                || (matching.matches(&transaction.category_name, &other.category_name)
+                    && matching.matches(
+                        transaction.subcategory_name.as_deref().unwrap_or_default(),
+                        other.subcategory_name.as_deref().unwrap_or_default(),
+                    )))
+            && (!self.key.tag || same_text(&transaction.tag, &other.tag))
+    }
+
+    /// Returns the index of the first of the indexed transactions which is a duplicate of the
+    /// given one, skipping the ones for which `is_excluded` returns `true`.
+    pub fn find_duplicate<'a>(
+        &self,
+        transaction: &Transaction,
+        transactions: impl IntoIterator<Item = (usize, &'a Transaction)>,
+        matching: NameMatching,
+        is_excluded: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        transactions
+            .into_iter()
+            .find(|(index, other)| {
+                !is_excluded(*index) && self.are_duplicates(transaction, other, matching)
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use chrono::NaiveDate;
+
+    fn transaction(day: u32, amount: i64, note: &str) -> Transaction {
+        let mut transaction = Transaction::new();
+        transaction.date = NaiveDate::from_ymd_opt(2023, 2, day).unwrap();
+        transaction.amount = Money::from_minor_units(amount);
+        transaction.category_name = "Nourriture".to_string();
+        transaction.note = Some(note.to_string());
+        transaction
+    }
+
+    #[test]
+    fn compare_transactions_with_key_and_date_tolerance() {
+        let mut duplicate_detection = DuplicateDetection::default();
+        let lunch = transaction(6, -790, "Migros");
+        assert!(duplicate_detection.are_duplicates(
+            &lunch,
+            &transaction(6, -790, " migros "),
+            NameMatching::default()
+        ));
+        assert!(!duplicate_detection.are_duplicates(
+            &lunch,
+            &transaction(7, -790, "Migros"),
+            NameMatching::default()
+        ));
+        assert!(!duplicate_detection.are_duplicates(
+            &lunch,
+            &transaction(6, -790, "Coop"),
+            NameMatching::default()
+        ));
+
+        duplicate_detection.date_tolerance_days = 2;
+        duplicate_detection.key.note = false;
+        let transactions = [
+            transaction(9, -790, "Migros"),
+            transaction(4, -1055, "Migros"),
+            transaction(8, -790, "Coop"),
+        ];
+        assert_eq!(
+            duplicate_detection.find_duplicate(
+                &lunch,
+                transactions.iter().enumerate(),
+                NameMatching::default(),
+                |_| false
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            duplicate_detection.find_duplicate(
+                &lunch,
+                transactions.iter().enumerate(),
+                NameMatching::default(),
+                |index| index == 2
+            ),
+            None
+        );
+    }
+}
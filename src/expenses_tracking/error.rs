@@ -0,0 +1,215 @@
+use chrono::NaiveDate;
+use std::error::Error;
+use std::fmt;
+
+use crate::currency::Currency;
+use crate::import_report::RejectionReason;
+use crate::transaction::TransactionId;
+
+/// The errors returned by the expense tracker.
+///
+/// Errors coming from files or external crates keep their message only, such that errors can be
+/// cloned and compared.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpenseTrackerError {
+    /// No category with this name exists.
+    UnknownCategory(String),
+    /// No sub-category with this name exists in the category.
+    UnknownSubCategory {
+        category_name: String,
+        subcategory_name: String,
+    },
+    /// A transaction has no sub-category although its category has some.
+    MissingSubCategory { category_name: String },
+    /// A name cannot be used for a category or sub-category, e.g. because it contains the
+    /// separator of paths of categories.
+    InvalidCategoryName(String),
+    /// A category with this name already exists.
+    DuplicateCategory(String),
+    /// A sub-category with this name already exists in the category.
+    DuplicateSubCategory {
+        category_name: String,
+        subcategory_name: String,
+    },
+    /// A category or sub-category cannot be deleted because transactions use it.
+    CategoryInUse {
+        category_name: String,
+        subcategory_name: Option<String>,
+        n_transactions: usize,
+    },
+    /// A transaction with this ID already exists.
+    DuplicateTransaction(TransactionId),
+    /// No transaction with this ID exists.
+    UnknownTransaction(TransactionId),
+    /// No account with this name exists.
+    UnknownAccount(String),
+    /// An account with this name already exists.
+    DuplicateAccount(String),
+    /// A transfer does not link two different accounts, or has a category.
+    InvalidTransfer(String),
+    /// The lines of a split transaction do not add up to its amount, or it has a category.
+    InvalidSplit(String),
+    /// A transaction cannot be changed because it is part of a reconciled statement.
+    ReconciledTransaction(TransactionId),
+    /// A transaction cannot be added to an account on a date it was already reconciled for.
+    ReconciledPeriod {
+        account_name: String,
+        reconciled_until: NaiveDate,
+    },
+    /// An account cannot be reconciled with a statement, e.g. because their balances differ.
+    Reconciliation(String),
+    /// A CSV file could not be read or written.
+    CsvIo(String),
+    /// A value such as a date or an amount could not be parsed.
+    Parse(String),
+    /// The configuration is invalid, or its file could not be read or written.
+    Config(String),
+    /// The storage backend could not read or write data.
+    Storage(String),
+    /// An entry of the audit journal was modified, removed or inserted after being written.
+    TamperedJournal { line: usize, reason: String },
+    /// Replaying a journal does not give the data it is enabled on, e.g. because it belongs to
+    /// another project or misses some changes.
+    JournalMismatch(String),
+    /// An import in strict mode was aborted because of an invalid row.
+    ImportAborted {
+        line: u64,
+        reason: Box<RejectionReason>,
+    },
+    /// A sum of amounts, such as the total of a category or the balance of an account, does not
+    /// fit in an amount.
+    AmountOutOfRange(String),
+    /// Amounts in different currencies would need to be added together.
+    MixedCurrencies {
+        currency: Currency,
+        other_currencies: Vec<Currency>,
+    },
+    /// No exchange rate exists between two currencies on or before a date.
+    MissingExchangeRate {
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    },
+}
+
+impl fmt::Display for ExpenseTrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpenseTrackerError::UnknownCategory(category_name) => {
+                write!(f, "Invalid category '{category_name}'")
+            }
+            ExpenseTrackerError::UnknownSubCategory {
+                category_name,
+                subcategory_name,
+            } => write!(
+                f,
+                "Sub-category '{subcategory_name}' does not exist in category '{category_name}'"
+            ),
+            ExpenseTrackerError::MissingSubCategory { category_name } => write!(
+                f,
+                "No sub-category set although the category '{category_name}' has some"
+            ),
+            ExpenseTrackerError::InvalidCategoryName(name) => write!(
+                f,
+                "Invalid name '{name}' for a category, which cannot be empty or contain '{}'",
+                crate::transaction::PATH_SEPARATOR
+            ),
+            ExpenseTrackerError::DuplicateCategory(category_name) => {
+                write!(f, "The category '{category_name}' already exists")
+            }
+            ExpenseTrackerError::DuplicateSubCategory {
+                category_name,
+                subcategory_name,
+            } => write!(
+                f,
+                "The sub-category '{subcategory_name}' already exists in category \
+                 '{category_name}'"
+            ),
+            ExpenseTrackerError::CategoryInUse {
+                category_name,
+                subcategory_name: None,
+                n_transactions,
+            } => write!(
+                f,
+                "The category '{category_name}' is used by {n_transactions} transactions"
+            ),
+            ExpenseTrackerError::CategoryInUse {
+                category_name,
+                subcategory_name: Some(subcategory_name),
+                n_transactions,
+            } => write!(
+                f,
+                "The sub-category '{subcategory_name}' of category '{category_name}' is used by \
+                 {n_transactions} transactions"
+            ),
+            ExpenseTrackerError::DuplicateTransaction(id) => {
+                write!(f, "A transaction with ID {id} already exists")
+            }
+            ExpenseTrackerError::UnknownTransaction(id) => {
+                write!(f, "No transaction with ID {id} exists")
+            }
+            ExpenseTrackerError::UnknownAccount(account_name) => {
+                write!(f, "Invalid account '{account_name}'")
+            }
+            ExpenseTrackerError::DuplicateAccount(account_name) => {
+                write!(f, "The account '{account_name}' already exists")
+            }
+            ExpenseTrackerError::InvalidTransfer(reason) => write!(f, "Invalid transfer: {reason}"),
+            ExpenseTrackerError::InvalidSplit(reason) => {
+                write!(f, "Invalid split transaction: {reason}")
+            }
+            ExpenseTrackerError::ReconciledTransaction(id) => {
+                write!(
+                    f,
+                    "The transaction {id} is reconciled and cannot be changed"
+                )
+            }
+            ExpenseTrackerError::ReconciledPeriod {
+                account_name,
+                reconciled_until,
+            } => write!(
+                f,
+                "The account '{account_name}' is reconciled until {reconciled_until}"
+            ),
+            ExpenseTrackerError::Reconciliation(reason) => {
+                write!(f, "Reconciliation failed: {reason}")
+            }
+            ExpenseTrackerError::CsvIo(message) => write!(f, "CSV error: {message}"),
+            ExpenseTrackerError::Parse(message) => write!(f, "Parse error: {message}"),
+            ExpenseTrackerError::Config(message) => write!(f, "Config error: {message}"),
+            ExpenseTrackerError::Storage(message) => write!(f, "Storage error: {message}"),
+            ExpenseTrackerError::TamperedJournal { line, reason } => {
+                write!(f, "The journal was tampered with at line {line}: {reason}")
+            }
+            ExpenseTrackerError::JournalMismatch(file_path) => write!(
+                f,
+                "Replaying the journal '{file_path}' does not give the current data"
+            ),
+            ExpenseTrackerError::ImportAborted { line, reason } => {
+                write!(f, "Import aborted at line {line}: {reason}")
+            }
+            ExpenseTrackerError::AmountOutOfRange(sum_name) => {
+                write!(f, "The {sum_name} is out of range")
+            }
+            ExpenseTrackerError::MixedCurrencies {
+                currency,
+                other_currencies,
+            } => {
+                let other_currencies: Vec<&str> =
+                    other_currencies.iter().map(Currency::code).collect();
+                write!(
+                    f,
+                    "Cannot compute a total in {currency} because some amounts are in {}",
+                    other_currencies.join(", ")
+                )
+            }
+            ExpenseTrackerError::MissingExchangeRate { from, to, date } => write!(
+                f,
+                "No exchange rate from {from} to {to} on or before {}",
+                date.format("%d.%m.%Y")
+            ),
+        }
+    }
+}
+
+impl Error for ExpenseTrackerError {}
@@ -0,0 +1,352 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use crate::currency::Currency;
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+
+/// The value of one unit of `from` expressed in `to`, valid from a given date on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub date: NaiveDate,
+    pub from: Currency,
+    pub to: Currency,
+    pub rate: f64,
+}
+
+/// The way an exchange rate is stored in a CSV file, e.g. `01.02.2023,EUR,CHF,0.9854`.
+#[derive(Debug, Deserialize)]
+struct ExchangeRateCsv {
+    date: String,
+    from: String,
+    to: String,
+    rate: String,
+}
+
+impl TryFrom<ExchangeRateCsv> for ExchangeRate {
+    type Error = Box<dyn Error>;
+
+    fn try_from(rate_csv: ExchangeRateCsv) -> Result<Self, Self::Error> {
+        let rate: f64 = rate_csv
+            .rate
+            .trim()
+            .parse()
+            .map_err(|e| format!("Failed to parse exchange rate '{}': {e}", rate_csv.rate))?;
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(format!("Exchange rate must be strictly positive, got {rate}").into());
+        }
+
+        Ok(ExchangeRate {
+            date: NaiveDate::parse_from_str(&rate_csv.date, "%d.%m.%Y")
+                .map_err(|e| format!("Failed to parse date of exchange rate: {e}"))?,
+            from: rate_csv.from.parse()?,
+            to: rate_csv.to.parse()?,
+            rate,
+        })
+    }
+}
+
+/// The conversion of an amount from one currency to another, which records the exchange rate that
+/// was used such that it can be audited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub original_amount: Money,
+    pub original_currency: Currency,
+    pub amount: Money,
+    pub currency: Currency,
+    /// The exchange rate used for the conversion, or `None` if no conversion was needed.
+    pub rate_used: Option<ExchangeRate>,
+    /// True if `rate_used` goes from `currency` to `original_currency` and was thus inverted.
+    pub is_inverted: bool,
+}
+
+/// A total converted into a single currency, along with the conversions it is made of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertedTotal {
+    pub currency: Currency,
+    pub total: Money,
+    pub conversions: Vec<Conversion>,
+}
+
+/// A table of dated exchange rates between pairs of currencies.
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRateTable {
+    rates: BTreeMap<(Currency, Currency), BTreeMap<NaiveDate, f64>>,
+}
+
+impl ExchangeRateTable {
+    /// Creates an empty `ExchangeRateTable`.
+    pub fn new() -> Self {
+        ExchangeRateTable {
+            rates: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an exchange rate to the table, replacing any rate for the same currencies and date.
+    pub fn add_rate(&mut self, exchange_rate: ExchangeRate) {
+        self.rates
+            .entry((exchange_rate.from, exchange_rate.to))
+            .or_default()
+            .insert(exchange_rate.date, exchange_rate.rate);
+    }
+
+    /// Returns the number of exchange rates in the table.
+    pub fn len(&self) -> usize {
+        self.rates.values().map(|rates| rates.len()).sum()
+    }
+
+    /// Returns true if the table contains no exchange rate.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Loads exchange rates from a CSV file with the columns `date,from,to,rate`.
+    pub fn load_from_csv(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .from_path(file_path)
+            .map_err(|e| {
+                ExpenseTrackerError::CsvIo(format!("Failed to load the CSV of exchange rates: {e}"))
+            })?;
+
+        let mut table = ExchangeRateTable::new();
+        for record in rdr.deserialize() {
+            let rate_csv: ExchangeRateCsv = record.map_err(|e| {
+                ExpenseTrackerError::CsvIo(format!(
+                    "Failed to deserialize the CSV exchange rate: {e}"
+                ))
+            })?;
+            let exchange_rate = ExchangeRate::try_from(rate_csv)
+                .map_err(|e| ExpenseTrackerError::Parse(e.to_string()))?;
+            table.add_rate(exchange_rate);
+        }
+
+        Ok(table)
+    }
+
+    /// Loads exchange rates from a JSON file containing a list of `ExchangeRate` objects.
+    pub fn load_from_json(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let file = File::open(file_path).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to open the JSON of exchange rates: {e}"))
+        })?;
+        let reader = std::io::BufReader::new(file);
+        let exchange_rates: Vec<ExchangeRate> = serde_json::from_reader(reader).map_err(|e| {
+            ExpenseTrackerError::Config(format!(
+                "Failed to deserialize the JSON of exchange rates: {e}"
+            ))
+        })?;
+
+        let mut table = ExchangeRateTable::new();
+        for exchange_rate in exchange_rates {
+            table.add_rate(exchange_rate);
+        }
+
+        Ok(table)
+    }
+
+    /// Loads exchange rates from a CSV or a JSON file depending on its extension.
+    pub fn load_from_file(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        match file_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("csv") => Self::load_from_csv(file_path),
+            Some("json") => Self::load_from_json(file_path),
+            _ => Err(ExpenseTrackerError::Config(format!(
+                "Exchange rates must be stored in a .csv or a .json file: {}",
+                file_path.display()
+            ))),
+        }
+    }
+
+    /// Returns the exchange rate from `from` to `to` with the closest date on or before `date`.
+    ///
+    /// The returned boolean is true if the rate was stored the other way around, from `to` to
+    /// `from`, in which case it must be inverted before use.
+    pub fn find_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> Option<(ExchangeRate, bool)> {
+        let closest_rate = |from: Currency, to: Currency| {
+            self.rates
+                .get(&(from, to))
+                .and_then(|rates| rates.range(..=date).next_back())
+                .map(|(date, rate)| ExchangeRate {
+                    date: *date,
+                    from,
+                    to,
+                    rate: *rate,
+                })
+        };
+
+        // A rate stored in the requested direction is preferred over an inverted one, unless the
+        // inverted one is more recent
+        match (closest_rate(from, to), closest_rate(to, from)) {
+            (Some(direct), Some(inverse)) if inverse.date > direct.date => Some((inverse, true)),
+            (Some(direct), _) => Some((direct, false)),
+            (None, Some(inverse)) => Some((inverse, true)),
+            (None, None) => None,
+        }
+    }
+
+    /// Converts an amount to another currency using the exchange rate of the closest earlier date.
+    pub fn convert(
+        &self,
+        amount: Money,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> Result<Conversion, ExpenseTrackerError> {
+        if from == to {
+            return Ok(Conversion {
+                original_amount: amount,
+                original_currency: from,
+                amount,
+                currency: to,
+                rate_used: None,
+                is_inverted: false,
+            });
+        }
+
+        let (exchange_rate, is_inverted) = self
+            .find_rate(from, to, date)
+            .ok_or(ExpenseTrackerError::MissingExchangeRate { from, to, date })?;
+
+        let rate = if is_inverted {
+            1.0 / exchange_rate.rate
+        } else {
+            exchange_rate.rate
+        };
+        let converted_minor_units = (amount.minor_units() as f64 * rate).round() as i64;
+
+        Ok(Conversion {
+            original_amount: amount,
+            original_currency: from,
+            amount: Money::from_minor_units(converted_minor_units),
+            currency: to,
+            rate_used: Some(exchange_rate),
+            is_inverted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn date(day: u32, month: u32, year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn convert_with_closest_earlier_rate() {
+        let mut table = ExchangeRateTable::new();
+        table.add_rate(ExchangeRate {
+            date: date(1, 1, 2023),
+            from: Currency::EUR,
+            to: Currency::CHF,
+            rate: 0.98,
+        });
+        table.add_rate(ExchangeRate {
+            date: date(1, 3, 2023),
+            from: Currency::EUR,
+            to: Currency::CHF,
+            rate: 1.02,
+        });
+
+        let conversion = table
+            .convert(
+                Money::from_minor_units(-10000),
+                Currency::EUR,
+                Currency::CHF,
+                date(15, 2, 2023),
+            )
+            .unwrap();
+        assert_eq!(conversion.amount, Money::from_minor_units(-9800));
+        assert_eq!(conversion.rate_used.unwrap().date, date(1, 1, 2023));
+
+        let conversion = table
+            .convert(
+                Money::from_minor_units(10000),
+                Currency::EUR,
+                Currency::CHF,
+                date(1, 3, 2023),
+            )
+            .unwrap();
+        assert_eq!(conversion.amount, Money::from_minor_units(10200));
+    }
+
+    #[test]
+    fn convert_with_inverted_rate() {
+        let mut table = ExchangeRateTable::new();
+        table.add_rate(ExchangeRate {
+            date: date(1, 1, 2023),
+            from: Currency::CHF,
+            to: Currency::EUR,
+            rate: 1.25,
+        });
+
+        let conversion = table
+            .convert(
+                Money::from_minor_units(10000),
+                Currency::EUR,
+                Currency::CHF,
+                date(2, 1, 2023),
+            )
+            .unwrap();
+        assert!(conversion.is_inverted);
+        assert_eq!(conversion.amount, Money::from_minor_units(8000));
+    }
+
+    #[test]
+    fn convert_without_rate() {
+        let table = ExchangeRateTable::new();
+        assert_eq!(
+            table.convert(
+                Money::from_minor_units(100),
+                Currency::EUR,
+                Currency::CHF,
+                date(1, 1, 2023)
+            ),
+            Err(ExpenseTrackerError::MissingExchangeRate {
+                from: Currency::EUR,
+                to: Currency::CHF,
+                date: date(1, 1, 2023)
+            })
+        );
+
+        // No rate is needed to convert to the same currency
+        let conversion = table
+            .convert(
+                Money::from_minor_units(100),
+                Currency::CHF,
+                Currency::CHF,
+                date(1, 1, 2023),
+            )
+            .unwrap();
+        assert_eq!(conversion.amount, Money::from_minor_units(100));
+        assert!(conversion.rate_used.is_none());
+    }
+
+    #[test]
+    fn load_csv_and_json_rates() {
+        let csv_table =
+            ExchangeRateTable::load_from_file(&PathBuf::from("test_data/exchange_rates.csv"))
+                .unwrap();
+        let json_table =
+            ExchangeRateTable::load_from_file(&PathBuf::from("test_data/exchange_rates.json"))
+                .unwrap();
+        assert_eq!(csv_table.len(), 3);
+        assert_eq!(
+            csv_table.find_rate(Currency::EUR, Currency::CHF, date(5, 2, 2023)),
+            json_table.find_rate(Currency::EUR, Currency::CHF, date(5, 2, 2023))
+        );
+    }
+}
@@ -1,62 +1,619 @@
-use chrono::NaiveDate;
-use log::{debug, info, trace};
-use std::error::Error;
-use std::{collections::BTreeSet, path::PathBuf};
+use chrono::{Days, NaiveDate};
+use log::{debug, info, trace, warn};
+use std::borrow::Cow;
+use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
 
-use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
+use crate::account::{Account, RunningBalance};
+use crate::category_totals::CategoryTotal;
+use crate::classifier::{classifier_path, CategoryClassifier, CategorySuggestion};
+use crate::currency::{Currency, Totals};
+use crate::duplicates::{DuplicateDetection, DuplicatePolicy};
+use crate::error::ExpenseTrackerError;
+use crate::exchange_rate::{Conversion, ConvertedTotal, ExchangeRateTable};
+use crate::export::ExportOptions;
+use crate::history::{Change, History};
+use crate::import_profile::{ImportProfile, DEFAULT_PROFILE_NAME};
+use crate::import_report::{
+    AutoFix, DuplicateMatch, ImportEntry, ImportMode, ImportOutcome, ImportReport, RejectionReason,
+    TransferMatch,
+};
+use crate::journal::{read_journal, Journal, SNAPSHOT_OPERATION};
+use crate::money::Money;
+use crate::name_matching::NameMatching;
+use crate::project_file::{read_project_file, write_project_file, ProjectFile};
+use crate::reconciliation::Reconciliation;
+use crate::rules::{CategorizationRule, RuleEngine, RuleMatch};
+use crate::store::{MemoryStore, TransactionStore, Transactions};
+use crate::transaction::{
+    join_path, split_path, Category, ClearedState, SubCategory, Transaction, TransactionId,
+    PATH_SEPARATOR,
+};
+use crate::transfers::{transfer_between, TransferMatching};
 
-use crate::transaction::{AsSubCategory, Category, SubCategory, Transaction, TransactionCsv};
+/// What to do with the transactions of a category or sub-category which is deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// The deletion fails if any transaction uses the category or sub-category.
+    Refuse,
+    /// The transactions are moved to another category, and sub-category if set.
+    Reassign {
+        category_name: String,
+        subcategory_name: Option<String>,
+    },
+}
+
+/// Splits a category, which may be a whole path such as "voyages/nourriture/restaurant", and the
+/// path of a sub-category into the name of the top-level category and the path below it.
+fn split_category_path(
+    category_name: &str,
+    subcategory_path: Option<&str>,
+) -> (String, Option<String>) {
+    let mut names = split_path(category_name);
+    let category_name = if names.is_empty() {
+        category_name.trim()
+    } else {
+        names.remove(0)
+    };
+    names.extend(subcategory_path.map(split_path).unwrap_or_default());
+    let subcategory_path = (!names.is_empty()).then(|| join_path(names));
+    (category_name.to_string(), subcategory_path)
+}
+
+/// Returns the display names of the levels below the category of a transaction, or `None` if the
+/// transaction does not belong to the category.
+///
+/// Levels which do not exist in the category keep the name used by the transaction.
+fn subcategory_names_in(
+    transaction: &Transaction,
+    category: &Category,
+    matching: NameMatching,
+) -> Option<Vec<String>> {
+    let (category_name, subcategory_path) = split_category_path(
+        &transaction.category_name,
+        transaction.subcategory_name.as_deref(),
+    );
+    if !category.is_named(&category_name, matching) {
+        return None;
+    }
+    let Some(subcategory_path) = subcategory_path else {
+        return Some(Vec::new());
+    };
+    Some(
+        match category.subcategory_path(&subcategory_path, matching) {
+            Some(subcategories) => subcategories
+                .iter()
+                .map(|subcategory| subcategory.name.clone())
+                .collect(),
+            None => split_path(&subcategory_path)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        },
+    )
+}
+
+/// Returns true if a transaction belongs to the category with the given name.
+fn is_in_category(transaction: &Transaction, category: &Category, matching: NameMatching) -> bool {
+    subcategory_names_in(transaction, category, matching).is_some()
+}
+
+/// Returns true if a transaction belongs to the sub-category of a category at
+/// `subcategory_names`, or to a sub-category below it.
+fn is_in_subcategory(
+    transaction: &Transaction,
+    category: &Category,
+    subcategory_names: &[String],
+    matching: NameMatching,
+) -> bool {
+    subcategory_names_in(transaction, category, matching)
+        .is_some_and(|names| names.starts_with(subcategory_names))
+}
+
+/// Checks that the path of a sub-category is valid in its category, and returns its display
+/// path.
+///
+/// Transactions belong to the last level of the tree, so a path which stops at a sub-category
+/// holding sub-categories is not valid.
+fn check_subcategory(
+    category: &Category,
+    subcategory_path: Option<&str>,
+    matching: NameMatching,
+) -> Result<Option<String>, ExpenseTrackerError> {
+    let Some(subcategory_path) = subcategory_path else {
+        // The None sub-category is valid as long as its associated category doesn't have
+        // sub-categories
+        if category.subcategories.is_empty() {
+            return Ok(None);
+        }
+        return Err(ExpenseTrackerError::MissingSubCategory {
+            category_name: category.name.clone(),
+        });
+    };
+
+    // The sub-category is valid as long as every level of its path exists in the tree of the
+    // category
+    let Some(subcategories) = category.subcategory_path(subcategory_path, matching) else {
+        return Err(ExpenseTrackerError::UnknownSubCategory {
+            category_name: category.name.clone(),
+            subcategory_name: subcategory_path.to_string(),
+        });
+    };
+    let display_path = join_path(
+        subcategories
+            .iter()
+            .map(|subcategory| subcategory.name.as_str()),
+    );
+    if subcategories
+        .last()
+        .is_some_and(|subcategory| !subcategory.subcategories.is_empty())
+    {
+        return Err(ExpenseTrackerError::MissingSubCategory {
+            category_name: join_path([category.name.as_str(), display_path.as_str()]),
+        });
+    }
+    Ok(Some(display_path))
+}
+
+/// Returns a transaction moved into a category and into the sub-category at the end of
+/// `subcategory_names`, checking that it is valid there.
+fn moved_transaction(
+    transaction: &Transaction,
+    category: &Category,
+    subcategory_names: &[String],
+    matching: NameMatching,
+) -> Result<Transaction, ExpenseTrackerError> {
+    let subcategory_path = (!subcategory_names.is_empty())
+        .then(|| join_path(subcategory_names.iter().map(String::as_str)));
+    Ok(Transaction {
+        category_name: category.name.clone(),
+        subcategory_name: check_subcategory(category, subcategory_path.as_deref(), matching)?,
+        ..transaction.clone()
+    })
+}
+
+/// Merges a tree of sub-categories into another one, merging the sub-categories with the same
+/// name along with their aliases.
+fn merge_subcategory_trees(
+    target_subcategories: &mut BTreeSet<SubCategory>,
+    source_subcategories: &BTreeSet<SubCategory>,
+    matching: NameMatching,
+) {
+    for subcategory in source_subcategories {
+        let existing_subcategory = target_subcategories
+            .iter()
+            .find(|existing| existing.is_named(&subcategory.name, matching))
+            .cloned();
+        match existing_subcategory {
+            None => {
+                target_subcategories.insert(subcategory.clone());
+            }
+            Some(mut existing_subcategory) => {
+                target_subcategories.remove(&existing_subcategory);
+                existing_subcategory
+                    .aliases
+                    .extend(subcategory.aliases.iter().cloned());
+                merge_subcategory_trees(
+                    &mut existing_subcategory.subcategories,
+                    &subcategory.subcategories,
+                    matching,
+                );
+                target_subcategories.insert(existing_subcategory);
+            }
+        }
+    }
+}
+
+/// Returns an error if a name cannot be used for a single level of categories.
+fn check_name(name: &str) -> Result<(), ExpenseTrackerError> {
+    if name.trim().is_empty() || name.contains(PATH_SEPARATOR) {
+        return Err(ExpenseTrackerError::InvalidCategoryName(name.to_string()));
+    }
+    Ok(())
+}
 
 /// A struct that deals with expense tracking.
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct ExpenseTracker {
-    pub valid_categories: BTreeSet<Category>,
-    #[serde(skip_serializing)]
-    #[serde(skip_deserializing)]
-    pub transactions: Vec<Transaction>,
+///
+/// Categories and transactions are kept in a `TransactionStore`, which only lives in memory by
+/// default.
+#[derive(Debug, Default)]
+pub struct ExpenseTracker<S = MemoryStore> {
+    /// Named descriptions of the CSV layouts exported by banks.
+    import_profiles: BTreeMap<String, ImportProfile>,
+    /// Currency of the transactions which do not specify one.
+    pub default_currency: Currency,
+    /// Currency in which reports are computed, which is the default currency if not set.
+    pub reporting_currency: Option<Currency>,
+    /// Exchange rates are stored in their own file, maintained by hand or imported.
+    pub exchange_rates: ExchangeRateTable,
+    /// How names of categories and sub-categories given by the user or imported are matched.
+    pub name_matching: NameMatching,
+    /// Rules assigning a category to the imported transactions which have none.
+    categorization_rules: Vec<CategorizationRule>,
+    /// Accounts the transactions are made with, along with their opening balances.
+    accounts: Vec<Account>,
+    /// Suggests categories learned from the categorized transactions, saved next to the project
+    /// file. It learns and forgets transactions as they are added, edited and removed.
+    classifier: CategoryClassifier,
+    /// How imported rows are compared to the existing transactions, and what to do with
+    /// duplicates.
+    pub duplicate_detection: DuplicateDetection,
+    /// How imported rows are paired with transactions of other accounts into transfers.
+    pub transfer_matching: TransferMatching,
+    /// Commands of the operations which can be undone and redone.
+    history: History,
+    /// Audit journal to which every change is appended, if enabled.
+    journal: Option<Journal>,
+    store: S,
 }
 
 impl ExpenseTracker {
-    /// Creates a new `ExpenseTracker` object.
+    /// Creates a new `ExpenseTracker` object which keeps its data in memory.
     pub fn new() -> Self {
+        ExpenseTracker::with_store(MemoryStore::new())
+    }
+
+    /// Loads categories, transactions and settings from a project file.
+    ///
+    /// Files written by older versions are upgraded in memory, and the parts they did not hold are
+    /// left empty. The file itself is only upgraded when saved. The classifier is loaded from its
+    /// own file next to the project file, or trained on the transactions if there is none.
+    pub fn load_info_from_file(file_path: &str) -> Result<Self, ExpenseTrackerError> {
+        let project_file = read_project_file(Path::new(file_path))?;
+        let classifier_path = classifier_path(Path::new(file_path));
+        let classifier = if classifier_path.exists() {
+            CategoryClassifier::load_from_file(&classifier_path)?
+        } else {
+            CategoryClassifier::train(project_file.transactions.iter())
+        };
+
+        Ok(ExpenseTracker {
+            import_profiles: project_file.import_profiles.into_owned(),
+            default_currency: project_file.default_currency,
+            reporting_currency: project_file.reporting_currency,
+            exchange_rates: ExchangeRateTable::new(),
+            name_matching: project_file.name_matching,
+            categorization_rules: project_file.categorization_rules.into_owned(),
+            accounts: project_file.accounts.into_owned(),
+            classifier,
+            duplicate_detection: project_file.duplicate_detection,
+            transfer_matching: project_file.transfer_matching,
+            history: History::new(),
+            journal: None,
+            store: MemoryStore::from_parts(
+                project_file.valid_categories.into_owned(),
+                project_file.transactions.into_owned(),
+            ),
+        })
+    }
+
+    /// Returns all the transactions in insertion order.
+    pub fn transactions(&self) -> &[Transaction] {
+        self.store.transactions()
+    }
+}
+
+impl<S: TransactionStore> ExpenseTracker<S> {
+    /// Rebuilds categories, transactions, import profiles and categorization rules in an empty
+    /// store from the changes written to a journal, after verifying its chain of hashes.
+    pub fn replay_journal(store: S, file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let mut expense_tracker = ExpenseTracker::with_store(store);
+        for entry in read_journal(file_path)? {
+            if entry.reverted {
+                expense_tracker.undo_change(&entry.change)?;
+            } else {
+                expense_tracker.redo_change(&entry.change)?;
+            }
+        }
+        Ok(expense_tracker)
+    }
+
+    /// Creates a new `ExpenseTracker` object which keeps its data in the given store.
+    pub fn with_store(store: S) -> Self {
         ExpenseTracker {
-            valid_categories: BTreeSet::new(),
-            transactions: Vec::new(),
+            import_profiles: BTreeMap::new(),
+            default_currency: Currency::default(),
+            reporting_currency: None,
+            exchange_rates: ExchangeRateTable::new(),
+            name_matching: NameMatching::default(),
+            categorization_rules: Vec::new(),
+            accounts: Vec::new(),
+            classifier: CategoryClassifier::new(),
+            duplicate_detection: DuplicateDetection::default(),
+            transfer_matching: TransferMatching::default(),
+            history: History::new(),
+            journal: None,
+            store,
+        }
+    }
+
+    /// Returns the store holding categories and transactions.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the valid categories along with their sub-categories.
+    pub fn categories(&self) -> &BTreeSet<Category> {
+        self.store.categories()
+    }
+
+    /// Returns the number of transactions.
+    pub fn n_transactions(&self) -> Result<usize, ExpenseTrackerError> {
+        self.store.n_transactions()
+    }
+
+    /// Returns an iterator over all the transactions in insertion order, which reads them from
+    /// the store a page at a time.
+    pub fn iter_transactions(&self) -> Transactions<'_, S> {
+        self.store.iter_transactions()
+    }
+
+    /// Returns the transaction at an index in insertion order, such as the existing transaction
+    /// of a `DuplicateMatch`.
+    pub fn transaction_at(
+        &self,
+        index: usize,
+    ) -> Result<Cow<'_, Transaction>, ExpenseTrackerError> {
+        self.store.transaction(index)
+    }
+
+    /// Returns the transactions whose date is between `start` and `end`, both included, in order
+    /// of date.
+    pub fn transactions_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        Ok(self
+            .store
+            .transactions_between(start, end)?
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .collect())
+    }
+
+    /// Returns the transactions of a category, and of one of its sub-categories if set, including
+    /// the ones of the sub-categories below it.
+    ///
+    /// The category may be a whole path such as "voyages/nourriture", and every level may be given
+    /// by one of its aliases. The matching lines of split transactions are returned after the
+    /// other transactions, with the ID of the split transaction.
+    pub fn transactions_in_category(
+        &self,
+        category_name: &str,
+        subcategory_path: Option<&str>,
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        let (category_name, subcategory_path) =
+            split_category_path(category_name, subcategory_path);
+        let Some(category) = self.get_category(&category_name) else {
+            return self
+                .store
+                .transactions_in_category(&category_name, subcategory_path.as_deref());
+        };
+        let subcategory_path = subcategory_path.map(|subcategory_path| {
+            match category.subcategory_path(&subcategory_path, self.name_matching) {
+                Some(subcategories) => join_path(
+                    subcategories
+                        .iter()
+                        .map(|subcategory| subcategory.name.as_str()),
+                ),
+                None => subcategory_path,
+            }
+        });
+        let mut transactions = self
+            .store
+            .transactions_in_category(&category.name, subcategory_path.as_deref())?;
+        // The store only looks at the category of whole transactions
+        let names: Vec<String> = subcategory_path
+            .as_deref()
+            .map(split_path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        for transaction in self.store.split_transactions()? {
+            transactions.extend(transaction.category_lines().into_iter().filter(|line| {
+                subcategory_names_in(line, category, self.name_matching)
+                    .is_some_and(|line_names| line_names.starts_with(&names))
+            }));
+        }
+        Ok(transactions)
+    }
+
+    /// Writes pending changes of the store to its durable location, if any.
+    pub fn flush(&mut self) -> Result<(), ExpenseTrackerError> {
+        self.store.flush()
+    }
+
+    /// Returns the commands of the operations which can be undone and redone.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Undoes the last operation, and returns its description or `None` if there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> Result<Option<String>, ExpenseTrackerError> {
+        let Some(command) = self.history.pop_undo() else {
+            return Ok(None);
+        };
+        let operation = format!("Undo: {}", command.description);
+        for (n_undone, change) in command.changes.iter().rev().enumerate() {
+            if let Err(e) = self.apply_change(&operation, change, true) {
+                // Changes undone so far are redone, such that the operation can be undone again
+                let n_changes = command.changes.len();
+                for change in &command.changes[n_changes - n_undone..] {
+                    if let Err(e) = self.apply_change(&operation, change, false) {
+                        warn!("Failed to redo a change of '{}': {e}", command.description);
+                    }
+                }
+                self.history.push_undo(command);
+                return Err(e);
+            }
+        }
+        info!("Undone: {}", command.description);
+        let description = command.description.clone();
+        self.history.push_redo(command);
+        Ok(Some(description))
+    }
+
+    /// Redoes the last undone operation, and returns its description or `None` if there is
+    /// nothing to redo.
+    pub fn redo(&mut self) -> Result<Option<String>, ExpenseTrackerError> {
+        let Some(command) = self.history.pop_redo() else {
+            return Ok(None);
+        };
+        let operation = format!("Redo: {}", command.description);
+        for (n_redone, change) in command.changes.iter().enumerate() {
+            if let Err(e) = self.apply_change(&operation, change, false) {
+                // Changes redone so far are undone, such that the operation can be redone again
+                for change in command.changes[..n_redone].iter().rev() {
+                    if let Err(e) = self.apply_change(&operation, change, true) {
+                        warn!("Failed to undo a change of '{}': {e}", command.description);
+                    }
+                }
+                self.history.push_redo(command);
+                return Err(e);
+            }
+        }
+        info!("Redone: {}", command.description);
+        let description = command.description.clone();
+        self.history.push_undo(command);
+        Ok(Some(description))
+    }
+
+    /// Replaces the history with one saved by `save_history_to_file`, such that operations done
+    /// before a restart can be undone.
+    ///
+    /// The history must have been saved along with the categories and transactions it applies
+    /// to, since its changes refer to transactions by index.
+    pub fn load_history_from_file(&mut self, file_path: &Path) -> Result<(), ExpenseTrackerError> {
+        self.history = History::load_from_file(file_path)?;
+        Ok(())
+    }
+
+    /// Saves the history, e.g. next to the project file at `history::history_path`.
+    pub fn save_history_to_file(&self, file_path: &Path) -> Result<(), ExpenseTrackerError> {
+        self.history.save_to_file(file_path)
+    }
+
+    /// Returns the audit journal, if enabled.
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Enables the audit journal, to which every change of categories, transactions, import
+    /// profiles and categorization rules is then appended, or disables it.
+    ///
+    /// When a journal without entries is enabled on an expense tracker which already holds data,
+    /// that data is first written to it as a snapshot, such that replaying the journal gives the
+    /// same categories, transactions and settings. A journal with entries is replayed first, and
+    /// fails with `JournalMismatch` if it does not give the current data.
+    pub fn set_journal(&mut self, journal: Option<Journal>) -> Result<(), ExpenseTrackerError> {
+        let mut journal = match journal {
+            Some(journal) => journal,
+            None => {
+                self.journal = None;
+                return Ok(());
+            }
+        };
+        if journal.is_empty() {
+            for change in self.snapshot_changes()? {
+                journal.append(SNAPSHOT_OPERATION, &change, false)?;
+            }
+        } else {
+            let replayed = ExpenseTracker::replay_journal(MemoryStore::new(), journal.file_path())?;
+            if replayed.snapshot()? != self.snapshot()? {
+                return Err(ExpenseTrackerError::JournalMismatch(
+                    journal.file_path().display().to_string(),
+                ));
+            }
+        }
+        self.journal = Some(journal);
+        Ok(())
+    }
+
+    /// Returns the changes of `snapshot_changes` as JSON, which compares every field of the
+    /// categories unlike their `PartialEq`.
+    fn snapshot(&self) -> Result<serde_json::Value, ExpenseTrackerError> {
+        serde_json::to_value(self.snapshot_changes()?).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to serialize a snapshot: {e}"))
+        })
+    }
+
+    /// Returns the changes which rebuild the current data in an empty expense tracker.
+    fn snapshot_changes(&self) -> Result<Vec<Change>, ExpenseTrackerError> {
+        let categories = self
+            .store
+            .categories()
+            .iter()
+            .map(|category| Change::SetCategory {
+                previous: None,
+                category: category.clone(),
+            });
+        let accounts = self.accounts.iter().cloned().map(Change::AddAccount);
+        let import_profiles =
+            self.import_profiles
+                .iter()
+                .map(|(name, import_profile)| Change::SetImportProfile {
+                    name: name.clone(),
+                    previous: None,
+                    import_profile: Box::new(import_profile.clone()),
+                });
+        let rules = self
+            .categorization_rules
+            .iter()
+            .cloned()
+            .map(Change::AddCategorizationRule);
+        let mut changes: Vec<Change> = categories
+            .chain(accounts)
+            .chain(import_profiles)
+            .chain(rules)
+            .collect();
+        for (index, transaction) in self.store.iter_transactions().enumerate() {
+            changes.push(Change::InsertTransaction {
+                index,
+                transaction: transaction?.into_owned(),
+            });
         }
+        Ok(changes)
     }
 
-    /// Returns an `Option` which contains a reference to a `Category` if it matches the argument.
+    /// Returns an `Option` which contains a reference to a `Category` if its name or one of its
+    /// aliases matches the argument.
     pub fn get_category(&self, category_name: &str) -> Option<&Category> {
-        self.valid_categories
+        self.store
+            .categories()
             .iter()
-            .find(|category| category.name == category_name.to_lowercase())
+            .find(|category| category.is_named(category_name, self.name_matching))
     }
 
-    /// Returns an `Option` which contains a reference to a `SubCategory` if it matches the argument.
+    /// Returns an `Option` which contains a reference to a `SubCategory` if every level of its
+    /// path, such as "nourriture/restaurant", matches the name or one of the aliases of a level
+    /// of the tree of the category.
     pub fn get_subcategory(
         &self,
-        subcategory_name: &str,
+        subcategory_path: &str,
         category_name: &str,
     ) -> Option<&SubCategory> {
-        match self.get_category(category_name) {
-            Some(category) => category
-                .subcategories
-                .iter()
-                .find(|subcategory| subcategory.name == subcategory_name.to_lowercase()),
-            None => None,
-        }
+        self.get_category(category_name)?
+            .subcategory(subcategory_path, self.name_matching)
     }
 
     /// Adds a valid category if it doesn't exist yet.
-    pub fn add_category(&mut self, category_name: &str, date_creation: Option<NaiveDate>) -> bool {
-        // Check whether a category with the same name exists (case insensitive)
+    pub fn add_category(
+        &mut self,
+        category_name: &str,
+        date_creation: Option<NaiveDate>,
+    ) -> Result<(), ExpenseTrackerError> {
+        check_name(category_name)?;
+        // Check whether a category with the same name or alias exists
         if let Some(found_category) = self.get_category(category_name) {
-            debug!(
-                "Cannot add Category as one with the same name already exists: {:?}",
-                found_category
-            );
-            return false;
+            return Err(ExpenseTrackerError::DuplicateCategory(
+                found_category.name.clone(),
+            ));
         }
 
         let category_date: NaiveDate = match date_creation {
@@ -66,46 +623,59 @@ impl ExpenseTracker {
         };
 
         let new_category = Category {
-            // All category names are lower case to avoid any confusion
-            name: category_name.to_lowercase(),
+            name: category_name.trim().to_string(),
+            aliases: BTreeSet::new(),
             date_added: category_date,
             subcategories: BTreeSet::new(),
         };
-        self.valid_categories.insert(new_category)
+        self.run_command(format!("Add category '{}'", new_category.name), |tracker| {
+            tracker.set_category(new_category)
+        })
     }
 
     /// Adds a valid sub-category associated with a category if it doesn't exist yet and if the
     /// category is valid.
+    ///
+    /// The sub-category may be given by a path such as "nourriture/restaurant", in which case
+    /// it is added below the sub-category at the start of the path, which must exist.
     pub fn add_subcategory(
         &mut self,
         category_name: &str,
-        subcategory_name: &str,
+        subcategory_path: &str,
         date_creation: Option<NaiveDate>,
-    ) -> Result<(), Box<dyn Error>> {
-        // We will need to clone the category in some cases
-        let cloned_category: Category;
+    ) -> Result<(), ExpenseTrackerError> {
+        // The category is modified and then replaced in the store
+        let mut cloned_category = self.existing_category(category_name)?;
 
-        match self.get_category(category_name) {
-            Some(category) => cloned_category = category.clone(),
-            None => {
-                return Err("Sub-category cannot be added because its category is invalid".into())
-            }
-        }
+        let mut names = split_path(subcategory_path);
+        let Some(subcategory_name) = names.pop() else {
+            return Err(ExpenseTrackerError::InvalidCategoryName(
+                subcategory_path.to_string(),
+            ));
+        };
+        let parent_names = if names.is_empty() {
+            Vec::new()
+        } else {
+            self.existing_subcategory(category_name, &join_path(names))?
+                .1
+        };
 
-        if self
-            .get_subcategory(subcategory_name, category_name)
-            .is_some()
-        {
-            return Err("The subcategory name already exists".into());
+        let mut new_path = parent_names.clone();
+        new_path.push(subcategory_name.to_string());
+        if let Some(found_subcategories) = cloned_category.subcategory_path(
+            &join_path(new_path.iter().map(String::as_str)),
+            self.name_matching,
+        ) {
+            return Err(ExpenseTrackerError::DuplicateSubCategory {
+                category_name: cloned_category.name.clone(),
+                subcategory_name: join_path(
+                    found_subcategories
+                        .iter()
+                        .map(|subcategory| subcategory.name.as_str()),
+                ),
+            });
         }
 
-        // We are in the situation where the sub-category needs to be added to the category.
-        // As the elements in a `BTreeSet` can by default not be modified, we need to remove
-        // the `Category` object, modify it, and insert it again
-
-        // Safe to unwrap because the category exists if the code arrives here
-        let mut extracted_category = self.valid_categories.take(&cloned_category).unwrap();
-
         let subcategory_date: NaiveDate = match date_creation {
             Some(date) => date,
             // If no date was used as an input, use today's date
@@ -113,383 +683,4481 @@ impl ExpenseTracker {
         };
 
         let new_subcategory = SubCategory {
-            name: subcategory_name.to_lowercase(),
+            name: subcategory_name.to_string(),
+            aliases: BTreeSet::new(),
+            subcategories: BTreeSet::new(),
             date_added: subcategory_date,
         };
+        cloned_category.edit_subcategories(&parent_names, |subcategories| {
+            subcategories.insert(new_subcategory)
+        });
 
-        extracted_category.subcategories.insert(new_subcategory);
-
-        self.valid_categories.insert(extracted_category);
-
-        Ok(())
+        self.run_command(
+            format!("Add sub-category '{}'", subcategory_path.trim()),
+            |tracker| tracker.set_category(cloned_category),
+        )
     }
 
-    /// Checks if a transaction is valid.
-    pub fn is_transaction_valid(&self, transaction: &Transaction) -> Result<(), Box<dyn Error>> {
-        let maybe_category = self.get_category(&transaction.category_name);
-        match maybe_category {
-            None => Err("Invalid category in transaction".into()),
-            Some(category) => {
-                match &transaction.subcategory_name {
-                    None => {
-                        // The None sub-category is valid as long as its associated category doesn't
-                        // have sub-categories
-                        if category.subcategories.is_empty() {
-                            return Ok(());
-                        }
-                        Err(
-                            "No sub-category set in transaction although the category has some"
-                                .into(),
-                        )
-                    }
-                    Some(subcategory_name) => {
-                        // The sub-category is valid as long as it's associated with its category
-                        // in the set of valid sub-categories
-                        if category
-                            .subcategories
-                            .contains(&subcategory_name.as_subcategory())
-                        {
-                            return Ok(());
-                        }
+    /// Adds a category and every level of a path such as "voyages/nourriture/restaurant" which
+    /// doesn't exist yet.
+    ///
+    /// Returns the number of added categories and sub-categories.
+    pub fn add_category_path(
+        &mut self,
+        path: &str,
+        date_creation: Option<NaiveDate>,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let (category_name, subcategory_path) = split_category_path(path, None);
+        self.run_command(format!("Add category path '{}'", path.trim()), |tracker| {
+            let created = tracker.add_missing_levels(
+                &category_name,
+                subcategory_path.as_deref(),
+                date_creation,
+            )?;
+            Ok(created.len())
+        })
+    }
 
-                        if category
-                            .subcategories
-                            .iter()
-                            .any(|subcategory| subcategory.name == subcategory_name.to_lowercase())
-                        {
-                            return Ok(());
-                        }
+    /// Adds the category and the levels of the path of a sub-category which don't exist yet, and
+    /// returns what was created.
+    fn add_missing_levels(
+        &mut self,
+        category_name: &str,
+        subcategory_path: Option<&str>,
+        date_creation: Option<NaiveDate>,
+    ) -> Result<Vec<AutoFix>, ExpenseTrackerError> {
+        let mut created = Vec::new();
+        if self.get_category(category_name).is_none() {
+            self.add_category(category_name, date_creation)?;
+            created.push(AutoFix::CategoryCreated(category_name.trim().to_string()));
+        }
 
-                        Err("Sub-category set in transaction does not exist in category".into())
-                    }
-                }
+        let display_category_name = self.existing_category(category_name)?.name;
+        let names = subcategory_path.map(split_path).unwrap_or_default();
+        for n_levels in 1..=names.len() {
+            let path = join_path(names[..n_levels].iter().copied());
+            if self.get_subcategory(&path, category_name).is_none() {
+                self.add_subcategory(category_name, &path, date_creation)?;
+                created.push(AutoFix::SubCategoryCreated {
+                    category_name: display_category_name.clone(),
+                    subcategory_name: path,
+                });
             }
         }
+        Ok(created)
     }
 
-    /// Adds a given transaction to the expense tracker if required conditions are met.
-    pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>> {
-        // Only add the transaction if its category is valid
-        self.is_transaction_valid(&transaction)?;
+    /// Adds an alias to a category, under which it is also found, e.g. the name used by the
+    /// exports of a bank.
+    pub fn add_category_alias(
+        &mut self,
+        category_name: &str,
+        alias: &str,
+    ) -> Result<(), ExpenseTrackerError> {
+        check_name(alias)?;
+        let mut category = self.existing_category(category_name)?;
+        if let Some(found_category) = self.get_category(alias) {
+            if found_category.name == category.name {
+                return Ok(());
+            }
+            return Err(ExpenseTrackerError::DuplicateCategory(
+                found_category.name.clone(),
+            ));
+        }
 
-        self.transactions.push(transaction);
-        Ok(())
+        category.aliases.insert(alias.trim().to_string());
+        self.run_command(
+            format!(
+                "Add alias '{}' to category '{}'",
+                alias.trim(),
+                category.name
+            ),
+            |tracker| tracker.set_category(category),
+        )
     }
 
-    /// Load transactions from a CSV and generate an expense tracker.
-    pub fn load_transactions_from_file(
+    /// Adds an alias to a sub-category, under which it is also found among the sub-categories at
+    /// the same level.
+    pub fn add_subcategory_alias(
         &mut self,
-        file_path: &PathBuf,
-        generate_categories_and_sub: bool,
-    ) -> Result<(), Box<dyn Error>> {
-        let mut rdr = csv::ReaderBuilder::new()
-            .has_headers(true)
-            .from_path(file_path)
-            .map_err(|e| format!("Failed to load the CSV of transactions: {e}"))?;
+        category_name: &str,
+        subcategory_path: &str,
+        alias: &str,
+    ) -> Result<(), ExpenseTrackerError> {
+        check_name(alias)?;
+        let (mut category, mut names) =
+            self.existing_subcategory(category_name, subcategory_path)?;
+        let subcategory_name = names.pop().unwrap_or_default();
 
-        let mut n_ignored_transactions: i32 = 0;
+        let mut alias_path = names.clone();
+        alias_path.push(alias.to_string());
+        if let Some(found_subcategory) = category.subcategory(
+            &join_path(alias_path.iter().map(String::as_str)),
+            self.name_matching,
+        ) {
+            if found_subcategory.name == subcategory_name {
+                return Ok(());
+            }
+            return Err(ExpenseTrackerError::DuplicateSubCategory {
+                category_name: category.name.clone(),
+                subcategory_name: found_subcategory.name.clone(),
+            });
+        }
 
-        // Iterate over each record in the CSV file
-        for record in rdr.deserialize() {
-            let transaction_csv: TransactionCsv =
-                record.map_err(|e| format!("Failed to deserialize the CSV transaction: {e}"))?;
-            let transaction = Transaction::try_from(transaction_csv)
-                .map_err(|e| format!("Failed to convert CSV transaction to transaction: {e}"))?;
-
-            if generate_categories_and_sub {
-                self.add_category(&transaction.category_name, Some(transaction.date));
-
-                if let Some(transaction_subcategory) = &transaction.subcategory_name {
-                    match self.add_subcategory(
-                        &transaction.category_name,
-                        transaction_subcategory,
-                        Some(transaction.date),
-                    ) {
-                        Ok(()) => (),
-                        Err(e) => debug!("{}", e),
-                    };
-                }
+        category.edit_subcategories(&names, |subcategories| {
+            let found_subcategory = subcategories
+                .iter()
+                .find(|subcategory| subcategory.name == subcategory_name)
+                .cloned();
+            if let Some(mut subcategory) = found_subcategory {
+                subcategories.remove(&subcategory);
+                subcategory.aliases.insert(alias.trim().to_string());
+                subcategories.insert(subcategory);
             }
+        });
+        self.run_command(
+            format!(
+                "Add alias '{}' to sub-category '{}'",
+                alias.trim(),
+                subcategory_path.trim()
+            ),
+            |tracker| tracker.set_category(category),
+        )
+    }
 
-            match self.add_transaction(transaction) {
-                Ok(()) => (),
-                Err(e) => {
-                    trace!("{}", e);
-                    n_ignored_transactions += 1;
-                }
-            };
+    /// Checks if a transaction is valid.
+    ///
+    /// The category of the transaction may be a whole path such as
+    /// "voyages/nourriture/restaurant", and every level must exist in the tree of categories.
+    pub fn is_transaction_valid(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<(), ExpenseTrackerError> {
+        if transaction.is_split() {
+            return self.validated_split(transaction.clone()).map(|_| ());
         }
+        self.display_names(transaction).map(|_| ())
+    }
 
-        info!(
-            "Number of valid transactions extracted from the CSV: {}",
-            self.transactions.len()
-        );
-        info!("Number of transactions ignored: {}", n_ignored_transactions);
+    /// Returns the display names of the category and sub-category of a transaction, which may
+    /// use aliases or other variants of the names, or an error if the transaction is invalid.
+    fn display_names(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<(String, Option<String>), ExpenseTrackerError> {
+        self.display_names_of(
+            &transaction.category_name,
+            transaction.subcategory_name.as_deref(),
+        )
+    }
 
-        Ok(())
+    /// Same as `display_names`, for a category and a sub-category given by their names.
+    fn display_names_of(
+        &self,
+        category_name: &str,
+        subcategory_name: Option<&str>,
+    ) -> Result<(String, Option<String>), ExpenseTrackerError> {
+        let (category_path_name, subcategory_path) =
+            split_category_path(category_name, subcategory_name);
+        match self.get_category(&category_path_name) {
+            None => Err(ExpenseTrackerError::UnknownCategory(
+                category_name.to_string(),
+            )),
+            Some(category) => Ok((
+                category.name.clone(),
+                check_subcategory(category, subcategory_path.as_deref(), self.name_matching)?,
+            )),
+        }
     }
 
-    pub fn write_transactions_to_file(&self, output_path: &PathBuf) -> Result<(), Box<dyn Error>> {
-        let mut writer = csv::Writer::from_path(output_path)
-            .map_err(|e| format!("Failed to open output CSV file: {e}"))?;
+    /// Renames a category and rewrites the transactions which use it.
+    ///
+    /// Returns the number of rewritten transactions.
+    pub fn rename_category(
+        &mut self,
+        category_name: &str,
+        new_category_name: &str,
+    ) -> Result<usize, ExpenseTrackerError> {
+        check_name(new_category_name)?;
+        let category = self.existing_category(category_name)?;
+        if let Some(existing_category) = self.get_category(new_category_name) {
+            // Changing the capitalization only is not a duplicate
+            if existing_category.name != category.name {
+                return Err(ExpenseTrackerError::DuplicateCategory(
+                    existing_category.name.clone(),
+                ));
+            }
+        }
 
-        writer
-            .write_record([
-                "date",
-                "amount_out",
-                "amount_in",
-                "category",
-                "subcategory",
-                "tag",
-                "note",
-            ])
-            .map_err(|e| format!("Failed to write header to output CSV file: {e}"))?;
+        let new_name = new_category_name.trim().to_string();
+        let matching = self.name_matching;
+        let rewrites = self.rewritten_transactions(|transaction| {
+            is_in_category(transaction, &category, matching).then(|| Transaction {
+                category_name: new_name.clone(),
+                ..transaction.clone()
+            })
+        })?;
 
-        for transaction in &self.transactions {
-            let amount_out = if transaction.amount < 0.0 {
-                format!("{:.2}", -transaction.amount)
-            } else {
-                String::new()
-            };
-            let amount_in = if transaction.amount >= 0.0 {
-                format!("{:.2}", transaction.amount)
-            } else {
-                String::new()
-            };
+        self.run_command(
+            format!("Rename category '{}' to '{new_name}'", category.name),
+            |tracker| {
+                tracker.remove_category(&category.name)?;
+                tracker.set_category(Category {
+                    name: new_name,
+                    ..category
+                })?;
+                tracker.apply_rewrites(rewrites)
+            },
+        )
+    }
 
-            writer
-                .write_record(&[
-                    transaction.date.format("%d.%m.%Y").to_string(),
-                    amount_out,
-                    amount_in,
-                    transaction.category_name.clone(),
-                    transaction
-                        .subcategory_name
-                        .clone()
-                        .unwrap_or("".to_string()),
-                    transaction.tag.clone().unwrap_or("".to_string()),
-                    transaction.note.clone().unwrap_or("".to_string()),
-                ])
-                .map_err(|e| format!("Failed to write a transaction to output CSV file: {e}"))?;
+    /// Moves the tree of sub-categories and the transactions of a category into another one, and
+    /// removes the first category.
+    ///
+    /// Sub-categories with the same name at the same level are merged. Fails without any change
+    /// if a transaction would become invalid, e.g. because it has no sub-category while the
+    /// merged category has some. Returns the number of rewritten transactions.
+    pub fn merge_categories(
+        &mut self,
+        source_category_name: &str,
+        target_category_name: &str,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let source_category = self.existing_category(source_category_name)?;
+        let mut merged_category = self.existing_category(target_category_name)?;
+        if source_category.name == merged_category.name {
+            return Ok(0);
         }
 
-        // Flush the writer to make sure all data is written to the file
-        writer
-            .flush()
-            .map_err(|e| format!("Failed to flush output CSV file: {e}"))?;
+        let matching = self.name_matching;
+        merged_category.date_added = merged_category.date_added.min(source_category.date_added);
+        // The merged category is still found under the names of the source category
+        merged_category.aliases.insert(source_category.name.clone());
+        merged_category
+            .aliases
+            .extend(source_category.aliases.iter().cloned());
+        merge_subcategory_trees(
+            &mut merged_category.subcategories,
+            &source_category.subcategories,
+            matching,
+        );
 
-        Ok(())
+        let rewrites = self.try_rewritten_transactions(|transaction| {
+            if let Some(names) = subcategory_names_in(transaction, &source_category, matching) {
+                moved_transaction(transaction, &merged_category, &names, matching).map(Some)
+            } else if let Some(names) =
+                subcategory_names_in(transaction, &merged_category, matching)
+            {
+                // Transactions already in the target category may need a sub-category now
+                moved_transaction(transaction, &merged_category, &names, matching)?;
+                Ok(None)
+            } else {
+                Ok(None)
+            }
+        })?;
+
+        self.run_command(
+            format!(
+                "Merge category '{}' into '{}'",
+                source_category.name, merged_category.name
+            ),
+            |tracker| {
+                tracker.remove_category(&source_category.name)?;
+                tracker.set_category(merged_category)?;
+                tracker.apply_rewrites(rewrites)
+            },
+        )
     }
 
-    /// Loads categories and sub-categories from a file.
-    pub fn load_info_from_file(file_path: &str) -> Result<Self, Box<dyn Error>> {
-        let file = File::open(file_path)?;
-        let reader = std::io::BufReader::new(file);
-        let expense_tracker: ExpenseTracker = serde_json::from_reader(reader)?;
-        Ok(expense_tracker)
+    /// Deletes a category along with its tree of sub-categories.
+    ///
+    /// Transactions using the category are handled according to `orphan_policy`. Returns the
+    /// number of reassigned transactions.
+    pub fn delete_category(
+        &mut self,
+        category_name: &str,
+        orphan_policy: OrphanPolicy,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let category = self.existing_category(category_name)?;
+        let matching = self.name_matching;
+        let is_orphan =
+            |transaction: &Transaction| is_in_category(transaction, &category, matching);
+        let n_orphans = self.n_transactions_with_lines(is_orphan)?;
+
+        let rewrites = match orphan_policy {
+            _ if n_orphans == 0 => Vec::new(),
+            OrphanPolicy::Refuse => {
+                return Err(ExpenseTrackerError::CategoryInUse {
+                    category_name: category.name.clone(),
+                    subcategory_name: None,
+                    n_transactions: n_orphans,
+                })
+            }
+            OrphanPolicy::Reassign {
+                category_name: target_category_name,
+                subcategory_name: target_subcategory_name,
+            } => {
+                let target_category = self.existing_category(&target_category_name)?;
+                // The deleted category cannot hold its own transactions
+                if target_category.name == category.name {
+                    return Err(ExpenseTrackerError::UnknownCategory(target_category.name));
+                }
+                self.reassigned_transactions(
+                    is_orphan,
+                    &target_category,
+                    target_subcategory_name.as_deref(),
+                )?
+            }
+        };
+
+        self.run_command(format!("Delete category '{}'", category.name), |tracker| {
+            tracker.remove_category(&category.name)?;
+            tracker.apply_rewrites(rewrites)
+        })
     }
 
-    /// Loads categories and sub-categories from the transactions part of the expense tracker.
-    pub fn load_info_from_transactions(&mut self) {
-        let cloned_transactions = self.transactions.clone();
-        for transaction in cloned_transactions {
-            self.add_category(&transaction.category_name, Some(transaction.date));
-            if let Some(transaction_subcategory) = &transaction.subcategory_name {
-                // We can directly unwrap this function because we just made the category of the
-                // transaction valid, meaning that this should not error
-                self.add_subcategory(
-                    &transaction.category_name,
-                    transaction_subcategory,
-                    Some(transaction.date),
-                )
-                .unwrap();
+    /// Renames the sub-category at the end of a path and rewrites the transactions which use it
+    /// or a sub-category below it.
+    ///
+    /// Returns the number of rewritten transactions.
+    pub fn rename_subcategory(
+        &mut self,
+        category_name: &str,
+        subcategory_path: &str,
+        new_subcategory_name: &str,
+    ) -> Result<usize, ExpenseTrackerError> {
+        check_name(new_subcategory_name)?;
+        let (mut category, names) = self.existing_subcategory(category_name, subcategory_path)?;
+        let matching = self.name_matching;
+        let new_name = new_subcategory_name.trim().to_string();
+        let (subcategory_name, parent_names) = names
+            .split_last()
+            .expect("paths of existing sub-categories are not empty");
+
+        let mut new_names = parent_names.to_vec();
+        new_names.push(new_name.clone());
+        if let Some(existing) =
+            category.subcategory(&join_path(new_names.iter().map(String::as_str)), matching)
+        {
+            // Changing the capitalization only is not a duplicate
+            if &existing.name != subcategory_name {
+                return Err(ExpenseTrackerError::DuplicateSubCategory {
+                    category_name: category.name.clone(),
+                    subcategory_name: join_path(new_names.iter().map(String::as_str)),
+                });
             }
         }
+
+        let rewrites = self.rewritten_transactions(|transaction| {
+            let mut transaction_names = subcategory_names_in(transaction, &category, matching)?;
+            if !transaction_names.starts_with(&names) {
+                return None;
+            }
+            transaction_names[names.len() - 1] = new_name.clone();
+            Some(Transaction {
+                subcategory_name: Some(join_path(transaction_names.iter().map(String::as_str))),
+                ..transaction.clone()
+            })
+        })?;
+
+        category.edit_subcategories(parent_names, |subcategories| {
+            let found_subcategory = subcategories
+                .iter()
+                .find(|subcategory| &subcategory.name == subcategory_name)
+                .cloned();
+            if let Some(subcategory) = found_subcategory {
+                subcategories.remove(&subcategory);
+                subcategories.insert(SubCategory {
+                    name: new_name.clone(),
+                    ..subcategory
+                });
+            }
+        });
+        self.run_command(
+            format!(
+                "Rename sub-category '{}' to '{new_name}'",
+                subcategory_path.trim()
+            ),
+            |tracker| {
+                tracker.set_category(category)?;
+                tracker.apply_rewrites(rewrites)
+            },
+        )
     }
 
-    /// Save categories and sub-categories to a file.
-    pub fn save_info_to_file(&self, file_path: PathBuf) -> Result<(), Box<dyn Error>> {
-        let file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(file_path)
-            .map_err(|e| format!("Failed to create config file: {e}"))?;
-        let writer = std::io::BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, &self)
-            .map_err(|e| format!("Failed to write categories to config: {e}"))?;
-        Ok(())
+    /// Moves the tree of sub-categories and the transactions of a sub-category into another
+    /// sub-category of the same category, and removes the first sub-category.
+    ///
+    /// Both sub-categories are given by their path, and sub-categories with the same name at the
+    /// same level are merged. Fails without any change if a transaction would become invalid.
+    /// Returns the number of rewritten transactions.
+    pub fn merge_subcategories(
+        &mut self,
+        category_name: &str,
+        source_subcategory_path: &str,
+        target_subcategory_path: &str,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let (category, source_names) =
+            self.existing_subcategory(category_name, source_subcategory_path)?;
+        let (_, target_names) =
+            self.existing_subcategory(category_name, target_subcategory_path)?;
+        if source_names == target_names {
+            return Ok(0);
+        }
+        // A sub-category below the source one is removed along with it
+        if target_names.starts_with(&source_names) {
+            return Err(ExpenseTrackerError::UnknownSubCategory {
+                category_name: category.name,
+                subcategory_name: target_subcategory_path.to_string(),
+            });
+        }
+
+        let matching = self.name_matching;
+        let source_subcategory = category
+            .subcategory(
+                &join_path(source_names.iter().map(String::as_str)),
+                matching,
+            )
+            .cloned()
+            .expect("the source sub-category exists");
+        let mut merged_category = category.clone();
+        let (source_name, source_parent_names) = source_names
+            .split_last()
+            .expect("paths of existing sub-categories are not empty");
+        merged_category.edit_subcategories(source_parent_names, |subcategories| {
+            subcategories.retain(|subcategory| &subcategory.name != source_name)
+        });
+        let (target_name, target_parent_names) = target_names
+            .split_last()
+            .expect("paths of existing sub-categories are not empty");
+        merged_category.edit_subcategories(target_parent_names, |subcategories| {
+            let found_subcategory = subcategories
+                .iter()
+                .find(|subcategory| &subcategory.name == target_name)
+                .cloned();
+            if let Some(mut target_subcategory) = found_subcategory {
+                subcategories.remove(&target_subcategory);
+                // The target sub-category is still found under the names of the source one
+                target_subcategory
+                    .aliases
+                    .insert(source_subcategory.name.clone());
+                target_subcategory
+                    .aliases
+                    .extend(source_subcategory.aliases.iter().cloned());
+                merge_subcategory_trees(
+                    &mut target_subcategory.subcategories,
+                    &source_subcategory.subcategories,
+                    matching,
+                );
+                subcategories.insert(target_subcategory);
+            }
+        });
+
+        let rewrites = self.try_rewritten_transactions(|transaction| {
+            let Some(names) = subcategory_names_in(transaction, &category, matching) else {
+                return Ok(None);
+            };
+            if names.starts_with(&source_names) {
+                let mut new_names = target_names.clone();
+                new_names.extend_from_slice(&names[source_names.len()..]);
+                moved_transaction(transaction, &merged_category, &new_names, matching).map(Some)
+            } else {
+                // Transactions already in the target sub-category may need a sub-category now
+                moved_transaction(transaction, &merged_category, &names, matching)?;
+                Ok(None)
+            }
+        })?;
+
+        self.run_command(
+            format!(
+                "Merge sub-category '{}' into '{}'",
+                source_subcategory_path.trim(),
+                target_subcategory_path.trim()
+            ),
+            |tracker| {
+                tracker.set_category(merged_category)?;
+                tracker.apply_rewrites(rewrites)
+            },
+        )
     }
-}
 
-#[cfg(test)]
-mod tests {
-    // Import everything from the parent module
-    use super::*;
-    use chrono::NaiveDate;
+    /// Deletes the sub-category at the end of a path, along with the sub-categories below it.
+    ///
+    /// Transactions using the sub-category are handled according to `orphan_policy`. Returns the
+    /// number of reassigned transactions.
+    pub fn delete_subcategory(
+        &mut self,
+        category_name: &str,
+        subcategory_path: &str,
+        orphan_policy: OrphanPolicy,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let (mut category, names) = self.existing_subcategory(category_name, subcategory_path)?;
+        let matching = self.name_matching;
+        let deleted_from = category.clone();
+        let is_orphan = |transaction: &Transaction| {
+            is_in_subcategory(transaction, &deleted_from, &names, matching)
+        };
+        let n_orphans = self.n_transactions_with_lines(is_orphan)?;
+        let (subcategory_name, parent_names) = names
+            .split_last()
+            .expect("paths of existing sub-categories are not empty");
+        category.edit_subcategories(parent_names, |subcategories| {
+            subcategories.retain(|subcategory| &subcategory.name != subcategory_name)
+        });
 
-    impl Default for Transaction {
-        fn default() -> Self {
-            Self::new()
-        }
+        let rewrites = match orphan_policy {
+            _ if n_orphans == 0 => Vec::new(),
+            OrphanPolicy::Refuse => {
+                return Err(ExpenseTrackerError::CategoryInUse {
+                    category_name: category.name,
+                    subcategory_name: Some(join_path(names.iter().map(String::as_str))),
+                    n_transactions: n_orphans,
+                })
+            }
+            OrphanPolicy::Reassign {
+                category_name: target_category_name,
+                subcategory_name: target_subcategory_name,
+            } => {
+                // The target may be the category which loses the sub-category
+                let target_category = match self.get_category(&target_category_name) {
+                    Some(target_category) if target_category.name == category.name => {
+                        category.clone()
+                    }
+                    _ => self.existing_category(&target_category_name)?,
+                };
+                self.reassigned_transactions(
+                    is_orphan,
+                    &target_category,
+                    target_subcategory_name.as_deref(),
+                )?
+            }
+        };
+
+        self.run_command(
+            format!("Delete sub-category '{}'", subcategory_path.trim()),
+            |tracker| {
+                tracker.set_category(category)?;
+                tracker.apply_rewrites(rewrites)
+            },
+        )
     }
 
-    impl Transaction {
-        pub fn new() -> Transaction {
-            Transaction {
-                date: NaiveDate::default(),
-                amount: 0.,
-                category_name: String::new(),
-                subcategory_name: None,
-                tag: None,
-                note: None,
+    /// Returns the number of transactions with at least one category line for which `predicate`
+    /// returns `true`.
+    fn n_transactions_with_lines(
+        &self,
+        predicate: impl Fn(&Transaction) -> bool,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let mut n_transactions = 0;
+        for transaction in self.store.iter_transactions() {
+            if transaction?.category_lines().iter().any(&predicate) {
+                n_transactions += 1;
             }
         }
+        Ok(n_transactions)
     }
 
-    #[test]
-    #[should_panic(expected = "Sub-category cannot be added because its category is invalid")]
-    fn add_subcategory_invalid_category() {
-        let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", None);
-        expense_tracker
-            .add_subcategory("Transports", "Train", None)
+    /// Moves the orphan transactions, or lines of split transactions, into a category and the
+    /// sub-category at a path, which must be valid for them.
+    fn reassigned_transactions(
+        &self,
+        is_orphan: impl Fn(&Transaction) -> bool,
+        target_category: &Category,
+        target_subcategory_path: Option<&str>,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let subcategory_name =
+            check_subcategory(target_category, target_subcategory_path, self.name_matching)?;
+        self.rewritten_transactions(|transaction| {
+            is_orphan(transaction).then(|| Transaction {
+                category_name: target_category.name.clone(),
+                subcategory_name: subcategory_name.clone(),
+                ..transaction.clone()
+            })
+        })
+    }
+
+    /// Returns the totals of every category, each one including the totals of its tree of
+    /// sub-categories.
+    ///
+    /// Split transactions are counted through their lines. Transfers, and transactions which are
+    /// not valid in the current tree of categories, are left out.
+    pub fn category_totals(&self) -> Result<Vec<CategoryTotal>, ExpenseTrackerError> {
+        let mut category_totals: Vec<CategoryTotal> = self
+            .store
+            .categories()
+            .iter()
+            .map(CategoryTotal::from_category)
+            .collect();
+        for sum in self.store.category_sums()? {
+            let Ok((category_name, subcategory_path)) =
+                self.display_names_of(&sum.category_name, sum.subcategory_name.as_deref())
+            else {
+                continue;
+            };
+            let subcategory_names: Vec<&str> = subcategory_path
+                .as_deref()
+                .map(split_path)
+                .unwrap_or_default();
+            if let Some(category_total) = category_totals
+                .iter_mut()
+                .find(|category_total| category_total.name == category_name)
+            {
+                category_total.add(
+                    &subcategory_names,
+                    sum.total,
+                    sum.currency,
+                    sum.n_transactions,
+                )?;
+            }
+        }
+        Ok(category_totals)
+    }
+
+    /// Returns the totals of the category or sub-category at a path such as
+    /// "voyages/nourriture", including the totals of the sub-categories below it, or `None` if
+    /// no such category or sub-category exists.
+    pub fn category_total(&self, path: &str) -> Result<Option<CategoryTotal>, ExpenseTrackerError> {
+        let (category_name, subcategory_path) = split_category_path(path, None);
+        let Some(category) = self.get_category(&category_name) else {
+            return Ok(None);
+        };
+        let subcategory_names: Vec<&str> = match &subcategory_path {
+            None => Vec::new(),
+            Some(subcategory_path) => {
+                match category.subcategory_path(subcategory_path, self.name_matching) {
+                    Some(subcategories) => subcategories
+                        .iter()
+                        .map(|subcategory| subcategory.name.as_str())
+                        .collect(),
+                    None => return Ok(None),
+                }
+            }
+        };
+        Ok(self
+            .category_totals()?
+            .into_iter()
+            .find(|category_total| category_total.name == category.name)
+            .and_then(|category_total| category_total.get(&subcategory_names).cloned()))
+    }
+
+    /// Returns a copy of the category with the given name, or an error if it does not exist.
+    fn existing_category(&self, category_name: &str) -> Result<Category, ExpenseTrackerError> {
+        self.get_category(category_name)
+            .cloned()
+            .ok_or_else(|| ExpenseTrackerError::UnknownCategory(category_name.to_string()))
+    }
+
+    /// Returns a copy of a category and the display names along the path of one of its
+    /// sub-categories, or an error if either does not exist.
+    fn existing_subcategory(
+        &self,
+        category_name: &str,
+        subcategory_path: &str,
+    ) -> Result<(Category, Vec<String>), ExpenseTrackerError> {
+        let category = self.existing_category(category_name)?;
+        let names = match category.subcategory_path(subcategory_path, self.name_matching) {
+            Some(subcategories) => subcategories
+                .iter()
+                .map(|subcategory| subcategory.name.clone())
+                .collect(),
+            None => {
+                return Err(ExpenseTrackerError::UnknownSubCategory {
+                    category_name: category.name,
+                    subcategory_name: subcategory_path.to_string(),
+                })
+            }
+        };
+        Ok((category, names))
+    }
+
+    /// Returns the transactions changed by `rewrite` along with their index, leaving out the ones
+    /// for which it returns `None`.
+    ///
+    /// Split transactions are rewritten line by line, `rewrite` being called with the transaction
+    /// of each line.
+    fn rewritten_transactions(
+        &self,
+        rewrite: impl Fn(&Transaction) -> Option<Transaction>,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        self.try_rewritten_transactions(|transaction| Ok(rewrite(transaction)))
+    }
+
+    /// Same as `rewritten_transactions`, also failing on the first error returned by `rewrite`.
+    fn try_rewritten_transactions(
+        &self,
+        rewrite: impl Fn(&Transaction) -> Result<Option<Transaction>, ExpenseTrackerError>,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let mut rewrites = Vec::new();
+        for (index, transaction) in self.store.iter_transactions().enumerate() {
+            let transaction = transaction?;
+            let mut is_rewritten = false;
+            let mut lines = Vec::new();
+            for line in transaction.category_lines() {
+                match rewrite(&line)? {
+                    Some(new_line) => {
+                        is_rewritten = true;
+                        lines.push(new_line);
+                    }
+                    None => lines.push(line),
+                }
+            }
+            if is_rewritten {
+                rewrites.push((index, transaction.with_category_lines(lines)));
+            }
+        }
+        Ok(rewrites)
+    }
+
+    /// Replaces transactions in the store, and returns the number of replaced transactions.
+    fn apply_rewrites(
+        &mut self,
+        rewrites: Vec<(usize, Transaction)>,
+    ) -> Result<usize, ExpenseTrackerError> {
+        let n_rewrites = rewrites.len();
+        for (index, transaction) in rewrites {
+            self.replace_transaction(index, transaction)?;
+        }
+        Ok(n_rewrites)
+    }
+
+    /// Runs an operation as a command of the history, or as part of the command of the operation
+    /// calling it. The changes made by a failing operation are reverted.
+    fn run_command<T>(
+        &mut self,
+        description: String,
+        operation: impl FnOnce(&mut Self) -> Result<T, ExpenseTrackerError>,
+    ) -> Result<T, ExpenseTrackerError> {
+        self.history.begin(description);
+        let n_changes = self.history.n_pending_changes();
+        let result = operation(self);
+        if result.is_err() {
+            let operation = format!(
+                "Revert: {}",
+                self.history.pending_description().unwrap_or_default()
+            );
+            for change in self.history.take_pending_changes(n_changes).iter().rev() {
+                if let Err(e) = self.apply_change(&operation, change, true) {
+                    warn!("Failed to revert a change of a failed operation: {e}");
+                }
+            }
+        }
+        self.history.end();
+        result
+    }
+
+    /// Records a change made to the store in the history, and appends it to the journal.
+    fn record(&mut self, change: Change) -> Result<(), ExpenseTrackerError> {
+        self.history.record(change.clone());
+        if let Some(journal) = &mut self.journal {
+            let operation = self.history.pending_description().unwrap_or("Change");
+            journal.append(operation, &change, false)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts a change, or makes it again, without recording it in the history, and appends it
+    /// to the journal.
+    fn apply_change(
+        &mut self,
+        operation: &str,
+        change: &Change,
+        reverted: bool,
+    ) -> Result<(), ExpenseTrackerError> {
+        if reverted {
+            self.undo_change(change)?;
+        } else {
+            self.redo_change(change)?;
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.append(operation, change, reverted)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts a category in the store, replacing the one with the same name, and records the
+    /// change.
+    fn set_category(&mut self, category: Category) -> Result<(), ExpenseTrackerError> {
+        let category_name = category.name.to_lowercase();
+        let previous = self
+            .store
+            .categories()
+            .iter()
+            .find(|existing| existing.name.to_lowercase() == category_name)
+            .cloned();
+        self.store.set_category(category.clone())?;
+        self.record(Change::SetCategory { previous, category })
+    }
+
+    /// Removes a category from the store and records the change.
+    fn remove_category(&mut self, category_name: &str) -> Result<(), ExpenseTrackerError> {
+        if let Some(category) = self.store.remove_category(category_name)? {
+            self.record(Change::RemoveCategory(category))?;
+        }
+        Ok(())
+    }
+
+    /// Appends a transaction to the store and records the change.
+    fn push_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError> {
+        let index = self.store.n_transactions()?;
+        self.store.push_transaction(transaction.clone())?;
+        self.follow_in_classifier(None, Some(&transaction));
+        self.record(Change::InsertTransaction { index, transaction })
+    }
+
+    /// Replaces a transaction in the store, records the change and returns the previous one.
+    fn replace_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        let previous = self.store.replace_transaction(index, transaction.clone())?;
+        self.follow_in_classifier(Some(&previous), Some(&transaction));
+        self.record(Change::ReplaceTransaction {
+            index,
+            previous: Box::new(previous.clone()),
+            transaction: Box::new(transaction),
+        })?;
+        Ok(previous)
+    }
+
+    /// Removes a transaction from the store, records the change and returns it.
+    fn remove_transaction_at(&mut self, index: usize) -> Result<Transaction, ExpenseTrackerError> {
+        let transaction = self.store.remove_transaction(index)?;
+        self.follow_in_classifier(Some(&transaction), None);
+        self.record(Change::RemoveTransaction {
+            index,
+            transaction: transaction.clone(),
+        })?;
+        Ok(transaction)
+    }
+
+    /// Makes the classifier forget the previous value of a transaction and learn its new one,
+    /// such that it keeps having learned every categorized transaction.
+    fn follow_in_classifier(
+        &mut self,
+        previous: Option<&Transaction>,
+        transaction: Option<&Transaction>,
+    ) {
+        if let Some(previous) = previous {
+            self.classifier.forget(previous);
+        }
+        if let Some(transaction) = transaction {
+            self.classifier.learn(transaction);
+        }
+    }
+
+    /// Replaces an account, records the change and returns the previous one.
+    fn replace_account(&mut self, account: Account) -> Result<Account, ExpenseTrackerError> {
+        let previous = self.existing_account(&account.name)?.clone();
+        self.set_account_value(&previous, account.clone());
+        self.record(Change::ReplaceAccount {
+            previous: previous.clone(),
+            account,
+        })?;
+        Ok(previous)
+    }
+
+    /// Replaces the account equal to `current` by `account`, without recording anything.
+    fn set_account_value(&mut self, current: &Account, account: Account) {
+        if let Some(existing) = self
+            .accounts
+            .iter_mut()
+            .find(|existing| *existing == current)
+        {
+            *existing = account;
+        }
+    }
+
+    /// Reverts a change, without recording anything.
+    fn undo_change(&mut self, change: &Change) -> Result<(), ExpenseTrackerError> {
+        match change {
+            Change::SetCategory { previous, category } => {
+                self.store.remove_category(&category.name)?;
+                if let Some(previous) = previous {
+                    self.store.set_category(previous.clone())?;
+                }
+            }
+            Change::RemoveCategory(category) => self.store.set_category(category.clone())?,
+            Change::InsertTransaction { index, .. } => {
+                let transaction = self.store.remove_transaction(*index)?;
+                self.follow_in_classifier(Some(&transaction), None);
+            }
+            Change::ReplaceTransaction {
+                index, previous, ..
+            } => {
+                let transaction = self
+                    .store
+                    .replace_transaction(*index, previous.as_ref().clone())?;
+                self.follow_in_classifier(Some(&transaction), Some(previous));
+            }
+            Change::RemoveTransaction { index, transaction } => {
+                self.store.insert_transaction(*index, transaction.clone())?;
+                self.follow_in_classifier(None, Some(transaction));
+            }
+            Change::SetImportProfile { name, previous, .. } => match previous {
+                Some(previous) => {
+                    self.import_profiles
+                        .insert(name.clone(), previous.as_ref().clone());
+                }
+                None => {
+                    self.import_profiles.remove(name);
+                }
+            },
+            Change::AddCategorizationRule(rule) => {
+                if let Some(position) = self
+                    .categorization_rules
+                    .iter()
+                    .rposition(|existing| existing == rule)
+                {
+                    self.categorization_rules.remove(position);
+                }
+            }
+            Change::AddAccount(account) => {
+                if let Some(position) = self
+                    .accounts
+                    .iter()
+                    .rposition(|existing| existing == account)
+                {
+                    self.accounts.remove(position);
+                }
+            }
+            Change::ReplaceAccount { previous, account } => {
+                self.set_account_value(account, previous.clone())
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes a change again after it was reverted, without recording anything.
+    fn redo_change(&mut self, change: &Change) -> Result<(), ExpenseTrackerError> {
+        match change {
+            Change::SetCategory { category, .. } => self.store.set_category(category.clone())?,
+            Change::RemoveCategory(category) => {
+                self.store.remove_category(&category.name)?;
+            }
+            Change::InsertTransaction { index, transaction } => {
+                self.store.insert_transaction(*index, transaction.clone())?;
+                self.follow_in_classifier(None, Some(transaction));
+            }
+            Change::ReplaceTransaction {
+                index, transaction, ..
+            } => {
+                let previous = self
+                    .store
+                    .replace_transaction(*index, transaction.as_ref().clone())?;
+                self.follow_in_classifier(Some(&previous), Some(transaction));
+            }
+            Change::RemoveTransaction { index, .. } => {
+                let transaction = self.store.remove_transaction(*index)?;
+                self.follow_in_classifier(Some(&transaction), None);
+            }
+            Change::SetImportProfile {
+                name,
+                import_profile,
+                ..
+            } => {
+                self.import_profiles
+                    .insert(name.clone(), import_profile.as_ref().clone());
+            }
+            Change::AddCategorizationRule(rule) => self.categorization_rules.push(rule.clone()),
+            Change::AddAccount(account) => self.accounts.push(account.clone()),
+            Change::ReplaceAccount { previous, account } => {
+                self.set_account_value(previous, account.clone())
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the transaction with an ID.
+    pub fn get_transaction(
+        &self,
+        id: TransactionId,
+    ) -> Result<Option<Cow<'_, Transaction>>, ExpenseTrackerError> {
+        match self.store.transaction_index(id)? {
+            Some(index) => Ok(Some(self.store.transaction(index)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Adds a given transaction to the expense tracker if required conditions are met.
+    ///
+    /// The category and sub-category of the transaction are stored under their display names.
+    pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError> {
+        if self.store.transaction_index(transaction.id)?.is_some() {
+            return Err(ExpenseTrackerError::DuplicateTransaction(transaction.id));
+        }
+        // Only add the transaction if its category is valid
+        let transaction = self.validated(transaction)?;
+        if transaction.is_reconciled() {
+            return Err(ExpenseTrackerError::ReconciledTransaction(transaction.id));
+        }
+        self.check_outside_reconciled_periods(&transaction)?;
+        self.run_command("Add transaction".to_string(), |tracker| {
+            tracker.push_transaction(transaction)
+        })
+    }
+
+    /// Returns a transaction with the display names of its category, sub-category and accounts,
+    /// or an error if it is not valid.
+    ///
+    /// A transaction of an account is in the currency of the account. A transfer must be made from
+    /// an account to another one in the same currency, and has no category.
+    fn validated(&self, transaction: Transaction) -> Result<Transaction, ExpenseTrackerError> {
+        let account = match &transaction.account {
+            None => None,
+            Some(account_name) => {
+                let account = self.existing_account(account_name)?;
+                // The balance of an account is only kept in its own currency
+                if transaction.currency != account.currency {
+                    return Err(ExpenseTrackerError::MixedCurrencies {
+                        currency: account.currency,
+                        other_currencies: vec![transaction.currency],
+                    });
+                }
+                Some(account.name.clone())
+            }
+        };
+        if transaction.is_split() {
+            return Ok(Transaction {
+                account,
+                transfer_cleared: ClearedState::Uncleared,
+                ..self.validated_split(transaction)?
+            });
+        }
+        let Some(transfer_account_name) = &transaction.transfer_account else {
+            let (category_name, subcategory_name) = self.display_names(&transaction)?;
+            return Ok(Transaction {
+                category_name,
+                subcategory_name,
+                account,
+                transfer_cleared: ClearedState::Uncleared,
+                ..transaction
+            });
+        };
+
+        let transfer_account = self.existing_account(transfer_account_name)?;
+        let (transfer_account, transfer_currency) =
+            (transfer_account.name.clone(), transfer_account.currency);
+        match &account {
+            None => {
+                return Err(ExpenseTrackerError::InvalidTransfer(format!(
+                    "the account of the transfer to '{transfer_account}' is missing"
+                )))
+            }
+            Some(account) if *account == transfer_account => {
+                return Err(ExpenseTrackerError::InvalidTransfer(format!(
+                    "the account '{account}' cannot transfer to itself"
+                )))
+            }
+            // The account is in the currency of the transaction
+            Some(account) if transaction.currency != transfer_currency => {
+                return Err(ExpenseTrackerError::InvalidTransfer(format!(
+                    "the account '{account}' in {} cannot transfer to '{transfer_account}' in {}",
+                    transaction.currency.code(),
+                    transfer_currency.code()
+                )))
+            }
+            Some(_) => {}
+        }
+        if !transaction.category_name.trim().is_empty() || transaction.subcategory_name.is_some() {
+            return Err(ExpenseTrackerError::InvalidTransfer(
+                "a transfer has no category".to_string(),
+            ));
+        }
+        Ok(Transaction {
+            category_name: String::new(),
+            account,
+            transfer_account: Some(transfer_account),
+            ..transaction
+        })
+    }
+
+    /// Returns a split transaction with the display names of the categories and sub-categories of
+    /// its lines, or an error if it is not valid.
+    ///
+    /// A split transaction has no category of its own, is not a transfer, and the amounts of its
+    /// lines add up to its amount.
+    fn validated_split(
+        &self,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        if transaction.is_transfer() {
+            return Err(ExpenseTrackerError::InvalidSplit(
+                "a transfer cannot be split".to_string(),
+            ));
+        }
+        if !transaction.category_name.trim().is_empty() || transaction.subcategory_name.is_some() {
+            return Err(ExpenseTrackerError::InvalidSplit(
+                "the categories are given by the lines".to_string(),
+            ));
+        }
+        let total = transaction
+            .splits
+            .iter()
+            .try_fold(Money::ZERO, |total, line| total.checked_add(line.amount))
+            .ok_or_else(|| {
+                ExpenseTrackerError::InvalidSplit("the lines add up out of range".to_string())
+            })?;
+        if total != transaction.amount {
+            return Err(ExpenseTrackerError::InvalidSplit(format!(
+                "the lines add up to {total} instead of {}",
+                transaction.amount
+            )));
+        }
+        let lines = transaction
+            .category_lines()
+            .into_iter()
+            .map(|line| {
+                let (category_name, subcategory_name) = self.display_names(&line)?;
+                Ok(Transaction {
+                    category_name,
+                    subcategory_name,
+                    ..line
+                })
+            })
+            .collect::<Result<Vec<_>, ExpenseTrackerError>>()?;
+        Ok(Transaction {
+            category_name: String::new(),
+            ..transaction.with_category_lines(lines)
+        })
+    }
+
+    fn existing_transaction_index(&self, id: TransactionId) -> Result<usize, ExpenseTrackerError> {
+        self.store
+            .transaction_index(id)?
+            .ok_or(ExpenseTrackerError::UnknownTransaction(id))
+    }
+
+    /// Fails if the transaction at an index is reconciled with one of its accounts.
+    fn check_not_reconciled(&self, index: usize) -> Result<(), ExpenseTrackerError> {
+        let transaction = self.store.transaction(index)?;
+        if transaction.is_reconciled() {
+            return Err(ExpenseTrackerError::ReconciledTransaction(transaction.id));
+        }
+        Ok(())
+    }
+
+    /// Fails if a transaction is dated in the reconciled period of one of its accounts, such
+    /// that adding, changing or removing it would change the balance of that period.
+    fn check_outside_reconciled_periods(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<(), ExpenseTrackerError> {
+        let account_names = [&transaction.account, &transaction.transfer_account];
+        for account_name in account_names.into_iter().flatten() {
+            if let Some(account) = self.get_account(account_name) {
+                if let Some(reconciled_until) = account.reconciled_until {
+                    if account.is_reconciled_on(transaction.date) {
+                        return Err(ExpenseTrackerError::ReconciledPeriod {
+                            account_name: account.name.clone(),
+                            reconciled_until,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the new value of the transaction at an index, validated and with the ID and the
+    /// cleared states of the previous value, or an error if it cannot be changed.
+    ///
+    /// Neither the previous nor the new value can be dated in the reconciled period of one of
+    /// its accounts, even if the transaction is not cleared.
+    fn edited(
+        &self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        self.check_not_reconciled(index)?;
+        let previous = self.store.transaction(index)?;
+        self.check_outside_reconciled_periods(&previous)?;
+        let transaction = self.validated(Transaction {
+            id: previous.id,
+            cleared: previous.cleared,
+            transfer_cleared: previous.transfer_cleared,
+            ..transaction
+        })?;
+        self.check_outside_reconciled_periods(&transaction)?;
+        Ok(transaction)
+    }
+
+    /// Replaces the transaction with an ID and returns its previous value. The ID and the cleared
+    /// state are kept.
+    ///
+    /// The new transaction is validated like in `add_transaction`, and nothing is changed if it
+    /// is not valid or if the transaction is reconciled.
+    pub fn update_transaction(
+        &mut self,
+        id: TransactionId,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        let index = self.existing_transaction_index(id)?;
+        let transaction = self.edited(index, transaction)?;
+        self.run_command("Update transaction".to_string(), |tracker| {
+            tracker.replace_transaction(index, transaction)
+        })
+    }
+
+    /// Applies `edit` to the transactions with the given IDs, e.g. to move them into another
+    /// category, and returns their previous values in the same order. IDs given twice are only
+    /// edited once.
+    ///
+    /// Every edited transaction is validated before any is replaced, such that nothing is changed
+    /// if one of them is not valid or is reconciled.
+    pub fn update_transactions(
+        &mut self,
+        ids: &[TransactionId],
+        mut edit: impl FnMut(&mut Transaction),
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        let mut edited_ids = BTreeSet::new();
+        let mut rewrites = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if !edited_ids.insert(id) {
+                continue;
+            }
+            let index = self.existing_transaction_index(id)?;
+            let mut transaction = self.store.transaction(index)?.into_owned();
+            edit(&mut transaction);
+            rewrites.push((index, self.edited(index, transaction)?));
+        }
+
+        self.run_command(
+            format!("Update {} transactions", rewrites.len()),
+            |tracker| {
+                rewrites
+                    .into_iter()
+                    .map(|(index, transaction)| tracker.replace_transaction(index, transaction))
+                    .collect()
+            },
+        )
+    }
+
+    /// Removes the transaction with an ID and returns it, unless it is reconciled or dated in the
+    /// reconciled period of one of its accounts.
+    pub fn remove_transaction(
+        &mut self,
+        id: TransactionId,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        let index = self.existing_transaction_index(id)?;
+        self.check_not_reconciled(index)?;
+        self.check_outside_reconciled_periods(self.store.transaction(index)?.as_ref())?;
+        self.run_command("Remove transaction".to_string(), |tracker| {
+            tracker.remove_transaction_at(index)
+        })
+    }
+
+    /// Removes the transactions with the given IDs and returns them in the same order. IDs given
+    /// twice are only removed once.
+    ///
+    /// Nothing is removed if one of the IDs is unknown, or if one of the transactions is
+    /// reconciled or dated in the reconciled period of one of its accounts.
+    pub fn remove_transactions(
+        &mut self,
+        ids: &[TransactionId],
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        let mut removed_ids = BTreeSet::new();
+        let mut indices = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if removed_ids.insert(id) {
+                let index = self.existing_transaction_index(id)?;
+                self.check_not_reconciled(index)?;
+                self.check_outside_reconciled_periods(self.store.transaction(index)?.as_ref())?;
+                indices.push(index);
+            }
+        }
+
+        // Transactions are removed from the last one, such that the other indices stay valid
+        let mut removal_order: Vec<usize> = (0..indices.len()).collect();
+        removal_order.sort_by_key(|&position| std::cmp::Reverse(indices[position]));
+        let mut removed: Vec<Option<Transaction>> = vec![None; indices.len()];
+        self.run_command(
+            format!("Remove {} transactions", indices.len()),
+            |tracker| {
+                for position in removal_order {
+                    removed[position] = Some(tracker.remove_transaction_at(indices[position])?);
+                }
+                Ok(())
+            },
+        )?;
+        Ok(removed.into_iter().flatten().collect())
+    }
+
+    /// Returns the sum of the amounts of all income and expenses, kept separately for each
+    /// currency. Transfers between accounts are left out.
+    pub fn totals(&self) -> Result<Totals, ExpenseTrackerError> {
+        let mut totals = Totals::new();
+        for sum in self.store.category_sums()? {
+            totals.add(sum.total, sum.currency)?;
+        }
+        Ok(totals)
+    }
+
+    /// Returns the sum of the amounts of all income and expenses in the default currency.
+    ///
+    /// Fails if any transaction is in another currency, as amounts in different currencies cannot
+    /// be added together.
+    pub fn total(&self) -> Result<Money, ExpenseTrackerError> {
+        self.totals()?.total_in(self.default_currency)
+    }
+
+    /// Returns the currency in which reports are computed.
+    pub fn reporting_currency(&self) -> Currency {
+        self.reporting_currency.unwrap_or(self.default_currency)
+    }
+
+    /// Loads the table of exchange rates from a CSV or a JSON file, replacing the current one.
+    pub fn load_exchange_rates_from_file(
+        &mut self,
+        file_path: &Path,
+    ) -> Result<(), ExpenseTrackerError> {
+        self.exchange_rates = ExchangeRateTable::load_from_file(file_path)?;
+        info!(
+            "Number of exchange rates loaded: {}",
+            self.exchange_rates.len()
+        );
+        Ok(())
+    }
+
+    /// Converts the amount of a transaction into the reporting currency.
+    pub fn convert_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Conversion, ExpenseTrackerError> {
+        self.exchange_rates.convert(
+            transaction.amount,
+            transaction.currency,
+            self.reporting_currency(),
+            transaction.date,
+        )
+    }
+
+    /// Adds an account, failing if an account with the same name exists.
+    pub fn add_account(&mut self, account: Account) -> Result<(), ExpenseTrackerError> {
+        check_name(&account.name)?;
+        if let Some(existing_account) = self.get_account(&account.name) {
+            return Err(ExpenseTrackerError::DuplicateAccount(
+                existing_account.name.clone(),
+            ));
+        }
+        self.run_command(format!("Add account '{}'", account.name), |tracker| {
+            tracker.accounts.push(account.clone());
+            tracker.record(Change::AddAccount(account))
+        })
+    }
+
+    /// Returns the accounts in the order in which they were added.
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Returns the account with the given name.
+    pub fn get_account(&self, account_name: &str) -> Option<&Account> {
+        self.accounts
+            .iter()
+            .find(|account| self.name_matching.matches(&account.name, account_name))
+    }
+
+    fn existing_account(&self, account_name: &str) -> Result<&Account, ExpenseTrackerError> {
+        self.get_account(account_name)
+            .ok_or_else(|| ExpenseTrackerError::UnknownAccount(account_name.to_string()))
+    }
+
+    /// Returns the balance of an account after each of its transactions from its opening date,
+    /// in order of date. Transfers are counted in both of their accounts.
+    pub fn running_balances(
+        &self,
+        account_name: &str,
+    ) -> Result<Vec<RunningBalance>, ExpenseTrackerError> {
+        let account = self.existing_account(account_name)?;
+        let transactions = self.store.transactions_of_account(&account.name)?;
+        account.running_balances(
+            transactions.iter().map(|(_, transaction)| transaction),
+            self.name_matching,
+        )
+    }
+
+    /// Returns the balance of an account at the end of a date, or `None` if the date is before
+    /// the opening date of the account.
+    pub fn account_balance(
+        &self,
+        account_name: &str,
+        date: NaiveDate,
+    ) -> Result<Option<Money>, ExpenseTrackerError> {
+        let account = self.existing_account(account_name)?;
+        let transactions = self.store.transactions_of_account(&account.name)?;
+        account.balance_on(
+            transactions.iter().map(|(_, transaction)| transaction),
+            date,
+            self.name_matching,
+        )
+    }
+
+    /// Returns the balance of every account opened on or before a date, at the end of the date.
+    pub fn account_balances(
+        &self,
+        date: NaiveDate,
+    ) -> Result<BTreeMap<String, Money>, ExpenseTrackerError> {
+        let mut balances = BTreeMap::new();
+        for account in &self.accounts {
+            let transactions = self.store.transactions_of_account(&account.name)?;
+            if let Some(balance) = account.balance_on(
+                transactions.iter().map(|(_, transaction)| transaction),
+                date,
+                self.name_matching,
+            )? {
+                balances.insert(account.name.clone(), balance);
+            }
+        }
+        Ok(balances)
+    }
+
+    /// Compares the balance of an account at the end of a date with the closing balance of a
+    /// statement, listing the transactions which are not cleared yet.
+    pub fn reconciliation(
+        &self,
+        account_name: &str,
+        statement_date: NaiveDate,
+        statement_balance: Money,
+    ) -> Result<Reconciliation, ExpenseTrackerError> {
+        let account = self.existing_account(account_name)?;
+        let transactions = self.store.transactions_of_account(&account.name)?;
+        Reconciliation::new(
+            account,
+            transactions.iter().map(|(_, transaction)| transaction),
+            statement_date,
+            statement_balance,
+            self.name_matching,
+        )
+    }
+
+    /// Marks the transactions with the given IDs as cleared in an account, or as uncleared, e.g.
+    /// while ticking them off a statement of the account. IDs given twice are only marked once.
+    ///
+    /// Only the side of the account is marked on a transfer, the other account clears it on its
+    /// own statements. Nothing is changed if one of the IDs is unknown or not part of the
+    /// account, or if one of the transactions is reconciled with the account or dated in its
+    /// reconciled period.
+    pub fn set_cleared(
+        &mut self,
+        account_name: &str,
+        ids: &[TransactionId],
+        cleared: bool,
+    ) -> Result<(), ExpenseTrackerError> {
+        let (cleared, state_name) = if cleared {
+            (ClearedState::Cleared, "cleared")
+        } else {
+            (ClearedState::Uncleared, "uncleared")
+        };
+        let account = self.existing_account(account_name)?;
+        let mut marked_ids = BTreeSet::new();
+        let mut rewrites = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if !marked_ids.insert(id) {
+                continue;
+            }
+            let index = self.existing_transaction_index(id)?;
+            let mut transaction = self.store.transaction(index)?.into_owned();
+            match account.cleared_state(&transaction, self.name_matching) {
+                None => {
+                    return Err(ExpenseTrackerError::Reconciliation(format!(
+                        "the transaction {id} is not part of '{}'",
+                        account.name
+                    )))
+                }
+                Some(ClearedState::Reconciled) => {
+                    return Err(ExpenseTrackerError::ReconciledTransaction(id))
+                }
+                Some(_) => {}
+            }
+            if let Some(reconciled_until) = account.reconciled_until {
+                if account.is_reconciled_on(transaction.date) {
+                    return Err(ExpenseTrackerError::ReconciledPeriod {
+                        account_name: account.name.clone(),
+                        reconciled_until,
+                    });
+                }
+            }
+            account.set_cleared_state(&mut transaction, cleared, self.name_matching);
+            rewrites.push((index, transaction));
+        }
+
+        let description = format!(
+            "Mark {} transactions of '{}' as {state_name}",
+            rewrites.len(),
+            account.name
+        );
+        self.run_command(description, |tracker| {
+            for (index, transaction) in rewrites {
+                tracker.replace_transaction(index, transaction)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Reconciles an account with a statement once its cleared transactions match the closing
+    /// balance of the statement, and returns the reconciliation.
+    ///
+    /// The cleared transactions up to the statement date become reconciled and cannot be changed
+    /// anymore, and no transaction can be added to the account up to that date. Only the side of
+    /// the account becomes reconciled on a transfer, the other account still reconciles it with
+    /// its own statements. Undoing the reconciliation unlocks them again.
+    pub fn reconcile(
+        &mut self,
+        account_name: &str,
+        statement_date: NaiveDate,
+        statement_balance: Money,
+    ) -> Result<Reconciliation, ExpenseTrackerError> {
+        let account = self.existing_account(account_name)?.clone();
+        if let Some(reconciled_until) = account.reconciled_until {
+            if statement_date <= reconciled_until {
+                return Err(ExpenseTrackerError::ReconciledPeriod {
+                    account_name: account.name,
+                    reconciled_until,
+                });
+            }
+        }
+        let reconciliation =
+            self.reconciliation(&account.name, statement_date, statement_balance)?;
+        if !reconciliation.is_balanced() {
+            return Err(ExpenseTrackerError::Reconciliation(format!(
+                "the cleared balance {} of '{}' does not match the statement balance {}",
+                reconciliation.cleared_balance, account.name, statement_balance
+            )));
+        }
+
+        let rewrites: Vec<(usize, Transaction)> = self
+            .store
+            .transactions_of_account(&account.name)?
+            .into_iter()
+            .filter(|(_, transaction)| {
+                account.cleared_state(transaction, self.name_matching)
+                    == Some(ClearedState::Cleared)
+                    && transaction.date <= statement_date
+            })
+            .map(|(index, mut transaction)| {
+                account.set_cleared_state(
+                    &mut transaction,
+                    ClearedState::Reconciled,
+                    self.name_matching,
+                );
+                (index, transaction)
+            })
+            .collect();
+        self.run_command(
+            format!("Reconcile '{}' until {statement_date}", account.name),
+            |tracker| {
+                for (index, transaction) in rewrites {
+                    tracker.replace_transaction(index, transaction)?;
+                }
+                tracker.replace_account(Account {
+                    reconciled_until: Some(statement_date),
+                    ..account
+                })?;
+                Ok(())
+            },
+        )?;
+        Ok(reconciliation)
+    }
+
+    /// Returns the sum of the amounts of all income and expenses converted into the reporting
+    /// currency, along with every conversion that was made.
+    pub fn total_in_reporting_currency(&self) -> Result<ConvertedTotal, ExpenseTrackerError> {
+        let mut converted_total = ConvertedTotal {
+            currency: self.reporting_currency(),
+            total: Money::ZERO,
+            conversions: Vec::new(),
+        };
+        for transaction in self.store.iter_transactions() {
+            let transaction = transaction?;
+            // Transfers move money between accounts without spending or earning any
+            if transaction.is_transfer() {
+                continue;
+            }
+            let conversion = self.convert_transaction(&transaction)?;
+            converted_total.total = converted_total
+                .total
+                .checked_add(conversion.amount)
+                .ok_or_else(|| {
+                    ExpenseTrackerError::AmountOutOfRange(format!(
+                        "total in {}",
+                        converted_total.currency
+                    ))
+                })?;
+            converted_total.conversions.push(conversion);
+        }
+        Ok(converted_total)
+    }
+
+    /// Adds a named import profile, replacing any profile with the same name.
+    pub fn add_import_profile(
+        &mut self,
+        profile_name: &str,
+        import_profile: ImportProfile,
+    ) -> Result<(), ExpenseTrackerError> {
+        self.run_command(format!("Add import profile '{profile_name}'"), |tracker| {
+            let previous = tracker
+                .import_profiles
+                .insert(profile_name.to_string(), import_profile.clone());
+            tracker.record(Change::SetImportProfile {
+                name: profile_name.to_string(),
+                previous: previous.map(Box::new),
+                import_profile: Box::new(import_profile),
+            })
+        })
+    }
+
+    /// Returns the named import profiles, without the built-in default profile.
+    pub fn import_profiles(&self) -> &BTreeMap<String, ImportProfile> {
+        &self.import_profiles
+    }
+
+    /// Returns the import profile with the given name, which may be the built-in default profile.
+    pub fn get_import_profile(&self, profile_name: &str) -> Option<ImportProfile> {
+        match self.import_profiles.get(profile_name) {
+            Some(import_profile) => Some(import_profile.clone()),
+            None if profile_name == DEFAULT_PROFILE_NAME => Some(ImportProfile::default()),
+            None => None,
+        }
+    }
+
+    /// Adds a categorization rule, failing if its regular expression is invalid.
+    pub fn add_categorization_rule(
+        &mut self,
+        rule: CategorizationRule,
+    ) -> Result<(), ExpenseTrackerError> {
+        RuleEngine::new(std::slice::from_ref(&rule))?;
+        self.run_command(
+            format!("Add categorization rule '{}'", rule.name),
+            |tracker| {
+                tracker.categorization_rules.push(rule.clone());
+                tracker.record(Change::AddCategorizationRule(rule))
+            },
+        )
+    }
+
+    /// Returns the categorization rules in the order in which they are applied.
+    pub fn categorization_rules(&self) -> &[CategorizationRule] {
+        &self.categorization_rules
+    }
+
+    /// Returns an engine applying the categorization rules.
+    pub fn rule_engine(&self) -> Result<RuleEngine, ExpenseTrackerError> {
+        RuleEngine::new(&self.categorization_rules)
+    }
+
+    /// Assigns a category to a transaction with the categorization rules, and returns which rule
+    /// matched if any.
+    pub fn categorize_transaction(
+        &self,
+        transaction: &mut Transaction,
+    ) -> Result<Option<RuleMatch>, ExpenseTrackerError> {
+        Ok(self.rule_engine()?.categorize(transaction))
+    }
+
+    /// Returns the classifier suggesting categories.
+    pub fn classifier(&self) -> &CategoryClassifier {
+        &self.classifier
+    }
+
+    /// Trains the classifier again on every categorized transaction, e.g. if it was saved before
+    /// some transactions were changed.
+    pub fn train_classifier(&mut self) -> Result<(), ExpenseTrackerError> {
+        let mut classifier = CategoryClassifier::new();
+        for transaction in self.store.iter_transactions() {
+            classifier.learn(transaction?.as_ref());
+        }
+        self.classifier = classifier;
+        Ok(())
+    }
+
+    /// Suggests a category and sub-category for an uncategorized transaction, with the
+    /// confidence of the classifier. Suggestions of categories which no longer exist are ignored.
+    pub fn suggest_category(&self, transaction: &Transaction) -> Option<CategorySuggestion> {
+        let suggestion = self.classifier.suggest(transaction)?;
+        self.get_category(&suggestion.category_name)?;
+        if let Some(subcategory_name) = &suggestion.subcategory_name {
+            self.get_subcategory(subcategory_name, &suggestion.category_name)?;
+        }
+        Some(suggestion)
+    }
+
+    /// Load transactions from a CSV and generate an expense tracker.
+    pub fn load_transactions_from_file(
+        &mut self,
+        file_path: &Path,
+        generate_categories_and_sub: bool,
+        import_mode: ImportMode,
+    ) -> Result<ImportReport, ExpenseTrackerError> {
+        self.load_transactions_from_file_with_profile(
+            file_path,
+            DEFAULT_PROFILE_NAME,
+            generate_categories_and_sub,
+            import_mode,
+        )
+    }
+
+    /// Load transactions from a CSV whose layout is described by a named import profile.
+    ///
+    /// Returns a report with the outcome of every row. In strict mode, the import is aborted at
+    /// the first rejected row and the expense tracker is left unchanged. Rows without a category
+    /// are categorized with the categorization rules, and the report tells which rule matched.
+    ///
+    /// The whole import is undone by a single `undo`.
+    pub fn load_transactions_from_file_with_profile(
+        &mut self,
+        file_path: &Path,
+        profile_name: &str,
+        generate_categories_and_sub: bool,
+        import_mode: ImportMode,
+    ) -> Result<ImportReport, ExpenseTrackerError> {
+        // An aborted import fails, which reverts every change made so far
+        self.run_command(
+            format!("Import transactions from '{}'", file_path.display()),
+            |tracker| {
+                tracker.import_transactions(
+                    file_path,
+                    profile_name,
+                    generate_categories_and_sub,
+                    import_mode,
+                )
+            },
+        )
+    }
+
+    fn import_transactions(
+        &mut self,
+        file_path: &Path,
+        profile_name: &str,
+        generate_categories_and_sub: bool,
+        import_mode: ImportMode,
+    ) -> Result<ImportReport, ExpenseTrackerError> {
+        let import_profile = self.get_import_profile(profile_name).ok_or_else(|| {
+            ExpenseTrackerError::Config(format!("No import profile named '{profile_name}'"))
+        })?;
+
+        let mut rdr = import_profile.csv_reader(file_path)?;
+        let headers = rdr
+            .headers()
+            .map_err(|e| {
+                ExpenseTrackerError::CsvIo(format!(
+                    "Failed to read the header of the CSV of transactions: {e}"
+                ))
+            })?
+            .clone();
+        let column_indices = import_profile.column_indices(&headers)?;
+        let date_parser = import_profile.date_parser();
+        let amount_parser = import_profile.amount_parser();
+        let rule_engine = self.rule_engine()?;
+        // Lines skipped before the header are not seen by the CSV reader
+        let line_offset = import_profile.skipped_lines as u64;
+
+        let initial_n_transactions = self.store.n_transactions()?;
+
+        let mut import_report = ImportReport::new();
+        // Every existing transaction can only be the duplicate of a single row, or the other side
+        // of the transfer of a single row
+        let mut matched_duplicates = BTreeSet::new();
+        let mut matched_transfers = BTreeSet::new();
+
+        // Iterate over each record in the CSV file
+        for record in rdr.records() {
+            let entry = match record {
+                Err(e) => ImportEntry {
+                    line: e.position().map_or(0, |position| position.line()) + line_offset,
+                    record: Vec::new(),
+                    outcome: ImportOutcome::Rejected(RejectionReason::MalformedRecord(
+                        e.to_string(),
+                    )),
+                },
+                Ok(record) => {
+                    let transaction_csv = column_indices.to_transaction_csv(&record);
+                    let outcome = match Transaction::from_csv_with_parsers(
+                        transaction_csv,
+                        self.default_currency,
+                        &date_parser,
+                        &amount_parser,
+                    ) {
+                        Ok((mut transaction, parsed_formats)) => {
+                            let mut auto_fixes =
+                                import_profile.alternative_formats(&parsed_formats);
+                            if transaction.account.is_none() {
+                                transaction.account.clone_from(&import_profile.account);
+                            }
+                            if transaction.category_name.trim().is_empty()
+                                && !transaction.is_transfer()
+                            {
+                                if let Some(rule_match) = rule_engine.categorize(&mut transaction) {
+                                    auto_fixes.push(AutoFix::CategorizedByRule(rule_match));
+                                }
+                            }
+                            // Rows are only compared to the transactions there before the
+                            // import, as a statement may hold identical transactions
+                            let transfer_index = if self.transfer_matching.enabled {
+                                let other_sides = self.existing_transactions_near(
+                                    transaction.date,
+                                    self.transfer_matching.date_tolerance_days,
+                                    initial_n_transactions,
+                                )?;
+                                self.transfer_matching.find_other_side(
+                                    &transaction,
+                                    other_sides.iter().map(|(index, other)| (*index, other)),
+                                    self.name_matching,
+                                    |index| matched_transfers.contains(&index),
+                                )
+                            } else {
+                                None
+                            };
+                            let mut duplicates = self.existing_transactions_near(
+                                transaction.date,
+                                self.duplicate_detection.date_tolerance_days,
+                                initial_n_transactions,
+                            )?;
+                            // A transaction with the same ID is a duplicate whatever its date
+                            if let Some(index) = self.store.transaction_index(transaction.id)? {
+                                if index < initial_n_transactions
+                                    && duplicates.iter().all(|(other, _)| *other != index)
+                                {
+                                    let existing = self.store.transaction(index)?.into_owned();
+                                    duplicates.push((index, existing));
+                                    duplicates.sort_by_key(|(index, _)| *index);
+                                }
+                            }
+                            let duplicate_index = self.duplicate_detection.find_duplicate(
+                                &transaction,
+                                duplicates.iter().map(|(index, other)| (*index, other)),
+                                self.name_matching,
+                                |index| matched_duplicates.contains(&index),
+                            );
+                            match (transfer_index, duplicate_index) {
+                                (Some(existing_index), _) => {
+                                    matched_transfers.insert(existing_index);
+                                    self.pair_imported_transfer(transaction, existing_index)
+                                }
+                                (None, Some(existing_index)) => {
+                                    matched_duplicates.insert(existing_index);
+                                    self.add_duplicate_transaction(
+                                        transaction,
+                                        existing_index,
+                                        generate_categories_and_sub,
+                                    )
+                                }
+                                (None, None) => self.add_imported_transaction(
+                                    transaction,
+                                    generate_categories_and_sub,
+                                    auto_fixes,
+                                ),
+                            }
+                        }
+                        Err(field_error) => {
+                            ImportOutcome::Rejected(RejectionReason::InvalidField(field_error))
+                        }
+                    };
+                    ImportEntry {
+                        line: record.position().map_or(0, |position| position.line()) + line_offset,
+                        record: record.iter().map(String::from).collect(),
+                        outcome,
+                    }
+                }
+            };
+
+            if let ImportOutcome::Rejected(reason) = &entry.outcome {
+                if import_mode == ImportMode::Strict {
+                    return Err(ExpenseTrackerError::ImportAborted {
+                        line: entry.line,
+                        reason: Box::new(reason.clone()),
+                    });
+                }
+                trace!("Transaction at line {} ignored: {reason}", entry.line);
+            }
+
+            import_report.entries.push(entry);
+        }
+
+        info!(
+            "Number of valid transactions extracted from the CSV: {}",
+            import_report.n_imported()
+        );
+        info!(
+            "Number of transactions ignored: {}",
+            import_report.rejected().count()
+        );
+
+        Ok(import_report)
+    }
+
+    /// Returns the transactions dated at most `tolerance_days` days before or after a date among
+    /// the first `n_transactions` ones, along with their indices, in insertion order.
+    fn existing_transactions_near(
+        &self,
+        date: NaiveDate,
+        tolerance_days: u32,
+        n_transactions: usize,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let tolerance = Days::new(u64::from(tolerance_days));
+        let mut transactions = self.store.transactions_between(
+            date.checked_sub_days(tolerance).unwrap_or(NaiveDate::MIN),
+            date.checked_add_days(tolerance).unwrap_or(NaiveDate::MAX),
+        )?;
+        transactions.retain(|(index, _)| *index < n_transactions);
+        transactions.sort_by_key(|(index, _)| *index);
+        Ok(transactions)
+    }
+
+    /// Adds an imported transaction, generating its category and sub-category if requested, and
+    /// returns the outcome of the import.
+    fn add_imported_transaction(
+        &mut self,
+        transaction: Transaction,
+        generate_categories_and_sub: bool,
+        mut auto_fixes: Vec<AutoFix>,
+    ) -> ImportOutcome {
+        if generate_categories_and_sub && !transaction.is_transfer() {
+            let (category_name, subcategory_path) = split_category_path(
+                &transaction.category_name,
+                transaction.subcategory_name.as_deref(),
+            );
+            match self.add_missing_levels(
+                &category_name,
+                subcategory_path.as_deref(),
+                Some(transaction.date),
+            ) {
+                Ok(created) => auto_fixes.extend(created),
+                Err(e) => debug!("{}", e),
+            };
+        }
+
+        match self.add_transaction(transaction) {
+            Ok(()) if auto_fixes.is_empty() => ImportOutcome::Accepted,
+            Ok(()) => ImportOutcome::AutoFixed(auto_fixes),
+            Err(e) => ImportOutcome::Rejected(RejectionReason::InvalidTransaction(e)),
+        }
+    }
+
+    /// Handles an imported transaction which looks like an existing one according to the
+    /// duplicate policy, and returns the outcome of the import.
+    fn add_duplicate_transaction(
+        &mut self,
+        transaction: Transaction,
+        existing_index: usize,
+        generate_categories_and_sub: bool,
+    ) -> ImportOutcome {
+        let policy = self.duplicate_detection.policy;
+        let existing = match self.store.transaction(existing_index) {
+            Ok(existing) => existing.into_owned(),
+            Err(e) => return ImportOutcome::Rejected(RejectionReason::InvalidTransaction(e)),
+        };
+        let duplicate = DuplicateMatch {
+            transaction: transaction.clone(),
+            existing_index,
+            existing,
+            policy,
+        };
+        trace!("Transaction {transaction:?} looks like the existing transaction {existing_index}");
+
+        if policy == DuplicatePolicy::Keep {
+            let outcome =
+                self.add_imported_transaction(transaction, generate_categories_and_sub, Vec::new());
+            if let ImportOutcome::Rejected(_) = outcome {
+                return outcome;
+            }
+        }
+        ImportOutcome::Duplicate(Box::new(duplicate))
+    }
+
+    /// Replaces an existing transaction by the transfer it makes with an imported row of another
+    /// account, and returns the outcome of the import.
+    fn pair_imported_transfer(
+        &mut self,
+        transaction: Transaction,
+        existing_index: usize,
+    ) -> ImportOutcome {
+        let existing = match self.store.transaction(existing_index) {
+            Ok(existing) => existing.into_owned(),
+            Err(e) => return ImportOutcome::Rejected(RejectionReason::InvalidTransaction(e)),
+        };
+        let transfer = match self.edited(existing_index, transfer_between(&existing, &transaction))
+        {
+            Ok(transfer) => transfer,
+            Err(e) => return ImportOutcome::Rejected(RejectionReason::InvalidTransaction(e)),
+        };
+        trace!("Transaction {transaction:?} is the other side of the transfer {transfer:?}");
+
+        match self.replace_transaction(existing_index, transfer.clone()) {
+            Ok(_) => ImportOutcome::Transfer(Box::new(TransferMatch {
+                transaction,
+                existing_index,
+                existing,
+                transfer,
+            })),
+            Err(e) => ImportOutcome::Rejected(RejectionReason::InvalidTransaction(e)),
+        }
+    }
+
+    /// Writes the transactions to a CSV file with the columns of `TransactionCsv`. The currency
+    /// column is only written if a transaction is not in the default currency, such that a file
+    /// without currencies is written back with the same columns.
+    pub fn write_transactions_to_file(
+        &self,
+        output_path: &PathBuf,
+    ) -> Result<(), ExpenseTrackerError> {
+        let mut include_currencies = false;
+        for transaction in self.store.iter_transactions() {
+            if transaction?.currency != self.default_currency {
+                include_currencies = true;
+                break;
+            }
+        }
+        self.write_transactions_to_file_with_options(
+            output_path,
+            &ExportOptions {
+                include_currencies,
+                ..ExportOptions::default()
+            },
+        )
+    }
+
+    /// Writes the transactions to a CSV file, with the optional columns chosen in `options`.
+    pub fn write_transactions_to_file_with_options(
+        &self,
+        output_path: &PathBuf,
+        options: &ExportOptions,
+    ) -> Result<(), ExpenseTrackerError> {
+        let mut writer = csv::Writer::from_path(output_path).map_err(|e| {
+            ExpenseTrackerError::CsvIo(format!("Failed to open output CSV file: {e}"))
+        })?;
+
+        writer.write_record(options.header()).map_err(|e| {
+            ExpenseTrackerError::CsvIo(format!("Failed to write header to output CSV file: {e}"))
+        })?;
+
+        for transaction in self.store.iter_transactions() {
+            for row in options.rows(transaction?.as_ref()) {
+                writer.write_record(row).map_err(|e| {
+                    ExpenseTrackerError::CsvIo(format!(
+                        "Failed to write a transaction to output CSV file: {e}"
+                    ))
+                })?;
+            }
+        }
+
+        // Flush the writer to make sure all data is written to the file
+        writer.flush().map_err(|e| {
+            ExpenseTrackerError::CsvIo(format!("Failed to flush output CSV file: {e}"))
+        })?;
+
+        Ok(())
+    }
+
+    /// Loads categories and sub-categories from the transactions part of the expense tracker.
+    pub fn load_info_from_transactions(&mut self) -> Result<(), ExpenseTrackerError> {
+        let mut category_lines = Vec::new();
+        for transaction in self.store.iter_transactions() {
+            category_lines.extend(transaction?.as_ref().category_lines());
+        }
+        self.history
+            .begin("Add categories of the transactions".to_string());
+        for transaction in category_lines {
+            // Categories and sub-categories shared by several transactions already exist after
+            // the first one, and are left as they are
+            let (category_name, subcategory_path) = split_category_path(
+                &transaction.category_name,
+                transaction.subcategory_name.as_deref(),
+            );
+            if let Err(e) = self.add_missing_levels(
+                &category_name,
+                subcategory_path.as_deref(),
+                Some(transaction.date),
+            ) {
+                debug!("{}", e);
+            }
+        }
+        self.history.end();
+        Ok(())
+    }
+
+    /// Saves categories, transactions and settings to a project file, and the classifier next to
+    /// it if it learned anything.
+    ///
+    /// A file written with an older schema version is backed up before being overwritten.
+    pub fn save_info_to_file(&self, file_path: PathBuf) -> Result<(), ExpenseTrackerError> {
+        if !self.classifier.is_empty() {
+            self.classifier.save_to_file(&classifier_path(&file_path))?;
+        }
+
+        let project_file = ProjectFile {
+            valid_categories: Cow::Borrowed(self.store.categories()),
+            import_profiles: Cow::Borrowed(&self.import_profiles),
+            default_currency: self.default_currency,
+            reporting_currency: self.reporting_currency,
+            name_matching: self.name_matching,
+            categorization_rules: Cow::Borrowed(&self.categorization_rules),
+            accounts: Cow::Borrowed(&self.accounts),
+            duplicate_detection: self.duplicate_detection,
+            transfer_matching: self.transfer_matching,
+            transactions: self
+                .store
+                .transactions_in_range(0..self.store.n_transactions()?)?,
+        };
+        write_project_file(&file_path, &project_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Import everything from the parent module
+    use super::*;
+    use crate::account::AccountKind;
+    use crate::export::SplitExport;
+    use crate::transaction::SplitLine;
+    use chrono::NaiveDate;
+
+    impl Default for Transaction {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Transaction {
+        pub fn new() -> Transaction {
+            Transaction {
+                id: TransactionId::new(),
+                date: NaiveDate::default(),
+                amount: Money::ZERO,
+                currency: Currency::default(),
+                category_name: String::new(),
+                subcategory_name: None,
+                tag: None,
+                note: None,
+                account: None,
+                transfer_account: None,
+                cleared: ClearedState::Uncleared,
+                transfer_cleared: ClearedState::Uncleared,
+                splits: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn add_subcategory_invalid_category() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        assert_eq!(
+            expense_tracker.add_subcategory("Transports", "Train", None),
+            Err(ExpenseTrackerError::UnknownCategory(
+                "Transports".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn add_category() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .add_category("Nourriture", Some(NaiveDate::default()))
+            .unwrap();
+        let category = Category {
+            name: "Nourriture".to_string(),
+            aliases: BTreeSet::new(),
+            date_added: NaiveDate::default(),
+            subcategories: BTreeSet::new(),
+        };
+        assert_eq!(expense_tracker.categories().first().unwrap(), &category);
+    }
+
+    #[test]
+    fn add_duplicate_category() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        assert_eq!(
+            expense_tracker.add_category("NOURRITURE", None),
+            Err(ExpenseTrackerError::DuplicateCategory(
+                "Nourriture".to_string()
+            ))
+        );
+
+        expense_tracker
+            .add_subcategory("Nourriture", "Courses", None)
+            .unwrap();
+        assert!(matches!(
+            expense_tracker.add_subcategory("Nourriture", "Courses", None),
+            Err(ExpenseTrackerError::DuplicateSubCategory { .. })
+        ));
+    }
+
+    #[test]
+    fn match_categories_by_alias_and_accents() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Voyages", None).unwrap();
+        expense_tracker
+            .add_subcategory("Voyages", "Activités", None)
+            .unwrap();
+        expense_tracker
+            .add_category_alias("Voyages", "Vacances")
+            .unwrap();
+        assert_eq!(
+            expense_tracker.add_category_alias("Voyages", "VOYAGES"),
+            Ok(())
+        );
+
+        // Accents are only ignored when requested
+        assert!(expense_tracker
+            .add_subcategory("Voyages", "Activites", None)
+            .is_ok());
+        expense_tracker
+            .delete_subcategory("Voyages", "Activites", OrphanPolicy::Refuse)
+            .unwrap();
+        expense_tracker.name_matching.fold_accents = true;
+        assert_eq!(
+            expense_tracker.add_subcategory("vacances", "Activites", None),
+            Err(ExpenseTrackerError::DuplicateSubCategory {
+                category_name: "Voyages".to_string(),
+                subcategory_name: "Activités".to_string(),
+            })
+        );
+
+        // Transactions are stored under the display names
+        expense_tracker
+            .add_transaction(transaction_in("VACANCES", Some("activites")))
+            .unwrap();
+        assert_eq!(
+            expense_tracker.transactions()[0],
+            Transaction {
+                id: expense_tracker.transactions()[0].id,
+                ..transaction_in("Voyages", Some("Activités"))
+            }
+        );
+        assert_eq!(
+            expense_tracker
+                .transactions_in_category("Vacances", Some("Activites"))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        expense_tracker.add_category("Transports", None).unwrap();
+        assert_eq!(
+            expense_tracker.add_category_alias("Transports", "vacances"),
+            Err(ExpenseTrackerError::DuplicateCategory(
+                "Voyages".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn add_subcategory_and_check_category() {}
+
+    #[test]
+    fn add_subcategory_valid() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        expense_tracker
+            .add_subcategory("Nourriture", "Courses", None)
+            .unwrap();
+
+        let mut category = Category {
+            name: "Nourriture".to_string(),
+            aliases: BTreeSet::new(),
+            date_added: NaiveDate::default(),
+            subcategories: BTreeSet::new(),
+        };
+        let subcategory = SubCategory {
+            name: "Courses".to_string(),
+            aliases: BTreeSet::new(),
+            subcategories: BTreeSet::new(),
+            date_added: NaiveDate::default(),
+        };
+        category.subcategories.insert(subcategory);
+
+        assert_eq!(expense_tracker.categories().first().unwrap(), &category);
+    }
+
+    #[test]
+    fn add_transactions_valid() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .add_category("Nourriture", Some(NaiveDate::default()))
+            .unwrap();
+        expense_tracker
+            .add_subcategory("Nourriture", "Courses", Some(NaiveDate::default()))
+            .unwrap();
+
+        expense_tracker.add_category("Transports", None).unwrap();
+
+        let mut transactions: Vec<Transaction> = Vec::new();
+
+        let mut transaction_1: Transaction = Transaction::new();
+        transaction_1.category_name = "Nourriture".to_string();
+        transaction_1.subcategory_name = Some("Courses".to_string());
+        transactions.push(transaction_1);
+
+        let mut transaction_2: Transaction = Transaction::new();
+        transaction_2.category_name = "Transports".to_string();
+        transactions.push(transaction_2);
+
+        // Adding a transaction to the expense tracker takes ownership of the Transaction object,
+        // so we need to clone transactions before using add_transaction() to be able to check the
+        // equality
+        let cloned_transactions = transactions.clone();
+        for transaction in transactions {
+            expense_tracker.add_transaction(transaction).unwrap();
+        }
+        assert_eq!(expense_tracker.transactions(), cloned_transactions);
+    }
+
+    #[test]
+    fn add_transaction_invalid_category() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        let mut valid_transaction: Transaction = Transaction::new();
+        valid_transaction.category_name = String::from("Transports");
+        assert_eq!(
+            expense_tracker.add_transaction(valid_transaction),
+            Err(ExpenseTrackerError::UnknownCategory(
+                "Transports".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn add_transaction_invalid_subcategory() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        expense_tracker
+            .add_subcategory("Nourriture", "Courses", None)
+            .unwrap();
+        let mut transaction: Transaction = Transaction::new();
+        transaction.category_name = "Nourriture".to_lowercase();
+        transaction.subcategory_name = Some("Restaurant".to_lowercase());
+        assert_eq!(
+            expense_tracker.add_transaction(transaction),
+            Err(ExpenseTrackerError::UnknownSubCategory {
+                category_name: "Nourriture".to_string(),
+                subcategory_name: "restaurant".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn add_transaction_missing_subcategory() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        expense_tracker
+            .add_subcategory("Nourriture", "Courses", None)
+            .unwrap();
+        let mut transaction: Transaction = Transaction::new();
+        transaction.category_name = "Nourriture".to_lowercase();
+        assert_eq!(
+            expense_tracker.add_transaction(transaction),
+            Err(ExpenseTrackerError::MissingSubCategory {
+                category_name: "Nourriture".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn load_transactions_from_file_and_write_to_another() {
+        extern crate tempdir;
+        use std::str::FromStr;
+        use tempdir::TempDir;
+        let input_path = PathBuf::from_str("test_data/transactions_example.csv").unwrap();
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(&input_path, true, ImportMode::Lenient)
+            .unwrap();
+        let tmp_dir = TempDir::new("example").unwrap();
+        let output_path = tmp_dir.path().join("transactions_out.csv");
+        expense_tracker
+            .write_transactions_to_file(&output_path)
+            .unwrap();
+
+        let input_data = csv_to_vec(&input_path).unwrap();
+        let output_data = csv_to_vec(&output_path).unwrap();
+        assert_eq!(input_data, output_data);
+
+        drop(output_path);
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn write_currencies_only_when_read() {
+        let input_path = PathBuf::from("test_data/transactions_with_currencies.csv");
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(&input_path, true, ImportMode::Lenient)
+            .unwrap();
+        assert_eq!(expense_tracker.transactions()[7].currency, Currency::EUR);
+
+        // Transactions in other currencies are written back with the currency column
+        let tmp_dir = tempdir::TempDir::new("currencies").unwrap();
+        let output_path = tmp_dir.path().join("transactions_out.csv");
+        expense_tracker
+            .write_transactions_to_file(&output_path)
+            .unwrap();
+        assert_eq!(
+            csv_to_vec(&input_path).unwrap(),
+            csv_to_vec(&output_path).unwrap()
+        );
+        let header = std::fs::read_to_string(&output_path).unwrap();
+        assert!(header.starts_with("date,amount_out,amount_in,currency,category,"));
+    }
+
+    #[test]
+    fn save_and_load_project_file() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_example.csv"),
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+        expense_tracker.reporting_currency = Some(Currency::EUR);
+
+        let tmp_dir = tempdir::TempDir::new("project").unwrap();
+        let config_path = tmp_dir.path().join("expenseTrackerConfig.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+
+        let loaded_expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            loaded_expense_tracker.transactions(),
+            expense_tracker.transactions()
+        );
+        assert_eq!(
+            loaded_expense_tracker.categories(),
+            expense_tracker.categories()
+        );
+        assert_eq!(
+            loaded_expense_tracker.reporting_currency,
+            Some(Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn load_project_file_with_schema_version() {
+        let tmp_dir = tempdir::TempDir::new("project").unwrap();
+        let config_path = tmp_dir.path().join("expenseTrackerConfig.json");
+
+        // Files written before the schema version existed only hold categories
+        std::fs::write(
+            &config_path,
+            r#"{"valid_categories": [{"name": "nourriture", "subcategories": [], "date_added": "2023-02-06"}]}"#,
+        )
+        .unwrap();
+        let expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert!(expense_tracker.get_category("Nourriture").is_some());
+        assert!(expense_tracker.transactions().is_empty());
+
+        std::fs::write(
+            &config_path,
+            r#"{"schema_version": 99, "valid_categories": []}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()),
+            Err(ExpenseTrackerError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn totals_keep_currencies_separate() {
+        let input_path = PathBuf::from("test_data/transactions_with_currencies.csv");
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(&input_path, true, ImportMode::Lenient)
+            .unwrap();
+
+        let totals = expense_tracker.totals().unwrap();
+        assert_eq!(totals.get(Currency::EUR), Money::from_minor_units(-53755));
+        assert_eq!(totals.get(Currency::CHF), Money::from_minor_units(46405));
+        assert!(matches!(
+            expense_tracker.total(),
+            Err(ExpenseTrackerError::MixedCurrencies { .. })
+        ));
+
+        let n_transactions = expense_tracker.transactions().len();
+        for index in (0..n_transactions).rev() {
+            if expense_tracker.transactions()[index].currency != Currency::CHF {
+                expense_tracker.store.remove_transaction(index).unwrap();
+            }
+        }
+        assert_eq!(
+            expense_tracker.total().unwrap(),
+            Money::from_minor_units(46405)
+        );
+    }
+
+    #[test]
+    fn total_in_reporting_currency() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_with_currencies.csv"),
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+
+        // Without exchange rates, the EUR transactions cannot be converted
+        assert!(matches!(
+            expense_tracker.total_in_reporting_currency(),
+            Err(ExpenseTrackerError::MissingExchangeRate { .. })
+        ));
+
+        expense_tracker
+            .load_exchange_rates_from_file(Path::new("test_data/exchange_rates.csv"))
+            .unwrap();
+        let converted_total = expense_tracker.total_in_reporting_currency().unwrap();
+        assert_eq!(converted_total.currency, Currency::CHF);
+        assert_eq!(
+            converted_total.conversions.len(),
+            expense_tracker.transactions().len()
+        );
+        // -537.55 EUR at the rate of 01.02.2023 gives -537.17 CHF
+        assert_eq!(
+            converted_total.total,
+            Money::from_minor_units(46405 - 53717)
+        );
+        let eur_conversion = converted_total
+            .conversions
+            .iter()
+            .find(|conversion| conversion.original_currency == Currency::EUR)
+            .unwrap();
+        assert_eq!(
+            eur_conversion.rate_used.as_ref().unwrap().date,
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn load_transactions_with_bank_profiles() {
+        use crate::import_profile::{AmountColumns, ColumnMapping};
+        use crate::parsing::AmountFormat;
+
+        let postfinance_profile = ImportProfile {
+            columns: ColumnMapping {
+                date: "Buchungsdatum".to_string(),
+                amounts: AmountColumns::InOut {
+                    amount_out: "Lastschrift in CHF".to_string(),
+                    amount_in: "Gutschrift in CHF".to_string(),
+                },
+                currency: None,
+                category: Some("Kategorie".to_string()),
+                category_is_mandatory: false,
+                subcategory: Some("Unterkategorie".to_string()),
+                tag: None,
+                note: Some("Avisierungstext".to_string()),
+                id: None,
+                account: None,
+                transfer_account: None,
+            },
+            delimiter: ';',
+            encoding: "windows-1252".to_string(),
+            skipped_lines: 3,
+            ..ImportProfile::default()
+        };
+        let revolut_profile = ImportProfile {
+            columns: ColumnMapping {
+                date: "Started Date".to_string(),
+                amounts: AmountColumns::Signed {
+                    amount: "Amount".to_string(),
+                    expenses_are_negative: true,
+                },
+                currency: Some("Currency".to_string()),
+                category: Some("Category".to_string()),
+                category_is_mandatory: false,
+                subcategory: None,
+                tag: None,
+                note: Some("Description".to_string()),
+                id: None,
+                account: None,
+                transfer_account: None,
+            },
+            date_formats: vec!["%Y-%m-%d %H:%M:%S".to_string()],
+            amount_formats: vec![AmountFormat {
+                decimal_separator: '.',
+                thousands_separators: Vec::new(),
+            }],
+            ..ImportProfile::default()
+        };
+
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .add_import_profile("postfinance", postfinance_profile)
+            .unwrap();
+        expense_tracker
+            .add_import_profile("revolut", revolut_profile)
+            .unwrap();
+
+        // Profiles are saved in the config next to the categories
+        let tmp_dir = tempdir::TempDir::new("profiles").unwrap();
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        let mut expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(expense_tracker.import_profiles().len(), 2);
+
+        expense_tracker
+            .load_transactions_from_file_with_profile(
+                Path::new("test_data/bank_exports/postfinance.csv"),
+                "postfinance",
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+        expense_tracker
+            .load_transactions_from_file_with_profile(
+                Path::new("test_data/bank_exports/revolut.csv"),
+                "revolut",
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+
+        let transactions = expense_tracker.transactions();
+        assert_eq!(transactions.len(), 5);
+        assert_eq!(transactions[0].category_name, "Dépenses persos");
+        assert_eq!(transactions[0].amount, Money::from_minor_units(-2490));
+        assert_eq!(transactions[1].amount, Money::from_minor_units(8500));
+        assert_eq!(transactions[2].amount, Money::from_minor_units(-103240));
+        assert_eq!(transactions[3].amount, Money::from_minor_units(-2340));
+        assert_eq!(transactions[3].currency, Currency::EUR);
+        assert_eq!(
+            transactions[3].date,
+            NaiveDate::from_ymd_opt(2023, 3, 10).unwrap()
+        );
+        assert_eq!(transactions[4].note.as_deref(), Some("Top-Up by *1234"));
+
+        assert!(matches!(
+            expense_tracker.load_transactions_from_file_with_profile(
+                Path::new("test_data/bank_exports/revolut.csv"),
+                "ubs",
+                true,
+                ImportMode::Lenient,
+            ),
+            Err(ExpenseTrackerError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn categorize_imported_transactions_with_rules() {
+        use crate::import_profile::{AmountColumns, ColumnMapping};
+        use crate::parsing::AmountFormat;
+        use crate::rules::RuleConditions;
+        use chrono::Weekday;
+
+        // The Revolut export is imported without its category column
+        let revolut_profile = ImportProfile {
+            columns: ColumnMapping {
+                date: "Started Date".to_string(),
+                amounts: AmountColumns::Signed {
+                    amount: "Amount".to_string(),
+                    expenses_are_negative: true,
+                },
+                currency: Some("Currency".to_string()),
+                category: None,
+                category_is_mandatory: false,
+                subcategory: None,
+                tag: None,
+                note: Some("Description".to_string()),
+                id: None,
+                account: None,
+                transfer_account: None,
+            },
+            date_formats: vec!["%Y-%m-%d %H:%M:%S".to_string()],
+            amount_formats: vec![AmountFormat {
+                decimal_separator: '.',
+                thousands_separators: Vec::new(),
+            }],
+            ..ImportProfile::default()
+        };
+
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .add_import_profile("revolut", revolut_profile)
+            .unwrap();
+        expense_tracker
+            .add_categorization_rule(CategorizationRule {
+                name: "Top-ups".to_string(),
+                priority: 0,
+                conditions: RuleConditions {
+                    description_contains: Some("top-up".to_string()),
+                    min_amount: Some(Money::ZERO),
+                    ..RuleConditions::default()
+                },
+                category_name: "Transferts".to_string(),
+                subcategory_name: None,
+                tag: None,
+            })
+            .unwrap();
+        expense_tracker
+            .add_categorization_rule(CategorizationRule {
+                name: "Market on fridays".to_string(),
+                priority: 1,
+                conditions: RuleConditions {
+                    description_regex: Some("^mercado".to_string()),
+                    weekdays: vec![Weekday::Fri],
+                    ..RuleConditions::default()
+                },
+                category_name: "Voyages/Nourriture".to_string(),
+                subcategory_name: None,
+                tag: Some("Espagne".to_string()),
+            })
+            .unwrap();
+        assert!(matches!(
+            expense_tracker.add_categorization_rule(CategorizationRule {
+                name: "Invalid".to_string(),
+                priority: 0,
+                conditions: RuleConditions {
+                    description_regex: Some("[a-".to_string()),
+                    ..RuleConditions::default()
+                },
+                category_name: "Transferts".to_string(),
+                subcategory_name: None,
+                tag: None,
+            }),
+            Err(ExpenseTrackerError::Config(_))
+        ));
+
+        // Rules are saved in the config next to the import profiles
+        let tmp_dir = tempdir::TempDir::new("rules").unwrap();
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        let mut expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(expense_tracker.categorization_rules().len(), 2);
+
+        let import_report = expense_tracker
+            .load_transactions_from_file_with_profile(
+                Path::new("test_data/bank_exports/revolut.csv"),
+                "revolut",
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+
+        let transactions = expense_tracker.transactions();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].category_name, "Voyages");
+        assert_eq!(
+            transactions[0].subcategory_name.as_deref(),
+            Some("Nourriture")
+        );
+        assert_eq!(transactions[0].tag.as_deref(), Some("Espagne"));
+        assert_eq!(transactions[1].category_name, "Transferts");
+
+        // The report explains which rule categorized each row
+        let ImportOutcome::AutoFixed(auto_fixes) = &import_report.entries[0].outcome else {
+            panic!("The first row should have been categorized by a rule");
+        };
+        let Some(AutoFix::CategorizedByRule(rule_match)) = auto_fixes.first() else {
+            panic!("The first row should have been categorized by a rule");
+        };
+        assert_eq!(
+            rule_match.to_string(),
+            "Rule 'Market on fridays' matched: description matches '^mercado', on a Fri"
+        );
+    }
+
+    #[test]
+    fn suggest_categories_learned_from_history() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_example.csv"),
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+        expense_tracker.train_classifier().unwrap();
+        let n_transactions = expense_tracker.transactions().len() as u32;
+        assert_eq!(
+            expense_tracker.classifier().n_transactions(),
+            n_transactions
+        );
+
+        let mut transaction = Transaction::new();
+        transaction.date = NaiveDate::from_ymd_opt(2023, 2, 4).unwrap();
+        transaction.amount = Money::from_minor_units(-1200);
+        transaction.tag = Some("Engelberg (ski weekend)".to_string());
+        let suggestion = expense_tracker.suggest_category(&transaction).unwrap();
+        assert_eq!(suggestion.category_name, "Voyages");
+        assert_eq!(suggestion.subcategory_name.as_deref(), Some("Nourriture"));
+        assert!(suggestion.confidence > 0.5);
+
+        // The accepted suggestion is learned, and forgotten when the user corrects it
+        transaction.category_name = suggestion.category_name;
+        transaction.subcategory_name = suggestion.subcategory_name;
+        expense_tracker
+            .add_transaction(transaction.clone())
+            .unwrap();
+        assert_eq!(
+            expense_tracker.classifier().n_transactions(),
+            n_transactions + 1
+        );
+        let n_learned = |expense_tracker: &ExpenseTracker, subcategory_name: &str| {
+            expense_tracker
+                .classifier()
+                .n_transactions_in("Voyages", Some(subcategory_name))
+        };
+        let (n_food, n_activities) = (
+            n_learned(&expense_tracker, "Nourriture"),
+            n_learned(&expense_tracker, "Activités"),
+        );
+        expense_tracker
+            .update_transactions(&[transaction.id], |transaction| {
+                transaction.subcategory_name = Some("Activités".to_string())
+            })
+            .unwrap();
+        assert_eq!(
+            expense_tracker.classifier().n_transactions(),
+            n_transactions + 1
+        );
+        assert_eq!(n_learned(&expense_tracker, "Nourriture"), n_food - 1);
+        assert_eq!(n_learned(&expense_tracker, "Activités"), n_activities + 1);
+        expense_tracker.undo().unwrap();
+        assert_eq!(n_learned(&expense_tracker, "Nourriture"), n_food);
+        expense_tracker.redo().unwrap();
+
+        // The classifier is saved next to the config
+        let tmp_dir = tempdir::TempDir::new("classifier").unwrap();
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        assert!(tmp_dir.path().join("config.classifier.json").exists());
+        let expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            expense_tracker.classifier().n_transactions(),
+            n_transactions + 1
+        );
+
+        // Suggestions of unknown categories are ignored
+        assert!(expense_tracker.get_category("Inconnue").is_none());
+        let mut classifier = CategoryClassifier::new();
+        classifier.learn(&transaction_in("Inconnue", None));
+        let expense_tracker = ExpenseTracker {
+            classifier,
+            ..expense_tracker
+        };
+        assert!(expense_tracker
+            .suggest_category(&Transaction::new())
+            .is_none());
+    }
+
+    #[test]
+    fn detect_duplicates_on_import() {
+        let input_path = Path::new("test_data/transactions_example.csv");
+        let mut expense_tracker = ExpenseTracker::new();
+        let import_report = expense_tracker
+            .load_transactions_from_file(input_path, true, ImportMode::Strict)
+            .unwrap();
+        let n_transactions = import_report.n_imported();
+        assert_eq!(import_report.duplicates().count(), 0);
+
+        // Importing the same file again skips every row
+        let import_report = expense_tracker
+            .load_transactions_from_file(input_path, true, ImportMode::Strict)
+            .unwrap();
+        assert_eq!(import_report.n_imported(), 0);
+        assert_eq!(import_report.duplicates().count(), n_transactions);
+        assert_eq!(expense_tracker.transactions().len(), n_transactions);
+        let duplicate = import_report.duplicates().nth(3).unwrap();
+        assert_eq!(duplicate.existing_index, 3);
+        assert_eq!(
+            duplicate.existing,
+            Transaction {
+                id: duplicate.existing.id,
+                ..duplicate.transaction.clone()
+            }
+        );
+
+        // Rows which are only close to existing transactions are left to the user
+        expense_tracker.duplicate_detection.policy = DuplicatePolicy::Ask;
+        expense_tracker.duplicate_detection.date_tolerance_days = 1;
+        let tmp_dir = tempdir::TempDir::new("duplicates").unwrap();
+        let statement_path = tmp_dir.path().join("statement.csv");
+        std::fs::write(
+            &statement_path,
+            concat!(
+                "date,amount_out,amount_in,currency,category,subcategory,tag,note\n",
+                "07.02.2023,8.50,,CHF,Abonnements,Spotify,,\n",
+                "07.02.2023,8.50,,CHF,Abonnements,Spotify,,\n",
+                "07.02.2023,12.00,,CHF,Abonnements,Spotify,,\n",
+            ),
+        )
+        .unwrap();
+        let import_report = expense_tracker
+            .load_transactions_from_file(&statement_path, true, ImportMode::Strict)
+            .unwrap();
+        assert_eq!(import_report.n_imported(), 2);
+        let pending: Vec<&DuplicateMatch> = import_report.pending_duplicates().collect();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            pending[0].existing.date,
+            NaiveDate::from_ymd_opt(2023, 2, 6).unwrap()
+        );
+        expense_tracker
+            .add_transaction(pending[0].transaction.clone())
+            .unwrap();
+        assert_eq!(expense_tracker.transactions().len(), n_transactions + 3);
+
+        // Duplicates are still reported when kept
+        expense_tracker.duplicate_detection.policy = DuplicatePolicy::Keep;
+        let import_report = expense_tracker
+            .load_transactions_from_file(input_path, true, ImportMode::Strict)
+            .unwrap();
+        assert_eq!(import_report.n_imported(), n_transactions);
+        assert_eq!(import_report.duplicates().count(), n_transactions);
+        assert_eq!(expense_tracker.transactions().len(), 2 * n_transactions + 3);
+    }
+
+    #[test]
+    fn identify_transactions_by_id() {
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_example.csv"),
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+        let ids: BTreeSet<TransactionId> = expense_tracker
+            .transactions()
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        assert_eq!(ids.len(), expense_tracker.transactions().len());
+
+        let transaction = expense_tracker.transactions()[3].clone();
+        assert_eq!(
+            expense_tracker.get_transaction(transaction.id),
+            Ok(Some(Cow::Borrowed(&transaction)))
+        );
+        assert!(expense_tracker
+            .get_transaction(TransactionId::new())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            expense_tracker.add_transaction(transaction.clone()),
+            Err(ExpenseTrackerError::DuplicateTransaction(transaction.id))
+        );
+
+        // IDs are kept in the project file
+        let tmp_dir = tempdir::TempDir::new("ids").unwrap();
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        let loaded_expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            loaded_expense_tracker.get_transaction(transaction.id),
+            Ok(Some(Cow::Borrowed(&transaction)))
+        );
+
+        // IDs are only exported when requested, and read back by the default profile
+        let output_path = tmp_dir.path().join("without_ids.csv");
+        expense_tracker
+            .write_transactions_to_file(&output_path)
+            .unwrap();
+        let header = std::fs::read_to_string(&output_path).unwrap();
+        assert!(header.starts_with("date,amount_out,amount_in,category,subcategory,tag,note\n"));
+
+        let output_path = tmp_dir.path().join("with_ids.csv");
+        expense_tracker
+            .write_transactions_to_file_with_options(
+                &output_path,
+                &ExportOptions {
+                    include_ids: true,
+                    ..ExportOptions::default()
+                },
+            )
+            .unwrap();
+        let mut imported_expense_tracker = ExpenseTracker::new();
+        imported_expense_tracker
+            .load_transactions_from_file(&output_path, true, ImportMode::Strict)
+            .unwrap();
+        assert_eq!(
+            imported_expense_tracker.transactions(),
+            expense_tracker.transactions()
+        );
+    }
+
+    #[test]
+    fn update_and_remove_transactions() {
+        let mut expense_tracker = expense_tracker_with_category_tree();
+        let ids: Vec<TransactionId> = expense_tracker
+            .transactions()
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        let n_transactions = ids.len();
+
+        // The updated transaction is validated, keeps its ID and is stored under display names
+        let previous = expense_tracker.transactions()[0].clone();
+        assert_eq!(
+            expense_tracker.update_transaction(ids[0], transaction_in("Voyages", Some("Hôtels"))),
+            Err(ExpenseTrackerError::UnknownSubCategory {
+                category_name: "Voyages".to_string(),
+                subcategory_name: "Hôtels".to_string(),
+            })
+        );
+        assert_eq!(expense_tracker.transactions()[0], previous);
+        assert_eq!(
+            expense_tracker
+                .update_transaction(ids[0], transaction_in("voyages/transports", None))
+                .unwrap(),
+            previous
+        );
+        let updated = expense_tracker.get_transaction(ids[0]).unwrap().unwrap();
+        assert_eq!(updated.category_name, "Voyages");
+        assert_eq!(updated.subcategory_name.as_deref(), Some("Transports"));
+
+        // A batch is only applied if every edited transaction is valid
+        let recategorize = |subcategory_name: &'static str| {
+            move |transaction: &mut Transaction| {
+                transaction.category_name = "Voyages".to_string();
+                transaction.subcategory_name = Some(subcategory_name.to_string());
+            }
+        };
+        assert!(matches!(
+            expense_tracker.update_transactions(&ids, recategorize("Hôtels")),
+            Err(ExpenseTrackerError::UnknownSubCategory { .. })
+        ));
+        let unknown_id = TransactionId::new();
+        assert_eq!(
+            expense_tracker.update_transactions(&[ids[1], unknown_id], recategorize("Transports")),
+            Err(ExpenseTrackerError::UnknownTransaction(unknown_id))
+        );
+        assert_eq!(
+            expense_tracker.transactions()[1]
+                .subcategory_name
+                .as_deref(),
+            Some("Nourriture/Courses")
+        );
+        let previous = expense_tracker
+            .update_transactions(&ids, recategorize("Nourriture/Restaurant"))
+            .unwrap();
+        assert_eq!(previous.len(), n_transactions);
+        assert_eq!(previous[0].subcategory_name.as_deref(), Some("Transports"));
+        assert!(expense_tracker.transactions().iter().all(|transaction| {
+            transaction.subcategory_name.as_deref() == Some("Nourriture/Restaurant")
+        }));
+
+        // Removed transactions are returned in the order of the IDs
+        let removed = expense_tracker
+            .remove_transactions(&[ids[0], ids[2], ids[0]])
+            .unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].id, ids[0]);
+        assert_eq!(removed[1].id, ids[2]);
+        assert_eq!(
+            expense_tracker.remove_transaction(ids[0]),
+            Err(ExpenseTrackerError::UnknownTransaction(ids[0]))
+        );
+        assert_eq!(
+            expense_tracker.remove_transaction(ids[1]).unwrap().id,
+            ids[1]
+        );
+        assert_eq!(expense_tracker.transactions().len(), n_transactions - 3);
+    }
+
+    #[test]
+    fn undo_and_redo_operations() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        let categories = expense_tracker.categories().clone();
+        let transactions = expense_tracker.transactions().to_vec();
+        let n_commands = expense_tracker.history().undo_stack().len();
+
+        // A rename which rewrites transactions is undone at once
+        expense_tracker
+            .rename_category("Nourriture", "Alimentation")
+            .unwrap();
+        assert_eq!(expense_tracker.history().undo_stack().len(), n_commands + 1);
+        let ids: Vec<TransactionId> = transactions
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        expense_tracker
+            .update_transactions(&ids[2..4], |transaction| {
+                transaction.subcategory_name = Some("Courses".to_string())
+            })
+            .unwrap();
+        expense_tracker.remove_transaction(ids[0]).unwrap();
+        assert_eq!(
+            expense_tracker.undo().unwrap().as_deref(),
+            Some("Remove transaction")
+        );
+        assert_eq!(
+            expense_tracker.undo().unwrap().as_deref(),
+            Some("Update 2 transactions")
+        );
+        assert_eq!(
+            expense_tracker.undo().unwrap().as_deref(),
+            Some("Rename category 'Nourriture' to 'Alimentation'")
+        );
+        assert_eq!(expense_tracker.categories(), &categories);
+        assert_eq!(expense_tracker.transactions(), transactions.as_slice());
+
+        // Undone operations are redone in order, until a new operation is done
+        assert!(expense_tracker.history().can_redo());
+        expense_tracker.redo().unwrap();
+        expense_tracker.redo().unwrap();
+        assert!(expense_tracker.get_category("Alimentation").is_some());
+        assert_eq!(
+            expense_tracker.transactions()[2]
+                .subcategory_name
+                .as_deref(),
+            Some("Courses")
+        );
+        expense_tracker.add_category("Loisirs", None).unwrap();
+        assert!(!expense_tracker.history().can_redo());
+        assert_eq!(expense_tracker.redo().unwrap(), None);
+
+        // Failed operations are not recorded
+        let n_commands = expense_tracker.history().undo_stack().len();
+        assert!(expense_tracker
+            .delete_category("Alimentation", OrphanPolicy::Refuse)
+            .is_err());
+        assert_eq!(expense_tracker.history().undo_stack().len(), n_commands);
+
+        // The history survives a restart when saved with the data
+        let tmp_dir = tempdir::TempDir::new("history").unwrap();
+        let config_path = tmp_dir.path().join("expenseTrackerConfig.json");
+        let history_path = crate::history::history_path(&config_path);
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        expense_tracker.save_history_to_file(&history_path).unwrap();
+        let mut loaded_expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        loaded_expense_tracker
+            .load_history_from_file(&history_path)
+            .unwrap();
+        while loaded_expense_tracker.undo().unwrap().is_some() {}
+        assert!(loaded_expense_tracker.categories().is_empty());
+        assert!(loaded_expense_tracker.transactions().is_empty());
+    }
+
+    #[test]
+    fn replay_changes_written_to_journal() {
+        let tmp_dir = tempdir::TempDir::new("journal").unwrap();
+        let journal_path = tmp_dir.path().join("journal.jsonl");
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .set_journal(Some(
+                Journal::open(&journal_path, Some("Eric".to_string())).unwrap(),
+            ))
+            .unwrap();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("./test_data/transactions_example.csv"),
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+        let id = expense_tracker.transactions()[0].id;
+        expense_tracker
+            .update_transactions(&[id], |transaction| {
+                transaction.note = Some("Corrected".to_string())
+            })
+            .unwrap();
+        expense_tracker.remove_transaction(id).unwrap();
+        expense_tracker.undo().unwrap();
+        let category_name = expense_tracker.transactions()[1].category_name.clone();
+        expense_tracker
+            .rename_category(&category_name, "Renamed")
+            .unwrap();
+
+        // Every change is written with its operation, and undone changes are marked as reverted
+        let entries = read_journal(&journal_path).unwrap();
+        assert!(entries
+            .iter()
+            .all(|entry| entry.author.as_deref() == Some("Eric")));
+        let updated = entries
+            .iter()
+            .find(|entry| entry.operation == "Update 1 transactions")
+            .unwrap();
+        assert!(matches!(
+            &updated.change,
+            Change::ReplaceTransaction { previous, transaction, .. }
+                if previous.note != transaction.note
+        ));
+        assert!(entries
+            .iter()
+            .any(|entry| entry.reverted && entry.operation == "Undo: Remove transaction"));
+
+        let replayed = ExpenseTracker::replay_journal(MemoryStore::new(), &journal_path).unwrap();
+        assert_eq!(replayed.categories(), expense_tracker.categories());
+        assert_eq!(replayed.transactions(), expense_tracker.transactions());
+    }
+
+    #[test]
+    fn snapshot_project_when_journal_starts() {
+        let tmp_dir = tempdir::TempDir::new("journal").unwrap();
+        let journal_path = tmp_dir.path().join("journal.jsonl");
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("./test_data/transactions_example.csv"),
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+        expense_tracker
+            .set_journal(Some(Journal::open(&journal_path, None).unwrap()))
+            .unwrap();
+        let n_entries = read_journal(&journal_path).unwrap().len();
+        assert!(n_entries > 0);
+        assert!(read_journal(&journal_path)
+            .unwrap()
+            .iter()
+            .all(|entry| entry.operation == SNAPSHOT_OPERATION));
+        expense_tracker.add_category("Épargne", None).unwrap();
+
+        let replayed = ExpenseTracker::replay_journal(MemoryStore::new(), &journal_path).unwrap();
+        assert_eq!(replayed.categories(), expense_tracker.categories());
+        assert_eq!(replayed.transactions(), expense_tracker.transactions());
+
+        // A journal which already has entries continues without a new snapshot, but only on the
+        // data it gives
+        expense_tracker
+            .set_journal(Some(Journal::open(&journal_path, None).unwrap()))
+            .unwrap();
+        assert_eq!(read_journal(&journal_path).unwrap().len(), n_entries + 1);
+        expense_tracker.set_journal(None).unwrap();
+        expense_tracker
+            .rename_category("Épargne", "Économies")
+            .unwrap();
+        assert_eq!(
+            expense_tracker.set_journal(Some(Journal::open(&journal_path, None).unwrap())),
+            Err(ExpenseTrackerError::JournalMismatch(
+                journal_path.display().to_string()
+            ))
+        );
+        assert!(matches!(
+            ExpenseTracker::new().set_journal(Some(Journal::open(&journal_path, None).unwrap())),
+            Err(ExpenseTrackerError::JournalMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn track_balances_of_accounts() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        for (name, kind, opening_balance) in [
+            ("Compte courant", AccountKind::Current, 250_000),
+            ("Carte de crédit", AccountKind::CreditCard, 0),
+        ] {
+            expense_tracker
+                .add_account(Account::new(
+                    name,
+                    kind,
+                    Currency::CHF,
+                    Money::from_minor_units(opening_balance),
+                    date(1),
+                ))
+                .unwrap();
+        }
+        assert_eq!(
+            expense_tracker.add_account(Account::new(
+                "compte courant",
+                AccountKind::Savings,
+                Currency::CHF,
+                Money::ZERO,
+                date(1),
+            )),
+            Err(ExpenseTrackerError::DuplicateAccount(
+                "Compte courant".to_string()
+            ))
+        );
+
+        // Transactions name an existing account, stored under its display name
+        let transaction_with = |account_name: &str, day: u32, amount: i64| {
+            let mut transaction = transaction_in("Nourriture", None);
+            transaction.date = date(day);
+            transaction.amount = Money::from_minor_units(amount);
+            transaction.account = Some(account_name.to_string());
+            transaction
+        };
+        assert_eq!(
+            expense_tracker.add_transaction(transaction_with("Espèces", 2, -500)),
+            Err(ExpenseTrackerError::UnknownAccount("Espèces".to_string()))
+        );
+        assert_eq!(
+            expense_tracker.add_transaction(Transaction {
+                currency: Currency::EUR,
+                ..transaction_with("Compte courant", 2, -500)
+            }),
+            Err(ExpenseTrackerError::MixedCurrencies {
+                currency: Currency::CHF,
+                other_currencies: vec![Currency::EUR],
+            })
+        );
+        for transaction in [
+            transaction_with("compte courant", 3, -4_550),
+            transaction_with("Carte de crédit", 4, -12_000),
+            transaction_with("Compte courant", 2, 10_000),
+        ] {
+            expense_tracker.add_transaction(transaction).unwrap();
+        }
+        assert_eq!(
+            expense_tracker.transactions()[0].account.as_deref(),
+            Some("Compte courant")
+        );
+
+        let balances: Vec<i64> = expense_tracker
+            .running_balances("Compte courant")
+            .unwrap()
+            .iter()
+            .map(|running_balance| running_balance.balance.minor_units())
+            .collect();
+        assert_eq!(balances, [260_000, 255_450]);
+        assert_eq!(
+            expense_tracker.account_balance("Compte courant", date(2)),
+            Ok(Some(Money::from_minor_units(260_000)))
+        );
+        assert_eq!(
+            expense_tracker.account_balance("Espèces", date(2)),
+            Err(ExpenseTrackerError::UnknownAccount("Espèces".to_string()))
+        );
+        let balances = expense_tracker.account_balances(date(31)).unwrap();
+        assert_eq!(
+            balances.get("Carte de crédit"),
+            Some(&Money::from_minor_units(-12_000))
+        );
+
+        // Accounts are exported when requested, and read back by the default profile
+        let tmp_dir = tempdir::TempDir::new("accounts").unwrap();
+        let output_path = tmp_dir.path().join("with_accounts.csv");
+        expense_tracker
+            .write_transactions_to_file_with_options(
+                &output_path,
+                &ExportOptions {
+                    include_accounts: true,
+                    ..ExportOptions::default()
+                },
+            )
+            .unwrap();
+        let mut imported_expense_tracker = ExpenseTracker::new();
+        for account in expense_tracker.accounts() {
+            imported_expense_tracker
+                .add_account(account.clone())
+                .unwrap();
+        }
+        imported_expense_tracker
+            .load_transactions_from_file(&output_path, true, ImportMode::Strict)
+            .unwrap();
+        assert_eq!(
+            imported_expense_tracker.account_balances(date(31)),
+            Ok(balances)
+        );
+
+        // Accounts are kept in the project file, and their addition can be undone
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
+            .unwrap();
+        let loaded_expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            loaded_expense_tracker.accounts(),
+            expense_tracker.accounts()
+        );
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker
+            .add_account(loaded_expense_tracker.accounts()[0].clone())
+            .unwrap();
+        assert_eq!(
+            expense_tracker.undo().unwrap().as_deref(),
+            Some("Add account 'Compte courant'")
+        );
+        assert!(expense_tracker.accounts().is_empty());
+    }
+
+    #[test]
+    fn record_transfers_between_accounts() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+        let mut expense_tracker = ExpenseTracker::new();
+        for (name, kind, opening_balance) in [
+            ("Compte courant", AccountKind::Current, 100_000),
+            ("Épargne", AccountKind::Savings, 0),
+        ] {
+            expense_tracker
+                .add_account(Account::new(
+                    name,
+                    kind,
+                    Currency::CHF,
+                    Money::from_minor_units(opening_balance),
+                    date(1),
+                ))
+                .unwrap();
+            expense_tracker
+                .add_import_profile(
+                    name,
+                    ImportProfile {
+                        account: Some(name.to_string()),
+                        ..ImportProfile::default()
+                    },
+                )
+                .unwrap();
+        }
+        expense_tracker.transfer_matching = TransferMatching {
+            enabled: true,
+            date_tolerance_days: 3,
+        };
+
+        // The withdrawal and the deposit on the two statements are paired into one transfer
+        let tmp_dir = tempdir::TempDir::new("transfers").unwrap();
+        let header = "date,amount_out,amount_in,currency,category,subcategory,tag,note\n";
+        let current_path = tmp_dir.path().join("current.csv");
+        std::fs::write(
+            &current_path,
+            format!(
+                "{header}06.03.2023,500.00,,CHF,Épargne,,,Virement\n\
+                 07.03.2023,45.50,,CHF,Nourriture,,,Migros\n"
+            ),
+        )
+        .unwrap();
+        let savings_path = tmp_dir.path().join("savings.csv");
+        std::fs::write(
+            &savings_path,
+            format!("{header}08.03.2023,,500.00,CHF,Épargne,,,Virement reçu\n"),
+        )
+        .unwrap();
+        expense_tracker
+            .load_transactions_from_file_with_profile(
+                &current_path,
+                "Compte courant",
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+        let withdrawal = expense_tracker.transactions()[0].clone();
+        let import_report = expense_tracker
+            .load_transactions_from_file_with_profile(
+                &savings_path,
+                "Épargne",
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+        let transfers: Vec<&TransferMatch> = import_report.transfers().collect();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].existing, withdrawal);
+        assert_eq!(expense_tracker.transactions().len(), 2);
+        let transfer = expense_tracker
+            .get_transaction(withdrawal.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer.date, date(6));
+        assert_eq!(transfer.account.as_deref(), Some("Compte courant"));
+        assert_eq!(transfer.transfer_account.as_deref(), Some("Épargne"));
+        assert_eq!(transfer.note.as_deref(), Some("Virement"));
+
+        // Transfers are left out of income and expenses, but count in both balances
+        assert_eq!(expense_tracker.total(), Ok(Money::from_minor_units(-4_550)));
+        assert_eq!(
+            expense_tracker
+                .total_in_reporting_currency()
+                .unwrap()
+                .conversions
+                .len(),
+            1
+        );
+        assert_eq!(
+            expense_tracker
+                .category_total("Épargne")
+                .unwrap()
+                .unwrap()
+                .n_transactions,
+            0
+        );
+        let balances = expense_tracker.account_balances(date(31)).unwrap();
+        assert_eq!(
+            balances.get("Compte courant"),
+            Some(&Money::from_minor_units(45_450))
+        );
+        assert_eq!(
+            balances.get("Épargne"),
+            Some(&Money::from_minor_units(50_000))
+        );
+
+        // Undoing the import of the second statement restores the withdrawal
+        expense_tracker.undo().unwrap();
+        assert_eq!(expense_tracker.transactions()[0], withdrawal);
+
+        // A transfer links two different accounts and has no category
+        let transfer_with = |account: Option<&str>, category_name: &str| {
+            let mut transaction = transaction_in(category_name, None);
+            transaction.account = account.map(str::to_string);
+            transaction.transfer_account = Some("épargne".to_string());
+            transaction
+        };
+        assert!(matches!(
+            expense_tracker.add_transaction(transfer_with(None, "")),
+            Err(ExpenseTrackerError::InvalidTransfer(_))
+        ));
+        assert!(matches!(
+            expense_tracker.add_transaction(transfer_with(Some("Épargne"), "")),
+            Err(ExpenseTrackerError::InvalidTransfer(_))
+        ));
+        assert!(matches!(
+            expense_tracker.add_transaction(transfer_with(Some("Compte courant"), "Épargne")),
+            Err(ExpenseTrackerError::InvalidTransfer(_))
+        ));
+        expense_tracker
+            .add_account(Account::new(
+                "Compte en euros",
+                AccountKind::Current,
+                Currency::EUR,
+                Money::ZERO,
+                date(1),
+            ))
+            .unwrap();
+        assert!(matches!(
+            expense_tracker.add_transaction(Transaction {
+                currency: Currency::EUR,
+                ..transfer_with(Some("Compte en euros"), "")
+            }),
+            Err(ExpenseTrackerError::InvalidTransfer(_))
+        ));
+        let transfer = transfer_with(Some("compte courant"), "");
+        expense_tracker.add_transaction(transfer.clone()).unwrap();
+        assert_eq!(
+            expense_tracker
+                .get_transaction(transfer.id)
+                .unwrap()
+                .unwrap()
+                .transfer_account
+                .as_deref(),
+            Some("Épargne")
+        );
+    }
+
+    #[test]
+    fn reconcile_account_with_statement() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2023, 2, day).unwrap();
+        let mut expense_tracker = ExpenseTracker::new();
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        expense_tracker
+            .add_account(Account::new(
+                "Compte courant",
+                AccountKind::Current,
+                Currency::CHF,
+                Money::from_minor_units(100_000),
+                date(1),
+            ))
+            .unwrap();
+        let transaction_on = |day: u32, amount: i64| {
+            let mut transaction = transaction_in("Nourriture", None);
+            transaction.date = date(day);
+            transaction.amount = Money::from_minor_units(amount);
+            transaction.account = Some("Compte courant".to_string());
+            transaction
+        };
+        let transactions = [
+            transaction_on(3, -5_000),
+            transaction_on(5, -2_000),
+            transaction_on(20, 10_000),
+        ];
+        for transaction in transactions.clone() {
+            expense_tracker.add_transaction(transaction).unwrap();
+        }
+        let ids: Vec<TransactionId> = transactions
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+
+        // The statement only holds the first transaction, which is not cleared yet
+        let statement_balance = Money::from_minor_units(95_000);
+        let reconciliation = expense_tracker
+            .reconciliation("Compte courant", date(10), statement_balance)
+            .unwrap();
+        assert_eq!(reconciliation.ledger_balance.minor_units(), 93_000);
+        assert_eq!(
+            reconciliation.difference(),
+            Ok(Money::from_minor_units(2_000))
+        );
+        assert_eq!(reconciliation.uncleared.len(), 2);
+        assert!(matches!(
+            expense_tracker.reconcile("Compte courant", date(10), statement_balance),
+            Err(ExpenseTrackerError::Reconciliation(_))
+        ));
+
+        expense_tracker
+            .set_cleared("Compte courant", &ids[..1], true)
+            .unwrap();
+        let reconciliation = expense_tracker
+            .reconcile("compte courant", date(10), statement_balance)
+            .unwrap();
+        assert_eq!(reconciliation.uncleared[0].id, ids[1]);
+        assert_eq!(
+            expense_tracker
+                .get_transaction(ids[0])
+                .unwrap()
+                .unwrap()
+                .cleared,
+            ClearedState::Reconciled
+        );
+
+        // Reconciled transactions and periods are locked
+        let reconciled = Err(ExpenseTrackerError::ReconciledTransaction(ids[0]));
+        assert_eq!(
+            expense_tracker
+                .update_transaction(ids[0], transaction_on(3, -500))
+                .map(|_| ()),
+            reconciled
+        );
+        assert_eq!(
+            expense_tracker.remove_transaction(ids[0]).map(|_| ()),
+            reconciled
+        );
+        assert_eq!(
+            expense_tracker.set_cleared("Compte courant", &ids, false),
+            reconciled
+        );
+        let locked_period = Err(ExpenseTrackerError::ReconciledPeriod {
+            account_name: "Compte courant".to_string(),
+            reconciled_until: date(10),
+        });
+        assert_eq!(
+            expense_tracker.add_transaction(transaction_on(8, -100)),
+            locked_period
+        );
+        assert_eq!(
+            expense_tracker
+                .update_transaction(ids[2], transaction_on(9, 10_000))
+                .map(|_| ()),
+            locked_period
+        );
+        assert_eq!(
+            expense_tracker
+                .reconcile("Compte courant", date(10), statement_balance)
+                .map(|_| ()),
+            locked_period
+        );
+        assert_eq!(
+            expense_tracker.set_cleared("Compte courant", &ids[1..2], true),
+            locked_period
+        );
+        // Uncleared transactions of the period are locked too, as they count in its balance
+        assert_eq!(
+            expense_tracker
+                .update_transaction(ids[1], transaction_on(5, -2_500))
+                .map(|_| ()),
+            locked_period
+        );
+        assert_eq!(
+            expense_tracker.remove_transaction(ids[1]).map(|_| ()),
+            locked_period
+        );
+        assert_eq!(
+            expense_tracker.remove_transactions(&ids[1..]).map(|_| ()),
+            locked_period
+        );
+        expense_tracker
+            .add_account(Account::new(
+                "Épargne",
+                AccountKind::Savings,
+                Currency::CHF,
+                Money::ZERO,
+                date(1),
+            ))
+            .unwrap();
+        expense_tracker
+            .reconcile("Épargne", date(10), Money::ZERO)
+            .unwrap();
+        // Nor can a transaction be moved into the reconciled period of another account
+        assert_eq!(
+            expense_tracker
+                .update_transaction(
+                    ids[2],
+                    Transaction {
+                        account: Some("Épargne".to_string()),
+                        ..transaction_on(9, 10_000)
+                    }
+                )
+                .map(|_| ()),
+            Err(ExpenseTrackerError::ReconciledPeriod {
+                account_name: "Épargne".to_string(),
+                reconciled_until: date(10),
+            })
+        );
+        expense_tracker
+            .add_transaction(transaction_on(12, -100))
+            .unwrap();
+
+        // The states and the period are kept in the project file
+        let tmp_dir = tempdir::TempDir::new("reconciliation").unwrap();
+        let config_path = tmp_dir.path().join("config.json");
+        expense_tracker
+            .save_info_to_file(config_path.clone())
             .unwrap();
+        let loaded_expense_tracker =
+            ExpenseTracker::load_info_from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            loaded_expense_tracker.transactions(),
+            expense_tracker.transactions()
+        );
+        assert_eq!(
+            loaded_expense_tracker.accounts()[0].reconciled_until,
+            Some(date(10))
+        );
+
+        // Undoing the reconciliation unlocks the transactions
+        for _ in 0..3 {
+            expense_tracker.undo().unwrap();
+        }
+        assert_eq!(
+            expense_tracker.undo().unwrap().as_deref(),
+            Some("Reconcile 'Compte courant' until 2023-02-10")
+        );
+        assert_eq!(expense_tracker.accounts()[0].reconciled_until, None);
+        assert_eq!(
+            expense_tracker
+                .get_transaction(ids[0])
+                .unwrap()
+                .unwrap()
+                .cleared,
+            ClearedState::Cleared
+        );
+        expense_tracker.remove_transaction(ids[0]).unwrap();
     }
 
     #[test]
-    fn add_category() {
+    fn reconcile_each_side_of_transfers() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2023, 2, day).unwrap();
         let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", Some(NaiveDate::default()));
-        let category = Category {
-            name: "Nourriture".to_lowercase(),
-            date_added: NaiveDate::default(),
-            subcategories: BTreeSet::new(),
+        expense_tracker.add_category("Nourriture", None).unwrap();
+        for (name, kind, opening_balance) in [
+            ("Compte courant", AccountKind::Current, 100_000),
+            ("Épargne", AccountKind::Savings, 0),
+        ] {
+            expense_tracker
+                .add_account(Account::new(
+                    name,
+                    kind,
+                    Currency::CHF,
+                    Money::from_minor_units(opening_balance),
+                    date(1),
+                ))
+                .unwrap();
+        }
+        let transfer = Transaction {
+            date: date(5),
+            amount: Money::from_minor_units(-10_000),
+            currency: Currency::CHF,
+            account: Some("Compte courant".to_string()),
+            transfer_account: Some("Épargne".to_string()),
+            ..Transaction::new()
         };
+        expense_tracker.add_transaction(transfer.clone()).unwrap();
+
+        // The transfer is only cleared on the statement it was ticked off
+        expense_tracker
+            .set_cleared("Compte courant", &[transfer.id], true)
+            .unwrap();
+        let reconciliation = expense_tracker
+            .reconciliation("Épargne", date(10), Money::from_minor_units(10_000))
+            .unwrap();
+        assert_eq!(reconciliation.cleared_balance, Money::ZERO);
+        assert_eq!(reconciliation.uncleared[0].id, transfer.id);
+        expense_tracker
+            .reconcile("Compte courant", date(10), Money::from_minor_units(90_000))
+            .unwrap();
+        let reconciled = expense_tracker
+            .get_transaction(transfer.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reconciled.cleared, ClearedState::Reconciled);
+        assert_eq!(reconciled.transfer_cleared, ClearedState::Uncleared);
+
+        // The receiving account still reconciles it with its own statement
+        expense_tracker
+            .set_cleared("Épargne", &[transfer.id], true)
+            .unwrap();
+        expense_tracker
+            .reconcile("Épargne", date(10), Money::from_minor_units(10_000))
+            .unwrap();
         assert_eq!(
-            expense_tracker.valid_categories.pop_first().unwrap(),
-            category
+            expense_tracker
+                .get_transaction(transfer.id)
+                .unwrap()
+                .unwrap()
+                .transfer_cleared,
+            ClearedState::Reconciled
         );
-    }
 
-    #[test]
-    fn add_subcategory_and_check_category() {}
+        let expense = Transaction {
+            date: date(12),
+            amount: Money::from_minor_units(-500),
+            currency: Currency::CHF,
+            category_name: "Nourriture".to_string(),
+            account: Some("Épargne".to_string()),
+            ..Transaction::new()
+        };
+        expense_tracker.add_transaction(expense.clone()).unwrap();
+        assert!(matches!(
+            expense_tracker.set_cleared("Compte courant", &[expense.id], true),
+            Err(ExpenseTrackerError::Reconciliation(_))
+        ));
+    }
 
     #[test]
-    fn add_subcategory_valid() {
+    fn split_transactions_across_categories() {
         let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", None);
+        expense_tracker.add_category("Nourriture", None).unwrap();
         expense_tracker
             .add_subcategory("Nourriture", "Courses", None)
             .unwrap();
-
-        let mut category = Category {
-            name: "Nourriture".to_lowercase(),
-            date_added: NaiveDate::default(),
-            subcategories: BTreeSet::new(),
-        };
-        let subcategory = SubCategory {
-            name: "Courses".to_lowercase(),
-            date_added: NaiveDate::default(),
+        expense_tracker.add_category("Maison", None).unwrap();
+        let line = |amount: i64, category_name: &str, subcategory_name: Option<&str>| SplitLine {
+            amount: Money::from_minor_units(amount),
+            category_name: category_name.to_string(),
+            subcategory_name: subcategory_name.map(str::to_string),
+            tag: None,
+            note: None,
         };
-        category.subcategories.insert(subcategory);
+        let mut receipt = transaction_in("", None);
+        receipt.amount = Money::from_minor_units(-8_000);
+        receipt.note = Some("Supermarché".to_string());
+        receipt.splits = vec![
+            line(-5_000, "nourriture", Some("courses")),
+            line(-2_000, "Maison", None),
+        ];
+
+        // The lines must add up to the amount, and have valid categories
+        assert_eq!(
+            expense_tracker.add_transaction(receipt.clone()),
+            Err(ExpenseTrackerError::InvalidSplit(
+                "the lines add up to -70.00 instead of -80.00".to_string()
+            ))
+        );
+        let mut overflowing = receipt.clone();
+        overflowing.splits[1].amount = Money::from_minor_units(i64::MIN);
+        assert!(matches!(
+            expense_tracker.add_transaction(overflowing),
+            Err(ExpenseTrackerError::InvalidSplit(_))
+        ));
+        receipt.splits.push(line(-1_000, "Jardin", None));
+        assert_eq!(
+            expense_tracker.add_transaction(receipt.clone()),
+            Err(ExpenseTrackerError::UnknownCategory("Jardin".to_string()))
+        );
+        receipt.splits[2] = line(-1_000, "Maison", None);
+        receipt.category_name = "Maison".to_string();
+        assert!(matches!(
+            expense_tracker.add_transaction(receipt.clone()),
+            Err(ExpenseTrackerError::InvalidSplit(_))
+        ));
+        receipt.category_name = String::new();
+        expense_tracker.add_transaction(receipt.clone()).unwrap();
+        let added = expense_tracker
+            .get_transaction(receipt.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(added.splits[0].category_name, "Nourriture");
+        assert_eq!(added.splits[0].subcategory_name.as_deref(), Some("Courses"));
 
+        // Reports count the lines
+        let mut groceries = transaction_in("Nourriture", Some("Courses"));
+        groceries.amount = Money::from_minor_units(-1_500);
+        expense_tracker.add_transaction(groceries).unwrap();
+        let total = expense_tracker.category_total("Maison").unwrap().unwrap();
+        assert_eq!(total.n_transactions, 2);
         assert_eq!(
-            expense_tracker.valid_categories.pop_first().unwrap(),
-            category
+            total.totals.get(Currency::CHF),
+            Money::from_minor_units(-3_000)
         );
+        let total = expense_tracker
+            .category_total("Nourriture/Courses")
+            .unwrap()
+            .unwrap();
+        assert_eq!(total.n_transactions, 2);
+        assert_eq!(
+            total.totals.get(Currency::CHF),
+            Money::from_minor_units(-6_500)
+        );
+        let lines = expense_tracker
+            .transactions_in_category("Maison", None)
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.id == receipt.id));
+
+        // Editing categories rewrites the lines
+        assert_eq!(expense_tracker.rename_category("Maison", "Ménage"), Ok(1));
+        let renamed = expense_tracker
+            .get_transaction(receipt.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.splits[1].category_name, "Ménage");
+        assert_eq!(renamed.splits[0].category_name, "Nourriture");
+        assert!(matches!(
+            expense_tracker.delete_category("Ménage", OrphanPolicy::Refuse),
+            Err(ExpenseTrackerError::CategoryInUse {
+                n_transactions: 1,
+                ..
+            })
+        ));
+
+        // Split transactions are exported flattened or grouped
+        let tmp_dir = tempdir::TempDir::new("splits").unwrap();
+        let output_path = tmp_dir.path().join("splits.csv");
+        let mut options = ExportOptions::default();
+        expense_tracker
+            .write_transactions_to_file_with_options(&output_path, &options)
+            .unwrap();
+        let flattened = std::fs::read_to_string(&output_path).unwrap();
+        assert_eq!(flattened.lines().count(), 5);
+        assert!(flattened.contains(",50.00,,Nourriture,Courses,,\n"));
+        // Flattened lines have no ID, such that they are imported as transactions of their own
+        options.include_ids = true;
+        expense_tracker
+            .write_transactions_to_file_with_options(&output_path, &options)
+            .unwrap();
+        let mut imported_expense_tracker = ExpenseTracker::new();
+        let import_report = imported_expense_tracker
+            .load_transactions_from_file(&output_path, true, ImportMode::Lenient)
+            .unwrap();
+        assert_eq!(import_report.n_imported(), 4);
+        assert_eq!(import_report.duplicates().count(), 0);
+        options.include_ids = false;
+        options.splits = SplitExport::Grouped;
+        expense_tracker
+            .write_transactions_to_file_with_options(&output_path, &options)
+            .unwrap();
+        let grouped = std::fs::read_to_string(&output_path).unwrap();
+        let grouped_lines: Vec<&str> = grouped.lines().collect();
+        assert_eq!(grouped_lines.len(), 6);
+        assert!(grouped_lines[1].ends_with(",80.00,,,,,Supermarché"));
+        assert_eq!(grouped_lines[2], ",50.00,,Nourriture,Courses,,");
     }
 
-    #[test]
-    fn add_transactions_valid() {
+    fn transaction_in(category_name: &str, subcategory_name: Option<&str>) -> Transaction {
+        let mut transaction = Transaction::new();
+        transaction.category_name = category_name.to_string();
+        transaction.subcategory_name = subcategory_name.map(str::to_string);
+        transaction
+    }
+
+    fn expense_tracker_for_category_editing() -> ExpenseTracker {
         let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", Some(NaiveDate::default()));
+        for (category_name, subcategory_names) in [
+            ("Dépenses persos", vec!["Achats"]),
+            ("Depenses perso", vec!["Achats", "Cadeaux"]),
+            ("Nourriture", vec!["Courses", "Restaurant"]),
+            ("Transports", vec![]),
+        ] {
+            expense_tracker.add_category(category_name, None).unwrap();
+            for subcategory_name in subcategory_names {
+                expense_tracker
+                    .add_subcategory(category_name, subcategory_name, None)
+                    .unwrap();
+            }
+        }
+        for transaction in [
+            transaction_in("Dépenses persos", Some("Achats")),
+            transaction_in("Depenses perso", Some("Cadeaux")),
+            transaction_in("Nourriture", Some("Restaurant")),
+            transaction_in("Nourriture", Some("Courses")),
+            transaction_in("Transports", None),
+        ] {
+            expense_tracker.add_transaction(transaction).unwrap();
+        }
         expense_tracker
-            .add_subcategory("Nourriture", "Courses", Some(NaiveDate::default()))
+    }
+
+    #[test]
+    fn rename_category_rewrites_transactions() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        assert_eq!(
+            expense_tracker.rename_category("Transports", "Nourriture"),
+            Err(ExpenseTrackerError::DuplicateCategory(
+                "Nourriture".to_string()
+            ))
+        );
+
+        let n_rewritten = expense_tracker
+            .rename_category("NOURRITURE", "Alimentation")
+            .unwrap();
+        assert_eq!(n_rewritten, 2);
+        assert!(expense_tracker.get_category("Nourriture").is_none());
+        assert!(expense_tracker
+            .get_subcategory("Restaurant", "Alimentation")
+            .is_some());
+        assert_eq!(
+            expense_tracker.transactions()[2].category_name,
+            "Alimentation"
+        );
+        for transaction in expense_tracker.transactions() {
+            expense_tracker.is_transaction_valid(transaction).unwrap();
+        }
+    }
+
+    #[test]
+    fn merge_categories_rewrites_transactions() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        let n_rewritten = expense_tracker
+            .merge_categories("Depenses perso", "Dépenses persos")
             .unwrap();
+        assert_eq!(n_rewritten, 1);
+        assert_eq!(expense_tracker.categories().len(), 3);
 
-        expense_tracker.add_category("Transports", None);
+        // The merged category is still found under the name of the removed one
+        let merged_category = expense_tracker.get_category("Depenses perso").unwrap();
+        assert_eq!(merged_category.name, "Dépenses persos");
+        assert_eq!(merged_category.subcategories.len(), 2);
+        assert_eq!(
+            expense_tracker.transactions()[1],
+            Transaction {
+                id: expense_tracker.transactions()[1].id,
+                ..transaction_in("Dépenses persos", Some("Cadeaux"))
+            }
+        );
 
-        let mut transactions: Vec<Transaction> = Vec::new();
+        // Transports transactions have no sub-category, which the merged category requires
+        assert!(matches!(
+            expense_tracker.merge_categories("Transports", "Nourriture"),
+            Err(ExpenseTrackerError::MissingSubCategory { .. })
+        ));
+        assert!(expense_tracker.get_category("Transports").is_some());
+    }
 
-        let mut transaction_1: Transaction = Transaction::new();
-        transaction_1.category_name = "Nourriture".to_lowercase();
-        transaction_1.subcategory_name = Some("Courses".to_lowercase());
-        transactions.push(transaction_1);
+    #[test]
+    fn rename_and_merge_subcategories() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        assert!(matches!(
+            expense_tracker.rename_subcategory("Nourriture", "Courses", "Restaurant"),
+            Err(ExpenseTrackerError::DuplicateSubCategory { .. })
+        ));
 
-        let mut transaction_2: Transaction = Transaction::new();
-        transaction_2.category_name = "Transports".to_lowercase();
-        transactions.push(transaction_2);
+        let n_rewritten = expense_tracker
+            .rename_subcategory("Nourriture", "Courses", "Supermarché")
+            .unwrap();
+        assert_eq!(n_rewritten, 1);
+        assert_eq!(
+            expense_tracker.transactions()[3]
+                .subcategory_name
+                .as_deref(),
+            Some("Supermarché")
+        );
 
-        // Adding a transaction to the expense tracker takes ownership of the Transaction object,
-        // so we need to clone transactions before using add_transaction() to be able to check the
-        // equality
-        let cloned_transactions = transactions.clone();
-        for transaction in transactions {
-            expense_tracker.add_transaction(transaction).unwrap();
-        }
-        assert_eq!(expense_tracker.transactions, cloned_transactions);
+        let n_rewritten = expense_tracker
+            .merge_subcategories("Nourriture", "Restaurant", "Supermarché")
+            .unwrap();
+        assert_eq!(n_rewritten, 1);
+        assert_eq!(
+            expense_tracker
+                .get_subcategory("Restaurant", "Nourriture")
+                .map(|subcategory| subcategory.name.as_str()),
+            Some("Supermarché")
+        );
+        assert_eq!(
+            expense_tracker.transactions()[2]
+                .subcategory_name
+                .as_deref(),
+            Some("Supermarché")
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Invalid category")]
-    fn add_transaction_invalid_category() {
-        let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", None);
-        let mut valid_transaction: Transaction = Transaction::new();
-        valid_transaction.category_name = String::from("Transports");
-        expense_tracker.add_transaction(valid_transaction).unwrap();
+    fn delete_category_refuses_or_reassigns_orphans() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        assert_eq!(
+            expense_tracker.delete_category("Transports", OrphanPolicy::Refuse),
+            Err(ExpenseTrackerError::CategoryInUse {
+                category_name: "Transports".to_string(),
+                subcategory_name: None,
+                n_transactions: 1,
+            })
+        );
+
+        // The target sub-category is validated before anything is deleted
+        let reassign_to = |subcategory_name: Option<&str>| OrphanPolicy::Reassign {
+            category_name: "Nourriture".to_string(),
+            subcategory_name: subcategory_name.map(str::to_string),
+        };
+        assert!(matches!(
+            expense_tracker.delete_category("Transports", reassign_to(None)),
+            Err(ExpenseTrackerError::MissingSubCategory { .. })
+        ));
+        assert_eq!(
+            expense_tracker
+                .delete_category("Transports", reassign_to(Some("Courses")))
+                .unwrap(),
+            1
+        );
+        assert!(expense_tracker.get_category("Transports").is_none());
+        assert_eq!(
+            expense_tracker.transactions()[4],
+            Transaction {
+                id: expense_tracker.transactions()[4].id,
+                ..transaction_in("Nourriture", Some("Courses"))
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Sub-category set in transaction does not exist in category")]
-    fn add_transaction_invalid_subcategory() {
-        let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", None);
+    fn delete_subcategory_refuses_or_reassigns_orphans() {
+        let mut expense_tracker = expense_tracker_for_category_editing();
+        assert!(matches!(
+            expense_tracker.delete_subcategory("Nourriture", "Courses", OrphanPolicy::Refuse),
+            Err(ExpenseTrackerError::CategoryInUse {
+                n_transactions: 1,
+                ..
+            })
+        ));
+
+        let n_reassigned = expense_tracker
+            .delete_subcategory(
+                "Nourriture",
+                "Courses",
+                OrphanPolicy::Reassign {
+                    category_name: "Nourriture".to_string(),
+                    subcategory_name: Some("Restaurant".to_string()),
+                },
+            )
+            .unwrap();
+        assert_eq!(n_reassigned, 1);
+        assert!(expense_tracker
+            .get_subcategory("Courses", "Nourriture")
+            .is_none());
+
+        // Nothing uses the sub-category anymore
         expense_tracker
             .add_subcategory("Nourriture", "Courses", None)
             .unwrap();
-        let mut transaction: Transaction = Transaction::new();
-        transaction.category_name = "Nourriture".to_lowercase();
-        transaction.subcategory_name = Some("Restaurant".to_lowercase());
-        expense_tracker.add_transaction(transaction).unwrap();
+        assert_eq!(
+            expense_tracker
+                .delete_subcategory("Nourriture", "Courses", OrphanPolicy::Refuse)
+                .unwrap(),
+            0
+        );
     }
 
-    #[test]
-    #[should_panic(expected = "No sub-category set in transaction although the category has some")]
-    fn add_transaction_missing_subcategory() {
+    fn expense_tracker_with_category_tree() -> ExpenseTracker {
         let mut expense_tracker = ExpenseTracker::new();
-        expense_tracker.add_category("Nourriture", None);
+        for path in [
+            "Voyages/Nourriture/Restaurant",
+            "Voyages/Nourriture/Courses",
+            "Voyages/Transports",
+            "Nourriture/Courses",
+        ] {
+            expense_tracker.add_category_path(path, None).unwrap();
+        }
+        for (category_name, subcategory_name, amount) in [
+            ("voyages/nourriture/restaurant", None, -4550),
+            ("Voyages", Some("Nourriture/Courses"), -1200),
+            ("Voyages", Some("transports"), -8000),
+            ("Nourriture", Some("Courses"), -3000),
+        ] {
+            let mut transaction = transaction_in(category_name, subcategory_name);
+            transaction.amount = Money::from_minor_units(amount);
+            expense_tracker.add_transaction(transaction).unwrap();
+        }
         expense_tracker
-            .add_subcategory("Nourriture", "Courses", None)
+    }
+
+    #[test]
+    fn validate_transactions_against_category_tree() {
+        let mut expense_tracker = expense_tracker_with_category_tree();
+        assert_eq!(expense_tracker.categories().len(), 2);
+        assert_eq!(
+            expense_tracker.add_category_path("Voyages/Nourriture/Restaurant", None),
+            Ok(0)
+        );
+        assert_eq!(
+            expense_tracker.add_subcategory("Voyages", "Hôtels/Genève", None),
+            Err(ExpenseTrackerError::UnknownSubCategory {
+                category_name: "Voyages".to_string(),
+                subcategory_name: "Hôtels".to_string(),
+            })
+        );
+        assert_eq!(
+            expense_tracker.add_category("Voyages/Hôtels", None),
+            Err(ExpenseTrackerError::InvalidCategoryName(
+                "Voyages/Hôtels".to_string()
+            ))
+        );
+        assert_eq!(
+            expense_tracker.add_category(" ", None),
+            Err(ExpenseTrackerError::InvalidCategoryName(" ".to_string()))
+        );
+
+        // Paths are stored under the display names of every level
+        assert_eq!(
+            expense_tracker.transactions()[0],
+            Transaction {
+                id: expense_tracker.transactions()[0].id,
+                amount: Money::from_minor_units(-4550),
+                ..transaction_in("Voyages", Some("Nourriture/Restaurant"))
+            }
+        );
+        assert_eq!(
+            expense_tracker.is_transaction_valid(&transaction_in("Voyages", Some("Nourriture"))),
+            Err(ExpenseTrackerError::MissingSubCategory {
+                category_name: "Voyages/Nourriture".to_string(),
+            })
+        );
+        assert_eq!(
+            expense_tracker.is_transaction_valid(&transaction_in("Voyages/Nourriture/Snack", None)),
+            Err(ExpenseTrackerError::UnknownSubCategory {
+                category_name: "Voyages".to_string(),
+                subcategory_name: "Nourriture/Snack".to_string(),
+            })
+        );
+        assert_eq!(
+            expense_tracker
+                .transactions_in_category("Voyages/Nourriture", None)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn roll_up_totals_of_category_tree() {
+        let expense_tracker = expense_tracker_with_category_tree();
+        let category_totals = expense_tracker.category_totals().unwrap();
+        let travel_totals = category_totals
+            .iter()
+            .find(|category_total| category_total.name == "Voyages")
             .unwrap();
-        let mut transaction: Transaction = Transaction::new();
-        transaction.category_name = "Nourriture".to_lowercase();
-        expense_tracker.add_transaction(transaction).unwrap();
+        assert_eq!(travel_totals.n_transactions, 3);
+        assert_eq!(
+            travel_totals.totals.get(Currency::CHF),
+            Money::from_minor_units(-13750)
+        );
+
+        let food_totals = expense_tracker
+            .category_total("voyages/nourriture")
+            .unwrap()
+            .unwrap();
+        assert_eq!(food_totals.path, "Voyages/Nourriture");
+        assert_eq!(food_totals.n_transactions, 2);
+        assert_eq!(
+            food_totals.totals.get(Currency::CHF),
+            Money::from_minor_units(-5750)
+        );
+        assert_eq!(
+            food_totals
+                .get(&["Restaurant"])
+                .unwrap()
+                .totals
+                .get(Currency::CHF),
+            Money::from_minor_units(-4550)
+        );
+        assert_eq!(expense_tracker.category_total("Voyages/Hôtels"), Ok(None));
     }
 
     #[test]
-    fn load_transactions_from_file_and_write_to_another() {
-        extern crate tempdir;
-        use std::str::FromStr;
-        use tempdir::TempDir;
-        let input_path = PathBuf::from_str("test_data/transactions_example.csv").unwrap();
-        let mut expense_tracker = ExpenseTracker::new();
+    fn edit_levels_of_category_tree() {
+        let mut expense_tracker = expense_tracker_with_category_tree();
+        assert_eq!(
+            expense_tracker
+                .rename_subcategory("Voyages", "Nourriture", "Repas")
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            expense_tracker.transactions()[0]
+                .subcategory_name
+                .as_deref(),
+            Some("Repas/Restaurant")
+        );
+
+        // Sub-categories with the same name are merged
         expense_tracker
-            .load_transactions_from_file(&input_path, true)
+            .add_subcategory("Voyages", "Transports/Restaurant", None)
             .unwrap();
-        let tmp_dir = TempDir::new("example").unwrap();
-        let output_path = tmp_dir.path().join("transactions_out.csv");
+        assert!(matches!(
+            expense_tracker.merge_subcategories("Voyages", "Repas", "Transports"),
+            Err(ExpenseTrackerError::MissingSubCategory { .. })
+        ));
         expense_tracker
-            .write_transactions_to_file(&output_path)
+            .delete_subcategory(
+                "Voyages",
+                "Transports",
+                OrphanPolicy::Reassign {
+                    category_name: "Nourriture".to_string(),
+                    subcategory_name: Some("Courses".to_string()),
+                },
+            )
+            .unwrap();
+        expense_tracker
+            .add_subcategory("Voyages", "Transports", None)
+            .unwrap();
+        expense_tracker
+            .add_subcategory("Voyages", "Transports/Restaurant", None)
             .unwrap();
+        assert_eq!(
+            expense_tracker
+                .merge_subcategories("Voyages", "Repas", "Transports")
+                .unwrap(),
+            2
+        );
+        let travel = expense_tracker.get_category("Voyages").unwrap();
+        assert_eq!(travel.subcategories.len(), 1);
+        assert_eq!(
+            expense_tracker.transactions()[0]
+                .subcategory_name
+                .as_deref(),
+            Some("Transports/Restaurant")
+        );
+        assert_eq!(
+            expense_tracker.transactions()[1]
+                .subcategory_name
+                .as_deref(),
+            Some("Transports/Courses")
+        );
 
-        let input_data = csv_to_vec(&input_path).unwrap();
-        let output_data = csv_to_vec(&output_path).unwrap();
-        assert_eq!(input_data, output_data);
+        assert_eq!(
+            expense_tracker.delete_subcategory("Voyages", "Transports", OrphanPolicy::Refuse),
+            Err(ExpenseTrackerError::CategoryInUse {
+                category_name: "Voyages".to_string(),
+                subcategory_name: Some("Transports".to_string()),
+                n_transactions: 2,
+            })
+        );
+    }
 
-        drop(output_path);
-        tmp_dir.close().unwrap();
+    fn expense_tracker_for_import_report() -> ExpenseTracker {
+        let mut expense_tracker = ExpenseTracker::new();
+        for (category_name, subcategory_name) in [
+            ("Abonnements", "Spotify"),
+            ("Dépenses persos", "Achats"),
+            ("Nourriture", "Lunch"),
+        ] {
+            expense_tracker.add_category(category_name, None).unwrap();
+            expense_tracker
+                .add_subcategory(category_name, subcategory_name, None)
+                .unwrap();
+        }
+        expense_tracker
+    }
+
+    #[test]
+    fn import_report_in_lenient_mode() {
+        use crate::transaction::CsvField;
+
+        let mut expense_tracker = expense_tracker_for_import_report();
+        let import_report = expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_with_errors.csv"),
+                false,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+
+        let lines: Vec<u64> = import_report
+            .entries
+            .iter()
+            .map(|entry| entry.line)
+            .collect();
+        assert_eq!(lines, vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(import_report.n_imported(), 2);
+        assert_eq!(expense_tracker.transactions().len(), 2);
+
+        let outcomes: Vec<&ImportOutcome> = import_report
+            .entries
+            .iter()
+            .map(|entry| &entry.outcome)
+            .collect();
+        assert_eq!(outcomes[0], &ImportOutcome::Accepted);
+        assert_eq!(
+            outcomes[1],
+            &ImportOutcome::AutoFixed(vec![
+                AutoFix::AlternativeFormat {
+                    field: CsvField::Date,
+                    format: "%Y-%m-%d".to_string(),
+                },
+                AutoFix::AlternativeFormat {
+                    field: CsvField::AmountOut,
+                    format: "1.234,50".to_string(),
+                },
+            ])
+        );
+        assert!(matches!(
+            outcomes[2],
+            ImportOutcome::Rejected(RejectionReason::InvalidField(field_error))
+                if field_error.field == CsvField::Date
+        ));
+        assert!(matches!(
+            outcomes[3],
+            ImportOutcome::Rejected(RejectionReason::InvalidField(field_error))
+                if field_error.field == CsvField::AmountOut
+        ));
+        assert!(matches!(
+            outcomes[4],
+            ImportOutcome::Rejected(RejectionReason::MalformedRecord(_))
+        ));
+        assert!(matches!(
+            outcomes[5],
+            ImportOutcome::Rejected(RejectionReason::InvalidTransaction(
+                ExpenseTrackerError::UnknownCategory(_)
+            ))
+        ));
+
+        // The raw record is kept such that the user can fix it
+        assert_eq!(import_report.entries[5].record[4], "Transports");
+    }
+
+    #[test]
+    fn import_report_in_strict_mode() {
+        let mut expense_tracker = expense_tracker_for_import_report();
+        let error = expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_with_errors.csv"),
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ExpenseTrackerError::ImportAborted { line: 4, .. }
+        ));
+
+        // Nothing is imported when the import is aborted
+        assert!(expense_tracker.transactions().is_empty());
+        assert_eq!(
+            expense_tracker.categories(),
+            expense_tracker_for_import_report().categories()
+        );
+    }
+
+    #[test]
+    fn import_report_lists_generated_categories() {
+        let mut expense_tracker = ExpenseTracker::new();
+        let import_report = expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_example.csv"),
+                true,
+                ImportMode::Strict,
+            )
+            .unwrap();
+        assert_eq!(import_report.rejected().count(), 0);
+        assert_eq!(
+            import_report.entries[0].outcome,
+            ImportOutcome::AutoFixed(vec![
+                AutoFix::CategoryCreated("Voyages".to_string()),
+                AutoFix::SubCategoryCreated {
+                    category_name: "Voyages".to_string(),
+                    subcategory_name: "Nourriture".to_string(),
+                },
+            ])
+        );
+        assert_eq!(import_report.entries[1].outcome, ImportOutcome::Accepted);
     }
 
     fn csv_to_vec(csv_path: &PathBuf) -> Result<Vec<csv::StringRecord>, csv::Error> {
@@ -0,0 +1,122 @@
+use crate::transaction::Transaction;
+
+/// Columns of `TransactionCsv` which are always written.
+const CSV_COLUMNS: [&str; 7] = [
+    "date",
+    "amount_out",
+    "amount_in",
+    "category",
+    "subcategory",
+    "tag",
+    "note",
+];
+
+/// Position of the optional currency column, right after the amounts.
+const CURRENCY_INDEX: usize = 3;
+
+/// Columns holding the fields of a line of a split transaction.
+const LINE_COLUMNS: [&str; 6] = [
+    "amount_out",
+    "amount_in",
+    "category",
+    "subcategory",
+    "tag",
+    "note",
+];
+
+/// How split transactions are written to CSV files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitExport {
+    /// One row per line, with the date, currency and accounts of the split transaction, such that
+    /// every row reads as a transaction of its own. The rows have no ID, as they would otherwise
+    /// be imported as duplicates of each other.
+    #[default]
+    Flattened,
+    /// One row for the split transaction with its total amount and no category, followed by one
+    /// row per line holding only its amount, category, sub-category, tag and note.
+    ///
+    /// The rows of the lines have no date, so such files are meant to be read and cannot be
+    /// imported back.
+    Grouped,
+}
+
+/// Options of the CSV files written by `ExpenseTracker::write_transactions_to_file_with_options`.
+///
+/// Optional columns are written after the columns of `TransactionCsv`, except for the currency
+/// which follows the amounts, and are read back by the default import profile. Split transactions
+/// can only be read back when flattened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Writes the currency of each transaction in a `currency` column.
+    pub include_currencies: bool,
+    /// Writes the ID of each transaction in an `id` column.
+    pub include_ids: bool,
+    /// Writes the accounts of each transaction in `account` and `transfer_account` columns.
+    pub include_accounts: bool,
+    /// How split transactions are written.
+    pub splits: SplitExport,
+}
+
+impl ExportOptions {
+    /// Returns the names of the columns written with these options.
+    pub fn header(&self) -> Vec<&'static str> {
+        let mut header = CSV_COLUMNS.to_vec();
+        if self.include_currencies {
+            header.insert(CURRENCY_INDEX, "currency");
+        }
+        if self.include_accounts {
+            header.extend(["account", "transfer_account"]);
+        }
+        if self.include_ids {
+            header.push("id");
+        }
+        header
+    }
+
+    /// Returns the fields of a transaction in the same order as `header`.
+    pub fn row(&self, transaction: &Transaction) -> Vec<String> {
+        let mut row = transaction.to_csv_row().to_vec();
+        if self.include_currencies {
+            row.insert(CURRENCY_INDEX, transaction.currency.to_string());
+        }
+        if self.include_accounts {
+            row.push(transaction.account.clone().unwrap_or_default());
+            row.push(transaction.transfer_account.clone().unwrap_or_default());
+        }
+        if self.include_ids {
+            row.push(transaction.id.to_string());
+        }
+        row
+    }
+
+    /// Returns the rows of a transaction, which are several ones for a split transaction.
+    pub fn rows(&self, transaction: &Transaction) -> Vec<Vec<String>> {
+        let lines = transaction.category_lines();
+        match self.splits {
+            _ if !transaction.is_split() => vec![self.row(transaction)],
+            SplitExport::Flattened => lines
+                .iter()
+                .map(|line| {
+                    let mut row = self.row(line);
+                    // The lines share the ID of the split transaction
+                    if let Some(id) = row.last_mut().filter(|_| self.include_ids) {
+                        id.clear();
+                    }
+                    row
+                })
+                .collect(),
+            SplitExport::Grouped => std::iter::once(self.row(transaction))
+                .chain(lines.iter().map(|line| {
+                    let mut row = self.row(line);
+                    // The other fields are the ones of the split transaction above
+                    for (column, field) in self.header().into_iter().zip(row.iter_mut()) {
+                        if !LINE_COLUMNS.contains(&column) {
+                            field.clear();
+                        }
+                    }
+                    row
+                }))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::account::Account;
+use crate::error::ExpenseTrackerError;
+use crate::import_profile::ImportProfile;
+use crate::rules::CategorizationRule;
+use crate::transaction::{Category, Transaction};
+
+/// Number of commands kept in the history, the oldest ones being dropped first.
+pub const MAX_HISTORY_LEN: usize = 100;
+
+/// A single change of the data of an `ExpenseTracker`, which holds what is needed to revert it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+    /// A category was inserted, replacing `previous` if it had the same name.
+    SetCategory {
+        previous: Option<Category>,
+        category: Category,
+    },
+    RemoveCategory(Category),
+    InsertTransaction {
+        index: usize,
+        transaction: Transaction,
+    },
+    ReplaceTransaction {
+        index: usize,
+        previous: Box<Transaction>,
+        transaction: Box<Transaction>,
+    },
+    RemoveTransaction {
+        index: usize,
+        transaction: Transaction,
+    },
+    /// An import profile was inserted, replacing `previous` if it had the same name.
+    SetImportProfile {
+        name: String,
+        previous: Option<Box<ImportProfile>>,
+        import_profile: Box<ImportProfile>,
+    },
+    /// A categorization rule was appended to the list of rules.
+    AddCategorizationRule(CategorizationRule),
+    /// An account was appended to the list of accounts.
+    AddAccount(Account),
+    /// An account was replaced by another one with the same name.
+    ReplaceAccount {
+        previous: Account,
+        account: Account,
+    },
+}
+
+/// An operation of the `ExpenseTracker`, made of the changes needed to do or undo it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Command {
+    /// Description of the operation shown to the user, e.g. "Rename category 'Voyages'".
+    pub description: String,
+    /// Changes in the order in which they were made, and in which they are redone.
+    pub changes: Vec<Change>,
+}
+
+/// The commands which can be undone and redone, most recent last.
+///
+/// Every mutating operation of the `ExpenseTracker` is recorded as a single `Command`, even if it
+/// calls other operations, and doing a new operation clears the commands which can be redone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    undo_stack: Vec<Command>,
+    redo_stack: Vec<Command>,
+    /// Command of the operation in progress, if any.
+    #[serde(skip)]
+    pending: Option<Command>,
+    /// Number of nested operations in progress.
+    #[serde(skip)]
+    depth: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Returns the commands which can be undone, most recent last.
+    pub fn undo_stack(&self) -> &[Command] {
+        &self.undo_stack
+    }
+
+    /// Returns the commands which can be redone, the next one last.
+    pub fn redo_stack(&self) -> &[Command] {
+        &self.redo_stack
+    }
+
+    /// Forgets every command.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Starts an operation, which is only recorded as a command of its own if no other
+    /// operation is in progress.
+    pub(crate) fn begin(&mut self, description: String) {
+        if self.depth == 0 {
+            self.pending = Some(Command {
+                description,
+                changes: Vec::new(),
+            });
+        }
+        self.depth += 1;
+    }
+
+    /// Ends an operation, and records its command if it changed anything.
+    pub(crate) fn end(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth > 0 {
+            return;
+        }
+        if let Some(command) = self.pending.take() {
+            if !command.changes.is_empty() {
+                self.push_undo(command);
+                self.redo_stack.clear();
+            }
+        }
+    }
+
+    /// Records a change of the operation in progress.
+    pub(crate) fn record(&mut self, change: Change) {
+        match &mut self.pending {
+            Some(command) => command.changes.push(change),
+            // Changes made outside of an operation are recorded on their own
+            None => {
+                self.push_undo(Command {
+                    description: "Change".to_string(),
+                    changes: vec![change],
+                });
+                self.redo_stack.clear();
+            }
+        }
+    }
+
+    /// Returns the description of the operation in progress, if any.
+    pub(crate) fn pending_description(&self) -> Option<&str> {
+        self.pending
+            .as_ref()
+            .map(|command| command.description.as_str())
+    }
+
+    /// Returns the number of changes recorded by the operation in progress.
+    pub(crate) fn n_pending_changes(&self) -> usize {
+        self.pending
+            .as_ref()
+            .map_or(0, |command| command.changes.len())
+    }
+
+    /// Removes the changes recorded by the operation in progress after the first `n_changes`,
+    /// e.g. because they are reverted, and returns them.
+    pub(crate) fn take_pending_changes(&mut self, n_changes: usize) -> Vec<Change> {
+        match &mut self.pending {
+            Some(command) if n_changes < command.changes.len() => {
+                command.changes.split_off(n_changes)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Records a command which can be undone, without clearing the ones which can be redone.
+    pub(crate) fn push_undo(&mut self, command: Command) {
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > MAX_HISTORY_LEN {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<Command> {
+        self.undo_stack.pop()
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<Command> {
+        self.redo_stack.pop()
+    }
+
+    /// Records a command which was undone, such that it can be redone.
+    pub(crate) fn push_redo(&mut self, command: Command) {
+        self.redo_stack.push(command);
+    }
+
+    /// Loads a history saved with `save_to_file`.
+    pub fn load_from_file(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let file = File::open(file_path).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to open history file: {e}"))
+        })?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| ExpenseTrackerError::Config(format!("Failed to read history file: {e}")))
+    }
+
+    /// Saves the history, replacing the file if it exists.
+    pub fn save_to_file(&self, file_path: &Path) -> Result<(), ExpenseTrackerError> {
+        let file = File::create(file_path).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to create history file: {e}"))
+        })?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self).map_err(|e| {
+            ExpenseTrackerError::Config(format!("Failed to write history file: {e}"))
+        })?;
+        writer
+            .flush()
+            .map_err(|e| ExpenseTrackerError::Config(format!("Failed to write history file: {e}")))
+    }
+}
+
+/// Returns the path of the history saved next to a project file, e.g.
+/// `expenseTrackerConfig.history.json` next to `expenseTrackerConfig.json`.
+pub fn history_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    config_path.with_file_name(format!("{stem}.history.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(name: &str) -> Change {
+        Change::RemoveCategory(Category::from_name(name))
+    }
+
+    #[test]
+    fn nested_operations_are_a_single_command() {
+        let mut history = History::new();
+        history.begin("Import".to_string());
+        history.record(change("a"));
+        history.begin("Add category".to_string());
+        history.record(change("b"));
+        history.end();
+        assert!(!history.can_undo());
+        history.end();
+        assert_eq!(history.undo_stack().len(), 1);
+        assert_eq!(history.undo_stack()[0].description, "Import");
+        assert_eq!(history.undo_stack()[0].changes.len(), 2);
+
+        // Operations which change nothing are not recorded
+        history.begin("Nothing".to_string());
+        history.end();
+        assert_eq!(history.undo_stack().len(), 1);
+
+        // A new operation clears the commands which can be redone
+        let command = history.pop_undo().unwrap();
+        history.push_redo(command);
+        assert!(history.can_redo());
+        history.record(change("c"));
+        assert!(!history.can_redo());
+
+        for _ in 0..MAX_HISTORY_LEN + 1 {
+            history.record(change("d"));
+        }
+        assert_eq!(history.undo_stack().len(), MAX_HISTORY_LEN);
+    }
+}
@@ -0,0 +1,438 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::ExpenseTrackerError;
+use crate::import_report::AutoFix;
+use crate::parsing::{AmountFormat, AmountParser, DateParser, ParsedFormats};
+use crate::transaction::{CsvField, TransactionCsv};
+
+/// Name of the built-in profile which reads the CSV layout written by the expense tracker.
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// How amounts are spread over the columns of a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AmountColumns {
+    /// Money going out and money coming in are stored as positive numbers in two columns.
+    InOut {
+        amount_out: String,
+        amount_in: String,
+    },
+    /// A single column contains a signed amount.
+    Signed {
+        amount: String,
+        /// Some banks (e.g. for credit cards) write expenses as positive numbers and income as
+        /// negative ones, in which case this must be false.
+        #[serde(default = "default_expenses_are_negative")]
+        expenses_are_negative: bool,
+    },
+}
+
+fn default_expenses_are_negative() -> bool {
+    true
+}
+
+/// The names of the source columns holding each field of a `Transaction`.
+///
+/// Fields set to `None`, or whose column is missing from a file, are left empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub date: String,
+    pub amounts: AmountColumns,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Whether files without the category column are rejected, instead of leaving the categories
+    /// empty for rules or the classifier to fill.
+    #[serde(default)]
+    pub category_is_mandatory: bool,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Column of the IDs written by `ExpenseTracker::write_transactions_to_file_with_options`.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub transfer_account: Option<String>,
+}
+
+/// A named description of the CSV layout exported by a bank, used to import its transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportProfile {
+    pub columns: ColumnMapping,
+    /// Character separating the fields of a row, which must be ASCII.
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Label of the text encoding of the file, e.g. `utf-8` or `windows-1252`.
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Number of lines before the header row, which some banks fill with account information.
+    #[serde(default)]
+    pub skipped_lines: usize,
+    /// Formats of the dates, tried in order, see `chrono::format::strftime` for the syntax.
+    #[serde(default = "default_date_formats")]
+    pub date_formats: Vec<String>,
+    /// Formats of the amounts, tried in order.
+    #[serde(default = "default_amount_formats")]
+    pub amount_formats: Vec<AmountFormat>,
+    /// Account of the rows which do not name one, e.g. the account of a bank statement.
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_encoding() -> String {
+    "utf-8".to_string()
+}
+
+fn default_date_formats() -> Vec<String> {
+    vec!["%d.%m.%Y".to_string(), "%Y-%m-%d".to_string()]
+}
+
+fn default_amount_formats() -> Vec<AmountFormat> {
+    vec![
+        // The ' character is used to delimit thousands from hundreds in CHF
+        AmountFormat {
+            decimal_separator: '.',
+            thousands_separators: vec!['\'', ',', ' '],
+        },
+        AmountFormat {
+            decimal_separator: ',',
+            thousands_separators: vec!['.', ' ', '\''],
+        },
+    ]
+}
+
+impl Default for ImportProfile {
+    /// Returns the profile matching the columns of `TransactionCsv`.
+    fn default() -> Self {
+        ImportProfile {
+            columns: ColumnMapping {
+                date: "date".to_string(),
+                amounts: AmountColumns::InOut {
+                    amount_out: "amount_out".to_string(),
+                    amount_in: "amount_in".to_string(),
+                },
+                currency: Some("currency".to_string()),
+                category: Some("category".to_string()),
+                category_is_mandatory: true,
+                subcategory: Some("subcategory".to_string()),
+                tag: Some("tag".to_string()),
+                note: Some("note".to_string()),
+                id: Some("id".to_string()),
+                account: Some("account".to_string()),
+                transfer_account: Some("transfer_account".to_string()),
+            },
+            delimiter: default_delimiter(),
+            encoding: default_encoding(),
+            skipped_lines: 0,
+            date_formats: default_date_formats(),
+            amount_formats: default_amount_formats(),
+            account: None,
+        }
+    }
+}
+
+/// Indices of the columns of a specific file, resolved from its header with an `ImportProfile`.
+#[derive(Debug)]
+pub(crate) struct ColumnIndices {
+    date: usize,
+    amounts: AmountIndices,
+    currency: Option<usize>,
+    category: Option<usize>,
+    subcategory: Option<usize>,
+    tag: Option<usize>,
+    note: Option<usize>,
+    id: Option<usize>,
+    account: Option<usize>,
+    transfer_account: Option<usize>,
+}
+
+#[derive(Debug)]
+enum AmountIndices {
+    InOut {
+        amount_out: usize,
+        amount_in: usize,
+    },
+    Signed {
+        amount: usize,
+        expenses_are_negative: bool,
+    },
+}
+
+impl ColumnIndices {
+    /// Extracts the fields of a CSV record into a `TransactionCsv`, without parsing them.
+    pub(crate) fn to_transaction_csv(&self, record: &StringRecord) -> TransactionCsv {
+        let field = |index: usize| record.get(index).unwrap_or_default().trim().to_string();
+        let optional_field = |index: Option<usize>| index.map(field).unwrap_or_default();
+
+        let (amount_out, amount_in) = match self.amounts {
+            AmountIndices::InOut {
+                amount_out,
+                amount_in,
+            } => (field(amount_out), field(amount_in)),
+            // A signed amount is stored as it is in the "in" column, as money coming in is
+            // positive. If expenses are positive, the amount goes to the "out" column instead,
+            // which flips its sign.
+            AmountIndices::Signed {
+                amount,
+                expenses_are_negative: true,
+            } => (String::new(), field(amount)),
+            AmountIndices::Signed {
+                amount,
+                expenses_are_negative: false,
+            } => (field(amount), String::new()),
+        };
+
+        TransactionCsv {
+            date: field(self.date),
+            amount_out,
+            amount_in,
+            currency: optional_field(self.currency),
+            category: optional_field(self.category),
+            subcategory: optional_field(self.subcategory),
+            tag: optional_field(self.tag),
+            note: optional_field(self.note),
+            id: optional_field(self.id),
+            account: optional_field(self.account),
+            transfer_account: optional_field(self.transfer_account),
+        }
+    }
+}
+
+impl ImportProfile {
+    /// Returns the delimiter as a byte, as expected by the `csv` crate.
+    fn delimiter_byte(&self) -> Result<u8, ExpenseTrackerError> {
+        if !self.delimiter.is_ascii() {
+            return Err(ExpenseTrackerError::Config(format!(
+                "CSV delimiter '{}' is not ASCII",
+                self.delimiter
+            )));
+        }
+        Ok(self.delimiter as u8)
+    }
+
+    /// Reads a file with the encoding of the profile, and returns a CSV reader positioned at the
+    /// header row.
+    pub(crate) fn csv_reader(
+        &self,
+        file_path: &Path,
+    ) -> Result<csv::Reader<std::io::Cursor<Vec<u8>>>, ExpenseTrackerError> {
+        let encoding =
+            encoding_rs::Encoding::for_label(self.encoding.as_bytes()).ok_or_else(|| {
+                ExpenseTrackerError::Config(format!("Unknown encoding '{}'", self.encoding))
+            })?;
+        let bytes = std::fs::read(file_path).map_err(|e| {
+            ExpenseTrackerError::CsvIo(format!("Failed to load the CSV of transactions: {e}"))
+        })?;
+        // Decoding also removes any byte order mark at the start of the file
+        let (text, _, had_errors) = encoding.decode(&bytes);
+        if had_errors {
+            return Err(ExpenseTrackerError::CsvIo(format!(
+                "The CSV of transactions is not valid {}",
+                encoding.name()
+            )));
+        }
+
+        let content: String = text
+            .split_inclusive('\n')
+            .skip(self.skipped_lines)
+            .collect();
+
+        Ok(csv::ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(self.delimiter_byte()?)
+            .from_reader(std::io::Cursor::new(content.into_bytes())))
+    }
+
+    /// Finds the index of every mapped column in the header of a file.
+    ///
+    /// The date and amount columns are mandatory, as well as the category column if the mapping
+    /// says so. The others are left empty if missing.
+    pub(crate) fn column_indices(
+        &self,
+        headers: &StringRecord,
+    ) -> Result<ColumnIndices, ExpenseTrackerError> {
+        let find = |name: &str| headers.iter().position(|header| header.trim() == name);
+        let find_mandatory = |name: &str| {
+            find(name).ok_or_else(|| {
+                ExpenseTrackerError::CsvIo(format!(
+                    "Column '{name}' is missing from the CSV header"
+                ))
+            })
+        };
+        let find_optional = |name: &Option<String>| name.as_deref().and_then(find);
+
+        let amounts = match &self.columns.amounts {
+            AmountColumns::InOut {
+                amount_out,
+                amount_in,
+            } => AmountIndices::InOut {
+                amount_out: find_mandatory(amount_out)?,
+                amount_in: find_mandatory(amount_in)?,
+            },
+            AmountColumns::Signed {
+                amount,
+                expenses_are_negative,
+            } => AmountIndices::Signed {
+                amount: find_mandatory(amount)?,
+                expenses_are_negative: *expenses_are_negative,
+            },
+        };
+
+        Ok(ColumnIndices {
+            date: find_mandatory(&self.columns.date)?,
+            amounts,
+            currency: find_optional(&self.columns.currency),
+            category: match &self.columns.category {
+                Some(category) if self.columns.category_is_mandatory => {
+                    Some(find_mandatory(category)?)
+                }
+                category => find_optional(category),
+            },
+            subcategory: find_optional(&self.columns.subcategory),
+            tag: find_optional(&self.columns.tag),
+            note: find_optional(&self.columns.note),
+            id: find_optional(&self.columns.id),
+            account: find_optional(&self.columns.account),
+            transfer_account: find_optional(&self.columns.transfer_account),
+        })
+    }
+
+    /// Returns a parser trying the date formats of the profile in order.
+    pub fn date_parser(&self) -> DateParser {
+        DateParser::new(self.date_formats.clone())
+    }
+
+    /// Returns a parser trying the amount formats of the profile in order.
+    pub fn amount_parser(&self) -> AmountParser {
+        AmountParser::new(self.amount_formats.clone())
+    }
+
+    /// Lists the fields which were not parsed with the preferred, i.e. first, format of the
+    /// profile.
+    pub fn alternative_formats(&self, parsed_formats: &ParsedFormats) -> Vec<AutoFix> {
+        let mut auto_fixes = Vec::new();
+
+        if self.date_formats.first() != Some(&parsed_formats.date) {
+            auto_fixes.push(AutoFix::AlternativeFormat {
+                field: CsvField::Date,
+                format: parsed_formats.date.clone(),
+            });
+        }
+
+        let preferred_amount_format = self.amount_formats.first().map(AmountFormat::example);
+        for (field, format) in [
+            (CsvField::AmountOut, &parsed_formats.amount_out),
+            (CsvField::AmountIn, &parsed_formats.amount_in),
+        ] {
+            if let Some(format) = format {
+                if preferred_amount_format.as_ref() != Some(format) {
+                    auto_fixes.push(AutoFix::AlternativeFormat {
+                        field,
+                        format: format.clone(),
+                    });
+                }
+            }
+        }
+
+        auto_fixes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_with_profile_formats() {
+        use crate::money::Money;
+        use crate::parsing::ValueParser;
+
+        let profile = ImportProfile::default();
+        let amount_parser = profile.amount_parser();
+        assert_eq!(
+            amount_parser.parse("1'234.50").unwrap().value,
+            Money::from_minor_units(123450)
+        );
+        assert_eq!(
+            amount_parser.parse("-1.234,5").unwrap().value,
+            Money::from_minor_units(-123450)
+        );
+
+        let profile = ImportProfile {
+            amount_formats: vec![AmountFormat {
+                decimal_separator: ',',
+                thousands_separators: vec!['.'],
+            }],
+            ..ImportProfile::default()
+        };
+        assert!(profile.amount_parser().parse("1'234,50").is_err());
+        assert!(profile.date_parser().parse("2023-02-06").is_ok());
+    }
+
+    #[test]
+    fn map_signed_amount_column() {
+        let profile = ImportProfile {
+            columns: ColumnMapping {
+                date: "Date".to_string(),
+                amounts: AmountColumns::Signed {
+                    amount: "Amount".to_string(),
+                    expenses_are_negative: false,
+                },
+                currency: None,
+                category: Some("Category".to_string()),
+                category_is_mandatory: false,
+                subcategory: None,
+                tag: None,
+                note: Some("Description".to_string()),
+                id: None,
+                account: None,
+                transfer_account: None,
+            },
+            ..ImportProfile::default()
+        };
+        let headers = StringRecord::from(vec!["Description", "Date", "Amount", "Category"]);
+        let column_indices = profile.column_indices(&headers).unwrap();
+        let transaction_csv = column_indices.to_transaction_csv(&StringRecord::from(vec![
+            "Coffee",
+            "06.02.2023",
+            "4.50",
+            "Nourriture",
+        ]));
+
+        // Expenses are positive in this file, so the amount goes out
+        assert_eq!(transaction_csv.amount_out, "4.50");
+        assert_eq!(transaction_csv.amount_in, "");
+        assert_eq!(transaction_csv.note, "Coffee");
+        assert_eq!(transaction_csv.subcategory, "");
+
+        let headers = StringRecord::from(vec!["Description", "Amount"]);
+        assert!(profile.column_indices(&headers).is_err());
+    }
+
+    #[test]
+    fn require_category_column_in_default_profile() {
+        let profile = ImportProfile::default();
+        let headers = StringRecord::from(vec!["date", "amount_out", "amount_in", "category"]);
+        assert!(profile.column_indices(&headers).is_ok());
+
+        let headers = StringRecord::from(vec!["date", "amount_out", "amount_in", "note"]);
+        assert_eq!(
+            profile.column_indices(&headers).unwrap_err(),
+            ExpenseTrackerError::CsvIo(
+                "Column 'category' is missing from the CSV header".to_string()
+            )
+        );
+    }
+}
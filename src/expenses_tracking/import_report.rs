@@ -0,0 +1,186 @@
+use std::fmt;
+
+use crate::duplicates::DuplicatePolicy;
+use crate::error::ExpenseTrackerError;
+use crate::rules::RuleMatch;
+use crate::transaction::{CsvField, CsvFieldError, Transaction};
+
+/// Whether an import stops at the first invalid row or skips it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// The import is aborted at the first invalid row, and nothing is imported.
+    Strict,
+    /// Invalid rows are skipped and listed in the `ImportReport`.
+    #[default]
+    Lenient,
+}
+
+/// The reason why a row of a CSV file was not imported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectionReason {
+    /// The row could not be read, e.g. because it does not have the same number of fields as the
+    /// header.
+    MalformedRecord(String),
+    /// A field of the row could not be parsed.
+    InvalidField(CsvFieldError),
+    /// The row was parsed but its transaction is not valid, e.g. because of its category.
+    InvalidTransaction(ExpenseTrackerError),
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectionReason::MalformedRecord(message) => {
+                write!(f, "Malformed CSV record: {message}")
+            }
+            RejectionReason::InvalidField(field_error) => write!(f, "{field_error}"),
+            RejectionReason::InvalidTransaction(error) => {
+                write!(f, "Invalid transaction: {error}")
+            }
+        }
+    }
+}
+
+/// A change made automatically such that a row could be imported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutoFix {
+    /// The category of the transaction did not exist and was created.
+    CategoryCreated(String),
+    /// The sub-category of the transaction did not exist and was created.
+    SubCategoryCreated {
+        category_name: String,
+        subcategory_name: String,
+    },
+    /// A field was parsed with another format than the preferred one of the import profile.
+    AlternativeFormat { field: CsvField, format: String },
+    /// The row had no category, which was assigned by a categorization rule.
+    CategorizedByRule(RuleMatch),
+}
+
+/// A row which looks like a transaction which was there before the import.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateMatch {
+    /// Transaction of the row.
+    pub transaction: Transaction,
+    /// Index and value of the existing transaction which the row matched.
+    pub existing_index: usize,
+    pub existing: Transaction,
+    /// What was done with the row. With `DuplicatePolicy::Ask`, the row is not imported and can
+    /// be added with `ExpenseTracker::add_transaction` if the user wants to keep it.
+    pub policy: DuplicatePolicy,
+}
+
+/// A row which was paired with a transaction of another account into a transfer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferMatch {
+    /// Transaction of the row, which was not added.
+    pub transaction: Transaction,
+    /// Index and previous value of the existing transaction which the row matched.
+    pub existing_index: usize,
+    pub existing: Transaction,
+    /// Transfer which replaced the existing transaction, keeping its ID.
+    pub transfer: Transaction,
+}
+
+/// What happened to a row of a CSV file during an import.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportOutcome {
+    Accepted,
+    AutoFixed(Vec<AutoFix>),
+    Rejected(RejectionReason),
+    Duplicate(Box<DuplicateMatch>),
+    Transfer(Box<TransferMatch>),
+}
+
+impl ImportOutcome {
+    /// Returns whether the transaction of the row was added.
+    pub fn is_imported(&self) -> bool {
+        match self {
+            ImportOutcome::Accepted | ImportOutcome::AutoFixed(_) | ImportOutcome::Transfer(_) => {
+                true
+            }
+            ImportOutcome::Rejected(_) => false,
+            ImportOutcome::Duplicate(duplicate) => duplicate.policy == DuplicatePolicy::Keep,
+        }
+    }
+}
+
+/// The outcome of the import of a single row of a CSV file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportEntry {
+    /// Line of the row in the CSV file, starting at 1.
+    pub line: u64,
+    /// Fields of the row as they were read from the file.
+    pub record: Vec<String>,
+    pub outcome: ImportOutcome,
+}
+
+/// A report listing the outcome of every row of an imported CSV file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub entries: Vec<ImportEntry>,
+}
+
+impl ImportReport {
+    /// Creates an empty `ImportReport`.
+    pub fn new() -> Self {
+        ImportReport {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the entries of the rows which were imported as they were.
+    pub fn accepted(&self) -> impl Iterator<Item = &ImportEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome == ImportOutcome::Accepted)
+    }
+
+    /// Returns the entries of the rows which were imported after being fixed automatically.
+    pub fn auto_fixed(&self) -> impl Iterator<Item = &ImportEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, ImportOutcome::AutoFixed(_)))
+    }
+
+    /// Returns the entries of the rows which were not imported.
+    pub fn rejected(&self) -> impl Iterator<Item = &ImportEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, ImportOutcome::Rejected(_)))
+    }
+
+    /// Returns the suspected duplicates, whether they were imported or not.
+    pub fn duplicates(&self) -> impl Iterator<Item = &DuplicateMatch> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.outcome {
+                ImportOutcome::Duplicate(duplicate) => Some(duplicate.as_ref()),
+                _ => None,
+            })
+    }
+
+    /// Returns the rows which were paired with transactions of other accounts into transfers.
+    pub fn transfers(&self) -> impl Iterator<Item = &TransferMatch> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.outcome {
+                ImportOutcome::Transfer(transfer) => Some(transfer.as_ref()),
+                _ => None,
+            })
+    }
+
+    /// Returns the suspected duplicates which the user must decide whether to keep.
+    pub fn pending_duplicates(&self) -> impl Iterator<Item = &DuplicateMatch> {
+        self.duplicates()
+            .filter(|duplicate| duplicate.policy == DuplicatePolicy::Ask)
+    }
+
+    /// Returns the number of rows which were imported, fixed or not.
+    pub fn n_imported(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome.is_imported())
+            .count()
+    }
+}
@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::ExpenseTrackerError;
+use crate::history::Change;
+
+/// Hash preceding the first entry of a journal.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Operation of the entries written when a journal starts on data which was not journaled.
+pub const SNAPSHOT_OPERATION: &str = "Snapshot of the project";
+
+/// A change of the data of an `ExpenseTracker`, as written in the audit journal.
+///
+/// Every entry holds the hash of the previous one, and its own hash covers all its fields, such
+/// that modifying, removing or inserting an entry breaks the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position of the entry in the journal, starting at 0.
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Who made the change, if known.
+    pub author: Option<String>,
+    /// Description of the operation which made the change, e.g. "Rename category 'Voyages'".
+    pub operation: String,
+    /// Whether the change was reverted rather than made, e.g. by an undo.
+    #[serde(default)]
+    pub reverted: bool,
+    /// The change, holding the values before and after it.
+    pub change: Change,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+impl JournalEntry {
+    /// Returns the hash of the entry, computed over every field but `hash`.
+    pub fn compute_hash(&self) -> Result<String, ExpenseTrackerError> {
+        let unhashed = JournalEntry {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let json = serde_json::to_vec(&unhashed).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to serialize a journal entry: {e}"))
+        })?;
+        Ok(Sha256::digest(json)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+}
+
+/// An append-only journal of the changes made to an `ExpenseTracker`, written as one JSON entry
+/// per line.
+#[derive(Debug)]
+pub struct Journal {
+    file_path: PathBuf,
+    author: Option<String>,
+    next_sequence: u64,
+    last_hash: String,
+}
+
+impl Journal {
+    /// Opens the journal at the given path, creating it when the first entry is appended if it
+    /// does not exist. An existing journal is verified first.
+    pub fn open(file_path: &Path, author: Option<String>) -> Result<Self, ExpenseTrackerError> {
+        let entries = if file_path.exists() {
+            read_journal(file_path)?
+        } else {
+            Vec::new()
+        };
+        Ok(Journal {
+            file_path: file_path.to_path_buf(),
+            author,
+            next_sequence: entries.len() as u64,
+            last_hash: entries
+                .last()
+                .map_or(GENESIS_HASH.to_string(), |entry| entry.hash.clone()),
+        })
+    }
+
+    /// Returns the path of the journal.
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Returns whether no entry was written to the journal yet.
+    pub fn is_empty(&self) -> bool {
+        self.next_sequence == 0
+    }
+
+    /// Returns the hash of the last entry, which can be kept elsewhere to detect that entries
+    /// were removed from the end of the journal.
+    pub fn last_hash(&self) -> &str {
+        &self.last_hash
+    }
+
+    /// Appends an entry for a change made, or reverted, by an operation.
+    pub fn append(
+        &mut self,
+        operation: &str,
+        change: &Change,
+        reverted: bool,
+    ) -> Result<(), ExpenseTrackerError> {
+        let mut entry = JournalEntry {
+            sequence: self.next_sequence,
+            timestamp: Utc::now(),
+            author: self.author.clone(),
+            operation: operation.to_string(),
+            reverted,
+            change: change.clone(),
+            previous_hash: self.last_hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+
+        let mut line = serde_json::to_string(&entry).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to serialize a journal entry: {e}"))
+        })?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file_path)
+            .map_err(|e| {
+                ExpenseTrackerError::Storage(format!("Failed to open the journal: {e}"))
+            })?;
+        file.write_all(line.as_bytes())
+            .and_then(|()| file.flush())
+            .map_err(|e| {
+                ExpenseTrackerError::Storage(format!("Failed to write to the journal: {e}"))
+            })?;
+
+        self.next_sequence += 1;
+        self.last_hash = entry.hash;
+        Ok(())
+    }
+}
+
+/// Reads the entries of a journal and verifies their chain of hashes.
+///
+/// Fails with `TamperedJournal` at the first entry which was modified, or which does not follow
+/// the previous one. Entries removed from the end of the journal can only be detected by comparing
+/// the hash of the last entry with one kept elsewhere.
+pub fn read_journal(file_path: &Path) -> Result<Vec<JournalEntry>, ExpenseTrackerError> {
+    let file = File::open(file_path)
+        .map_err(|e| ExpenseTrackerError::Storage(format!("Failed to open the journal: {e}")))?;
+
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to read the journal: {e}"))
+        })?;
+        let tampered = |reason: String| ExpenseTrackerError::TamperedJournal {
+            line: index + 1,
+            reason,
+        };
+
+        let entry: JournalEntry = serde_json::from_str(&line)
+            .map_err(|e| tampered(format!("the entry cannot be read: {e}")))?;
+        let previous_hash = entries
+            .last()
+            .map_or(GENESIS_HASH, |previous| previous.hash.as_str());
+        if entry.sequence != entries.len() as u64 {
+            return Err(tampered(format!(
+                "expected entry {} but found entry {}",
+                entries.len(),
+                entry.sequence
+            )));
+        }
+        if entry.previous_hash != previous_hash {
+            return Err(tampered(
+                "the entry does not follow the previous one".to_string(),
+            ));
+        }
+        if entry.compute_hash()? != entry.hash {
+            return Err(tampered("the entry does not match its hash".to_string()));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Returns the path of the journal written next to a project file, e.g.
+/// `expenseTrackerConfig.journal.jsonl` next to `expenseTrackerConfig.json`.
+pub fn journal_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    config_path.with_file_name(format!("{stem}.journal.jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Category;
+
+    #[test]
+    fn detect_tampered_entries() {
+        let tmp_dir = tempdir::TempDir::new("journal").unwrap();
+        let file_path = tmp_dir.path().join("journal.jsonl");
+        let mut journal = Journal::open(&file_path, Some("Eric".to_string())).unwrap();
+        for name in ["Nourriture", "Transports", "Voyages"] {
+            journal
+                .append(
+                    "Add category",
+                    &Change::RemoveCategory(Category::from_name(name)),
+                    false,
+                )
+                .unwrap();
+        }
+        let entries = read_journal(&file_path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].previous_hash, GENESIS_HASH);
+        assert_eq!(entries[2].hash, journal.last_hash());
+
+        // Reopening the journal continues the chain
+        let mut journal = Journal::open(&file_path, None).unwrap();
+        journal
+            .append(
+                "Delete category",
+                &Change::RemoveCategory(Category::from_name("Voyages")),
+                true,
+            )
+            .unwrap();
+        assert_eq!(read_journal(&file_path).unwrap().len(), 4);
+
+        let lines: Vec<String> = std::fs::read_to_string(&file_path)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        let write_lines = |lines: &[String]| {
+            std::fs::write(&file_path, lines.join("\n")).unwrap();
+        };
+
+        let mut modified = lines.clone();
+        modified[1] = modified[1].replace("Transports", "Loisirs");
+        write_lines(&modified);
+        assert!(matches!(
+            read_journal(&file_path),
+            Err(ExpenseTrackerError::TamperedJournal { line: 2, .. })
+        ));
+
+        let mut removed = lines.clone();
+        removed.remove(1);
+        write_lines(&removed);
+        assert!(matches!(
+            read_journal(&file_path),
+            Err(ExpenseTrackerError::TamperedJournal { line: 2, .. })
+        ));
+
+        write_lines(&lines);
+        assert!(read_journal(&file_path).is_ok());
+    }
+}
@@ -1,20 +1,154 @@
-use std::{error::Error, path::PathBuf, str::FromStr};
+use std::{error::Error, path::Path, path::PathBuf, str::FromStr};
 
-mod expense_tracker;
-mod transaction;
-
-use expense_tracker::ExpenseTracker;
+use expenses_tracking::expense_tracker::ExpenseTracker;
+use expenses_tracking::history::history_path;
+use expenses_tracking::import_report::ImportMode;
+use expenses_tracking::journal::{journal_path, read_journal, Journal};
+use expenses_tracking::store::MemoryStore;
+use log::{info, warn};
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Enable logging
     env_logger::init();
 
+    let config_file_path = PathBuf::from_str("config/expenseTrackerConfig.json")
+        .map_err(|e| format!("Failed to open config file at: {e}"))?;
+
+    // `undo`, `redo`, `history` and `verify-journal` work on the saved project, anything else
+    // imports transactions into it
+    match std::env::args().nth(1).as_deref() {
+        Some("undo") => undo_or_redo(&config_file_path, true),
+        Some("redo") => undo_or_redo(&config_file_path, false),
+        Some("history") => print_history(&config_file_path),
+        Some("verify-journal") => verify_journal(&config_file_path),
+        _ => import_transactions(config_file_path),
+    }
+}
+
+/// Loads the project file along with its history, if it was saved, or starts an empty project.
+/// Every change is appended to the journal next to the project file, which starts with a
+/// snapshot of the project if it was not journaled yet. Fails if the journal does not replay to
+/// the project, e.g. because the project file was changed outside of it.
+fn load_project(config_file_path: &Path) -> Result<ExpenseTracker, Box<dyn Error>> {
+    let mut expense_tracker = load_saved_project(config_file_path)?;
+    expense_tracker.set_journal(Some(Journal::open(
+        &journal_path(config_file_path),
+        std::env::var("USER").ok(),
+    )?))?;
+    Ok(expense_tracker)
+}
+
+/// Loads the project file along with its history, without attaching the journal.
+fn load_saved_project(config_file_path: &Path) -> Result<ExpenseTracker, Box<dyn Error>> {
+    let mut expense_tracker = if config_file_path.exists() {
+        ExpenseTracker::load_info_from_file(
+            config_file_path
+                .to_str()
+                .ok_or("Path of the config file is not valid UTF-8")?,
+        )?
+    } else {
+        ExpenseTracker::new()
+    };
+    let history_file_path = history_path(config_file_path);
+    if history_file_path.exists() {
+        expense_tracker.load_history_from_file(&history_file_path)?;
+    }
+    Ok(expense_tracker)
+}
+
+/// Saves the project file along with its history.
+fn save_project(
+    expense_tracker: &ExpenseTracker,
+    config_file_path: PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    expense_tracker.save_history_to_file(&history_path(&config_file_path))?;
+    expense_tracker.save_info_to_file(config_file_path)?;
+    Ok(())
+}
+
+fn undo_or_redo(config_file_path: &Path, undo: bool) -> Result<(), Box<dyn Error>> {
+    let mut expense_tracker = load_project(config_file_path)?;
+    let description = if undo {
+        expense_tracker.undo()?
+    } else {
+        expense_tracker.redo()?
+    };
+    match description {
+        Some(description) => {
+            let verb = if undo { "Undone" } else { "Redone" };
+            println!("{verb}: {description}");
+            save_project(&expense_tracker, config_file_path.to_path_buf())
+        }
+        None => {
+            println!("Nothing to {}", if undo { "undo" } else { "redo" });
+            Ok(())
+        }
+    }
+}
+
+fn print_history(config_file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let expense_tracker = load_project(config_file_path)?;
+    let history = expense_tracker.history();
+    for command in history.redo_stack() {
+        println!("  (undone) {}", command.description);
+    }
+    for command in history.undo_stack().iter().rev() {
+        println!("  {}", command.description);
+    }
+    Ok(())
+}
+
+/// Checks the chain of hashes of the journal, and that replaying it gives the saved project.
+fn verify_journal(config_file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let journal_file_path = journal_path(config_file_path);
+    let entries = read_journal(&journal_file_path)?;
+    println!(
+        "The {} entries of the journal are intact, the last hash is {}",
+        entries.len(),
+        entries.last().map_or("none", |entry| entry.hash.as_str())
+    );
+
+    let replayed = ExpenseTracker::replay_journal(MemoryStore::new(), &journal_file_path)?;
+    let expense_tracker = load_saved_project(config_file_path)?;
+    if replayed.categories() == expense_tracker.categories()
+        && replayed.transactions() == expense_tracker.transactions()
+    {
+        println!("Replaying the journal gives the saved project");
+    } else {
+        warn!(
+            "Replaying the journal does not give the saved project, which was changed \
+             outside of it"
+        );
+    }
+    Ok(())
+}
+
+fn import_transactions(config_file_path: PathBuf) -> Result<(), Box<dyn Error>> {
     // Specify the path to your CSV file
     let transactions_file_path = PathBuf::from_str("/Users/eric/Desktop/transactions_short.csv")
         .map_err(|e| format!("Failed to convert path of input transactions CSV file: {e}"))?;
 
-    let mut expense_tracker = ExpenseTracker::new();
-    expense_tracker.load_transactions_from_file(&transactions_file_path, true)?;
+    let mut expense_tracker = load_project(&config_file_path)?;
+    let import_report = expense_tracker.load_transactions_from_file(
+        &transactions_file_path,
+        true,
+        ImportMode::Lenient,
+    )?;
+    for entry in import_report.rejected() {
+        warn!("Line {} was not imported: {:?}", entry.line, entry.outcome);
+    }
+    for duplicate in import_report.duplicates() {
+        warn!(
+            "Transaction {:?} looks like existing transaction {:?}",
+            duplicate.transaction, duplicate.existing
+        );
+    }
+    for transfer in import_report.transfers() {
+        info!(
+            "Transaction {:?} was paired with existing transaction {:?} into a transfer",
+            transfer.transaction, transfer.existing
+        );
+    }
 
     //println!("{:?}", expense_tracker);
 
@@ -24,10 +158,5 @@ fn main() -> Result<(), Box<dyn Error>> {
         .write_transactions_to_file(&output_path)
         .map_err(|e| format!("Failed to write transaction to CSV file: {e}"))?;
 
-    let config_file_path = PathBuf::from_str("config/expenseTrackerConfig.json")
-        .map_err(|e| format!("Failed to open config file at: {e}"))?;
-
-    expense_tracker.save_info_to_file(config_file_path)?;
-
-    Ok(())
+    save_project(&expense_tracker, config_file_path)
 }
@@ -1,2 +1,24 @@
+pub mod account;
+pub mod category_totals;
+pub mod classifier;
+pub mod currency;
+pub mod duplicates;
+pub mod error;
+pub mod exchange_rate;
 pub mod expense_tracker;
+pub mod export;
+pub mod history;
+pub mod import_profile;
+pub mod import_report;
+pub mod journal;
+pub mod money;
+pub mod name_matching;
+pub mod parsing;
+pub mod project_file;
+pub mod reconciliation;
+pub mod rules;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod store;
 pub mod transaction;
+pub mod transfers;
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of minor units (e.g. cents) in one major unit (e.g. franc).
+pub(crate) const MINOR_UNITS_PER_MAJOR: i64 = 100;
+/// Number of decimal digits used to represent the minor units.
+const N_DECIMALS: usize = 2;
+
+/// Error of an amount which does not fit in a `Money` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountOutOfRange(pub String);
+
+impl fmt::Display for AmountOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Amount '{}' is out of range", self.0)
+    }
+}
+
+impl Error for AmountOutOfRange {}
+
+/// An exact amount of money, stored as an integer number of minor units (e.g. cents).
+///
+/// Using integers instead of floating point numbers guarantees that adding many amounts together
+/// never drifts by a few cents. It is serialized as a decimal string, e.g. `"-12.50"`.
+///
+/// There are no arithmetic operators, as amounts read from files may be as large as the type
+/// allows. The `checked_*` methods return `None` if the result is out of range instead.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct Money {
+    minor_units: i64,
+}
+
+impl Money {
+    /// An amount of zero.
+    pub const ZERO: Money = Money { minor_units: 0 };
+
+    /// Creates a `Money` object from a number of minor units, e.g. `1250` for `12.50`.
+    pub const fn from_minor_units(minor_units: i64) -> Money {
+        Money { minor_units }
+    }
+
+    /// Returns the number of minor units of the amount, e.g. `1250` for `12.50`.
+    pub const fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    /// Returns true if the amount is strictly below zero.
+    pub const fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    /// Returns the absolute value of the amount, or `None` if it is out of range.
+    pub const fn checked_abs(&self) -> Option<Money> {
+        match self.minor_units.checked_abs() {
+            Some(minor_units) => Some(Money { minor_units }),
+            None => None,
+        }
+    }
+
+    /// Returns the sum of two amounts, or `None` if it is out of range.
+    pub const fn checked_add(&self, other: Money) -> Option<Money> {
+        match self.minor_units.checked_add(other.minor_units) {
+            Some(minor_units) => Some(Money { minor_units }),
+            None => None,
+        }
+    }
+
+    /// Returns the difference of two amounts, or `None` if it is out of range.
+    pub const fn checked_sub(&self, other: Money) -> Option<Money> {
+        match self.minor_units.checked_sub(other.minor_units) {
+            Some(minor_units) => Some(Money { minor_units }),
+            None => None,
+        }
+    }
+
+    /// Returns the opposite of the amount, or `None` if it is out of range.
+    pub const fn checked_neg(&self) -> Option<Money> {
+        match self.minor_units.checked_neg() {
+            Some(minor_units) => Some(Money { minor_units }),
+            None => None,
+        }
+    }
+}
+
+impl FromStr for Money {
+    type Err = Box<dyn Error>;
+
+    /// Parses an amount such as `12`, `-12.5` or `+1234.50`.
+    ///
+    /// At most two decimals are accepted, as anything more precise cannot be represented exactly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+
+        let (is_negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+
+        let (integer_part, decimal_part) = match unsigned.split_once('.') {
+            Some((integer_part, decimal_part)) => (integer_part, decimal_part),
+            None => (unsigned, ""),
+        };
+
+        if integer_part.is_empty() && decimal_part.is_empty() {
+            return Err(format!("No digits in amount '{s}'").into());
+        }
+        if !integer_part.chars().all(|c| c.is_ascii_digit())
+            || !decimal_part.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(format!("Invalid character in amount '{s}'").into());
+        }
+        if decimal_part.len() > N_DECIMALS {
+            return Err(format!(
+                "Amount '{s}' has more than {N_DECIMALS} decimals and cannot be represented exactly"
+            )
+            .into());
+        }
+
+        let major_units: i64 = if integer_part.is_empty() {
+            0
+        } else {
+            // The part only holds digits, so it fails to parse only when it is too large
+            integer_part
+                .parse()
+                .map_err(|_| AmountOutOfRange(s.to_string()))?
+        };
+        // Pad the decimals with zeros such that "5" means 50 cents
+        let minor_digits = format!("{decimal_part:0<N_DECIMALS$}");
+        let minor_units: i64 = minor_digits
+            .parse()
+            .map_err(|e| format!("Failed to parse amount '{s}': {e}"))?;
+
+        let absolute_minor_units = major_units
+            .checked_mul(MINOR_UNITS_PER_MAJOR)
+            .and_then(|units| units.checked_add(minor_units))
+            .ok_or_else(|| AmountOutOfRange(s.to_string()))?;
+
+        Ok(Money {
+            minor_units: if is_negative {
+                -absolute_minor_units
+            } else {
+                absolute_minor_units
+            },
+        })
+    }
+}
+
+impl TryFrom<String> for Money {
+    type Error = Box<dyn Error>;
+
+    fn try_from(amount: String) -> Result<Self, Self::Error> {
+        amount.parse()
+    }
+}
+
+impl From<Money> for String {
+    fn from(amount: Money) -> Self {
+        amount.to_string()
+    }
+}
+
+impl fmt::Display for Money {
+    /// Formats the amount with exactly two decimals, e.g. `-12.50`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let absolute_minor_units = self.minor_units.unsigned_abs();
+        let major_units = absolute_minor_units / MINOR_UNITS_PER_MAJOR as u64;
+        let minor_units = absolute_minor_units % MINOR_UNITS_PER_MAJOR as u64;
+        write!(f, "{sign}{major_units}.{minor_units:0N_DECIMALS$}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let cases = [
+            ("12", "12.00"),
+            ("12.5", "12.50"),
+            ("12.05", "12.05"),
+            ("-0.5", "-0.50"),
+            ("+3.90", "3.90"),
+            (".75", "0.75"),
+            ("330.08", "330.08"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Money::from_str(input).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_amounts() {
+        for input in ["", "-", ".", "12.345", "1,50", "abc", "1.2.3"] {
+            assert!(
+                Money::from_str(input).is_err(),
+                "'{input}' should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn sum_is_exact() {
+        // 0.1 cannot be represented exactly as a floating point number, so summing it many times
+        // drifts with f32 but must not with `Money`
+        let amount = Money::from_str("0.10").unwrap();
+        let total = std::iter::repeat_n(amount, 100_000)
+            .try_fold(Money::ZERO, |total, amount| total.checked_add(amount))
+            .unwrap();
+        assert_eq!(total, Money::from_minor_units(1_000_000));
+    }
+
+    #[test]
+    fn arithmetic_and_comparison() {
+        let a = Money::from_str("10.20").unwrap();
+        let b = Money::from_str("3.25").unwrap();
+        let difference = a.checked_sub(b).unwrap();
+        assert_eq!(difference.to_string(), "6.95");
+        assert_eq!(b.checked_sub(a).unwrap().to_string(), "-6.95");
+        assert_eq!(a.checked_neg(), Some(Money::from_minor_units(-1020)));
+        assert_eq!(a.checked_add(b), Some(Money::from_minor_units(1345)));
+        assert!(b < a);
+        assert!(b.checked_sub(a).unwrap().is_negative());
+        assert_eq!(b.checked_sub(a).unwrap().checked_abs(), Some(difference));
+    }
+
+    #[test]
+    fn amounts_out_of_range() {
+        let min = Money::from_minor_units(i64::MIN);
+        assert_eq!(min.checked_neg(), None);
+        assert_eq!(min.checked_abs(), None);
+        assert_eq!(min.checked_sub(Money::from_minor_units(1)), None);
+        assert_eq!(
+            Money::from_minor_units(i64::MAX).checked_add(Money::from_minor_units(1)),
+            None
+        );
+        assert_eq!(
+            min.checked_add(Money::from_minor_units(1)),
+            Some(Money::from_minor_units(i64::MIN + 1))
+        );
+
+        let error = Money::from_str("-100000000000000000000").unwrap_err();
+        assert!(error.is::<AmountOutOfRange>());
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// How the names of categories and sub-categories are compared.
+///
+/// Names are always compared after Unicode normalization, ignoring case and repeated whitespace,
+/// such that "Café" typed with a combining accent matches "café". Accents can also be ignored,
+/// such that "Activites" matches "Activités".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameMatching {
+    /// Whether accents and other combining marks are ignored.
+    #[serde(default)]
+    pub fold_accents: bool,
+}
+
+impl NameMatching {
+    /// Returns the key under which a name is compared to other names.
+    pub fn key(self, name: &str) -> String {
+        let name = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+        name.nfkd()
+            .filter(|c| !(self.fold_accents && is_combining_mark(*c)))
+            .flat_map(char::to_lowercase)
+            .nfc()
+            .collect()
+    }
+
+    /// Returns true if two names are considered the same.
+    pub fn matches(self, name: &str, other_name: &str) -> bool {
+        self.key(name) == self.key(other_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_names() {
+        let matching = NameMatching::default();
+        // "é" as a single code point and as "e" followed by a combining accent
+        assert!(matching.matches("Activit\u{e9}s", "activite\u{301}s"));
+        assert!(matching.matches("  Billets  de train ", "billets de train"));
+        assert!(!matching.matches("Activités", "Activites"));
+
+        let matching = NameMatching { fold_accents: true };
+        assert!(matching.matches("Activités", "ACTIVITES"));
+        assert_eq!(matching.key("Dépenses persos"), "depenses persos");
+        assert!(!matching.matches("Activités", "Activité"));
+    }
+}
@@ -0,0 +1,368 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::money::{AmountOutOfRange, Money};
+
+/// A value parsed from text, along with the description of the format which matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub format: String,
+}
+
+/// Something able to parse a value such as a date or an amount out of text.
+pub trait ValueParser<T> {
+    fn parse(&self, input: &str) -> Result<Parsed<T>, Box<dyn Error>>;
+}
+
+/// The formats which matched when parsing the fields of a CSV transaction.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedFormats {
+    pub date: String,
+    /// The format of the amount going out, or `None` if the amount was empty.
+    pub amount_out: Option<String>,
+    /// The format of the amount coming in, or `None` if the amount was empty.
+    pub amount_in: Option<String>,
+}
+
+/// Tries every format on an input and returns the value of the first one which matched.
+///
+/// Fails if no format matched, or if several formats matched but gave different values, in which
+/// case the input is ambiguous.
+fn parse_with_formats<T, F>(
+    input: &str,
+    kind: &str,
+    formats: impl Iterator<Item = (String, F)>,
+) -> Result<Parsed<T>, Box<dyn Error>>
+where
+    T: PartialEq + Display,
+    F: Fn(&str) -> Result<T, Box<dyn Error>>,
+{
+    let mut matches: Vec<Parsed<T>> = Vec::new();
+    let mut failures: Vec<String> = Vec::new();
+    let mut out_of_range = None;
+
+    for (format, parse) in formats {
+        match parse(input) {
+            Ok(value) => matches.push(Parsed { value, format }),
+            Err(e) if e.is::<AmountOutOfRange>() => out_of_range = Some(e),
+            Err(e) => failures.push(format!("'{format}' ({e})")),
+        }
+    }
+
+    let mut matches = matches.into_iter();
+    let Some(first_match) = matches.next() else {
+        // A value read by a format but too large to be represented is reported as such, rather
+        // than among the formats which do not match
+        if let Some(e) = out_of_range {
+            return Err(e);
+        }
+        if failures.is_empty() {
+            return Err(format!("No format configured to parse {kind} '{input}'").into());
+        }
+        return Err(format!(
+            "{kind} '{input}' does not match any format: {}",
+            failures.join(", ")
+        )
+        .into());
+    };
+
+    if let Some(conflicting_match) = matches.find(|other| other.value != first_match.value) {
+        return Err(format!(
+            "{kind} '{input}' is ambiguous: it is {} with format '{}' but {} with format '{}'",
+            first_match.value,
+            first_match.format,
+            conflicting_match.value,
+            conflicting_match.format
+        )
+        .into());
+    }
+
+    Ok(first_match)
+}
+
+/// Parses dates by trying a list of `chrono` formats in order, e.g. `%d.%m.%Y` or `%Y-%m-%d`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateParser {
+    formats: Vec<String>,
+}
+
+impl DateParser {
+    /// Creates a `DateParser` from `chrono` formats, see `chrono::format::strftime` for the syntax.
+    pub fn new(formats: Vec<String>) -> Self {
+        DateParser { formats }
+    }
+}
+
+impl ValueParser<NaiveDate> for DateParser {
+    fn parse(&self, input: &str) -> Result<Parsed<NaiveDate>, Box<dyn Error>> {
+        let trimmed = input.trim();
+        parse_with_formats(
+            trimmed,
+            "Date",
+            self.formats.iter().map(|format| {
+                let parse = move |input: &str| -> Result<NaiveDate, Box<dyn Error>> {
+                    Ok(NaiveDate::parse_from_str(input, format)?)
+                };
+                (format.clone(), parse)
+            }),
+        )
+    }
+}
+
+/// A way of writing amounts, defined by its decimal and thousands separators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountFormat {
+    pub decimal_separator: char,
+    /// Characters allowed between groups of three digits, e.g. `'` in `1'234.50`.
+    #[serde(default)]
+    pub thousands_separators: Vec<char>,
+}
+
+impl AmountFormat {
+    /// Returns an example of an amount in this format, e.g. `1'234.50`.
+    pub fn example(&self) -> String {
+        match self.thousands_separators.first() {
+            Some(thousands_separator) => {
+                format!("1{thousands_separator}234{}50", self.decimal_separator)
+            }
+            None => format!("1234{}50", self.decimal_separator),
+        }
+    }
+
+    /// Parses an unsigned amount made only of digits and of the separators of this format.
+    fn parse_unsigned(&self, input: &str) -> Result<Money, Box<dyn Error>> {
+        let (integer_part, decimal_part) = match input.split_once(self.decimal_separator) {
+            Some((integer_part, decimal_part)) => (integer_part, decimal_part),
+            None => (input, ""),
+        };
+
+        if !decimal_part.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid decimals '{decimal_part}'").into());
+        }
+
+        let groups: Vec<&str> = integer_part
+            .split(|c| self.thousands_separators.contains(&c))
+            .collect();
+        if !groups
+            .iter()
+            .all(|group| group.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(format!("Invalid character in '{integer_part}'").into());
+        }
+        // Thousands separators must split the integer part into groups of exactly three digits,
+        // which prevents reading a decimal separator as a thousands one
+        if groups.len() > 1
+            && (groups[0].is_empty()
+                || groups[0].len() > 3
+                || groups[1..].iter().any(|group| group.len() != 3))
+        {
+            return Err(format!("Misplaced thousands separator in '{integer_part}'").into());
+        }
+
+        format!("{}.{decimal_part}", groups.concat()).parse()
+    }
+}
+
+/// Parses amounts by trying a list of `AmountFormat` in order.
+///
+/// Signs, parentheses for negative amounts and currency codes or symbols around the amount are
+/// accepted with every format, e.g. `-12.00`, `12.00-`, `(12.00)` or `CHF 12.00`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountParser {
+    formats: Vec<AmountFormat>,
+}
+
+impl AmountParser {
+    /// Creates an `AmountParser` from a list of formats.
+    pub fn new(formats: Vec<AmountFormat>) -> Self {
+        AmountParser { formats }
+    }
+}
+
+/// Removes currency codes and symbols at both ends of an amount.
+fn strip_currency(input: &str) -> &str {
+    let is_currency_char = |c: char| c.is_alphabetic() || matches!(c, '€' | '$' | '£' | '¥');
+    input
+        .trim_matches(|c: char| is_currency_char(c) || c.is_whitespace())
+        .trim()
+}
+
+/// Splits the sign from an amount and returns true if the amount is negative.
+fn split_sign(input: &str) -> Result<(bool, &str), Box<dyn Error>> {
+    let mut is_negative = false;
+    let mut unsigned = input;
+
+    // Accounting notation writes negative amounts in parentheses
+    if let Some(inner) = unsigned
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        is_negative = true;
+        unsigned = strip_currency(inner);
+    }
+
+    let mut n_signs = 0;
+    if let Some(rest) = unsigned.strip_prefix('-') {
+        is_negative = !is_negative;
+        unsigned = rest;
+        n_signs += 1;
+    } else if let Some(rest) = unsigned.strip_prefix('+') {
+        unsigned = rest;
+        n_signs += 1;
+    }
+    // Some banks put the minus sign after the amount
+    if let Some(rest) = unsigned.strip_suffix('-') {
+        is_negative = !is_negative;
+        unsigned = rest;
+        n_signs += 1;
+    }
+    if n_signs > 1 {
+        return Err(format!("Amount '{input}' has more than one sign").into());
+    }
+
+    Ok((is_negative, strip_currency(unsigned)))
+}
+
+impl ValueParser<Money> for AmountParser {
+    fn parse(&self, input: &str) -> Result<Parsed<Money>, Box<dyn Error>> {
+        // Non-breaking spaces are commonly used as thousands separators
+        let normalized = input.trim().replace(['\u{a0}', '\u{202f}'], " ");
+        let (is_negative, unsigned) = split_sign(strip_currency(&normalized))?;
+        if unsigned.is_empty() {
+            return Err(format!("No digits in amount '{input}'").into());
+        }
+
+        let parsed = parse_with_formats(
+            unsigned,
+            "Amount",
+            self.formats.iter().map(|format| {
+                let parse = move |input: &str| format.parse_unsigned(input);
+                (format.example(), parse)
+            }),
+        )?;
+
+        let value = if is_negative {
+            parsed
+                .value
+                .checked_neg()
+                .ok_or_else(|| AmountOutOfRange(input.to_string()))?
+        } else {
+            parsed.value
+        };
+        Ok(Parsed {
+            value,
+            format: parsed.format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_amount_parser() -> AmountParser {
+        AmountParser::new(vec![
+            AmountFormat {
+                decimal_separator: '.',
+                thousands_separators: vec!['\'', ',', ' '],
+            },
+            AmountFormat {
+                decimal_separator: ',',
+                thousands_separators: vec!['.', ' ', '\''],
+            },
+        ])
+    }
+
+    #[test]
+    fn parse_amounts_in_various_formats() {
+        let amount_parser = default_amount_parser();
+        let cases = [
+            ("1 234,50", 123450, "1.234,50"),
+            ("1,234.50", 123450, "1'234.50"),
+            ("1'234.50", 123450, "1'234.50"),
+            ("CHF 12.00", 1200, "1'234.50"),
+            ("12.00 EUR", 1200, "1'234.50"),
+            ("-12.00", -1200, "1'234.50"),
+            ("12.00-", -1200, "1'234.50"),
+            ("(12.00)", -1200, "1'234.50"),
+            ("(CHF 12,5)", -1250, "1.234,50"),
+            ("1\u{a0}000", 100000, "1'234.50"),
+            ("7", 700, "1'234.50"),
+        ];
+        for (input, minor_units, format) in cases {
+            let parsed = amount_parser.parse(input).unwrap();
+            assert_eq!(
+                parsed.value,
+                Money::from_minor_units(minor_units),
+                "'{input}'"
+            );
+            assert_eq!(parsed.format, format, "'{input}'");
+        }
+    }
+
+    #[test]
+    fn parse_invalid_amounts() {
+        let amount_parser = default_amount_parser();
+        for input in ["", "CHF", "12,34,5", "1.2.3", "--12", "12..0", "1 23"] {
+            assert!(amount_parser.parse(input).is_err(), "'{input}'");
+        }
+        for input in ["100'000'000'000'000'000'000.00", "-92233720368547758.08"] {
+            let error = amount_parser.parse(input).unwrap_err();
+            assert!(error.is::<AmountOutOfRange>(), "'{input}': {error}");
+        }
+    }
+
+    #[test]
+    fn parse_amount_with_a_single_interpretation() {
+        // "1.234" could be read with a decimal point, but amounts have at most two decimals, so
+        // the dot can only be a thousands separator
+        let amount_parser = default_amount_parser();
+        let parsed = amount_parser.parse("1.234").unwrap();
+        assert_eq!(parsed.value, Money::from_minor_units(123400));
+        assert_eq!(parsed.format, "1.234,50");
+
+        // "12.50" could be read with a thousands separator, but it is followed by two digits only
+        let parsed = amount_parser.parse("12.50").unwrap();
+        assert_eq!(parsed.value, Money::from_minor_units(1250));
+        assert_eq!(parsed.format, "1'234.50");
+    }
+
+    #[test]
+    fn parse_dates_in_various_formats() {
+        let date_parser = DateParser::new(vec!["%d.%m.%Y".to_string(), "%Y-%m-%d".to_string()]);
+        let expected = NaiveDate::from_ymd_opt(2023, 4, 3).unwrap();
+
+        let parsed = date_parser.parse("03.04.2023").unwrap();
+        assert_eq!(parsed.value, expected);
+        assert_eq!(parsed.format, "%d.%m.%Y");
+
+        let parsed = date_parser.parse("2023-04-03").unwrap();
+        assert_eq!(parsed.value, expected);
+        assert_eq!(parsed.format, "%Y-%m-%d");
+
+        let error = date_parser.parse("03/04/2023").unwrap_err().to_string();
+        assert!(error.contains("does not match any format"), "{error}");
+    }
+
+    #[test]
+    fn parse_ambiguous_date() {
+        let date_parser = DateParser::new(vec!["%d.%m.%Y".to_string(), "%m.%d.%Y".to_string()]);
+
+        let error = date_parser.parse("03.04.2023").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Date '03.04.2023' is ambiguous: it is 2023-04-03 with format '%d.%m.%Y' but \
+             2023-03-04 with format '%m.%d.%Y'"
+        );
+
+        // A day above 12 cannot be a month, so there is no ambiguity
+        let parsed = date_parser.parse("13.04.2023").unwrap();
+        assert_eq!(parsed.value, NaiveDate::from_ymd_opt(2023, 4, 13).unwrap());
+
+        // Both formats agree on the same day and month
+        assert!(date_parser.parse("04.04.2023").is_ok());
+    }
+}
@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::account::Account;
+use crate::currency::Currency;
+use crate::duplicates::DuplicateDetection;
+use crate::error::ExpenseTrackerError;
+use crate::import_profile::ImportProfile;
+use crate::name_matching::NameMatching;
+use crate::rules::CategorizationRule;
+use crate::transaction::{Category, Transaction};
+use crate::transfers::TransferMatching;
+
+/// Version of the layout of the project file written by `ExpenseTracker::save_info_to_file`.
+///
+/// - Version 0 is the layout of files without a version, which only held categories and settings.
+/// - Version 1 also holds the transactions, and the version next to them.
+/// - Version 2 wraps the data in an envelope holding the version.
+/// - Version 3 allows sub-categories to hold sub-categories, which older versions would drop.
+/// - Version 4 gives each side of a transfer its own cleared state.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Upgrades the data of a project file from one version to the next, the migration at index `n`
+/// upgrading from version `n` to version `n + 1`.
+type Migration = fn(Map<String, Value>) -> Result<Map<String, Value>, ExpenseTrackerError>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    add_empty_transactions,
+    move_version_into_envelope,
+    keep_subcategories_as_leaves,
+    clear_both_sides_of_transfers,
+];
+
+fn add_empty_transactions(
+    mut data: Map<String, Value>,
+) -> Result<Map<String, Value>, ExpenseTrackerError> {
+    data.entry("transactions")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    Ok(data)
+}
+
+fn move_version_into_envelope(
+    mut data: Map<String, Value>,
+) -> Result<Map<String, Value>, ExpenseTrackerError> {
+    // The envelope is built when the file is written, the version only has to leave the data
+    data.remove("schema_version");
+    Ok(data)
+}
+
+/// Reads files of version 2 as they are: their sub-categories become the leaves of the tree, as
+/// `SubCategory::subcategories` defaults to an empty set.
+///
+/// The version is still bumped so that older versions of the program refuse files with nested
+/// sub-categories instead of dropping them, and upgrading a file keeps a backup they can read.
+fn keep_subcategories_as_leaves(
+    data: Map<String, Value>,
+) -> Result<Map<String, Value>, ExpenseTrackerError> {
+    Ok(data)
+}
+
+/// Gives the receiving side of every transfer the cleared state of the transfer, which applied to
+/// both of its accounts in version 3.
+fn clear_both_sides_of_transfers(
+    mut data: Map<String, Value>,
+) -> Result<Map<String, Value>, ExpenseTrackerError> {
+    if let Some(Value::Array(transactions)) = data.get_mut("transactions") {
+        for transaction in transactions.iter_mut().filter_map(Value::as_object_mut) {
+            let is_transfer = transaction
+                .get("transfer_account")
+                .is_some_and(|account| !account.is_null());
+            if let Some(cleared) = transaction.get("cleared").filter(|_| is_transfer) {
+                let cleared = cleared.clone();
+                transaction.insert("transfer_cleared".to_string(), cleared);
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// The versioned envelope around the data of a project file.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    schema_version: u32,
+    data: T,
+}
+
+/// The content of the project file, borrowed when saving and owned when loading.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ProjectFile<'a> {
+    pub(crate) valid_categories: Cow<'a, BTreeSet<Category>>,
+    #[serde(default)]
+    pub(crate) import_profiles: Cow<'a, BTreeMap<String, ImportProfile>>,
+    #[serde(default)]
+    pub(crate) default_currency: Currency,
+    #[serde(default)]
+    pub(crate) reporting_currency: Option<Currency>,
+    #[serde(default)]
+    pub(crate) name_matching: NameMatching,
+    #[serde(default)]
+    pub(crate) categorization_rules: Cow<'a, [CategorizationRule]>,
+    #[serde(default)]
+    pub(crate) duplicate_detection: DuplicateDetection,
+    #[serde(default)]
+    pub(crate) transfer_matching: TransferMatching,
+    #[serde(default)]
+    pub(crate) accounts: Cow<'a, [Account]>,
+    #[serde(default)]
+    pub(crate) transactions: Cow<'a, [Transaction]>,
+}
+
+fn config_error(context: &str) -> impl Fn(std::io::Error) -> ExpenseTrackerError + '_ {
+    move |e| ExpenseTrackerError::Config(format!("{context}: {e}"))
+}
+
+/// Splits the content of a project file into its data and its schema version.
+///
+/// Files older than version 2 have no envelope, their version is next to the data if any.
+fn split_envelope(value: Value) -> Result<(u32, Map<String, Value>), ExpenseTrackerError> {
+    let Value::Object(mut object) = value else {
+        return Err(ExpenseTrackerError::Config(
+            "Config file does not contain a JSON object".to_string(),
+        ));
+    };
+
+    let schema_version = match object.get("schema_version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| {
+                ExpenseTrackerError::Config(format!("Invalid schema version {version}"))
+            })?,
+    };
+
+    if schema_version >= 2 {
+        match object.remove("data") {
+            Some(Value::Object(data)) => Ok((schema_version, data)),
+            _ => Err(ExpenseTrackerError::Config(format!(
+                "Config file with schema version {schema_version} has no data object"
+            ))),
+        }
+    } else {
+        Ok((schema_version, object))
+    }
+}
+
+/// Returns the schema version of a project file.
+fn read_schema_version(file_path: &Path) -> Result<u32, ExpenseTrackerError> {
+    let file = File::open(file_path).map_err(config_error("Failed to open config file"))?;
+    let value: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| ExpenseTrackerError::Config(format!("Failed to read config file: {e}")))?;
+    Ok(split_envelope(value)?.0)
+}
+
+/// Reads a project file and upgrades it in memory to the current schema version.
+pub(crate) fn read_project_file(
+    file_path: &Path,
+) -> Result<ProjectFile<'static>, ExpenseTrackerError> {
+    let file = File::open(file_path).map_err(config_error("Failed to open config file"))?;
+    let value: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| ExpenseTrackerError::Config(format!("Failed to read config file: {e}")))?;
+
+    let (schema_version, mut data) = split_envelope(value)?;
+    if schema_version > SCHEMA_VERSION {
+        return Err(ExpenseTrackerError::Config(format!(
+            "Config file has schema version {schema_version}, but only versions up to \
+             {SCHEMA_VERSION} are supported"
+        )));
+    }
+    for migration in &MIGRATIONS[schema_version as usize..] {
+        data = migration(data)?;
+    }
+
+    serde_json::from_value(Value::Object(data)).map_err(|e| {
+        ExpenseTrackerError::Config(format!("Failed to read project from config: {e}"))
+    })
+}
+
+/// Returns the path of the backup of a project file written with an older schema version.
+pub fn backup_path(file_path: &Path, schema_version: u32) -> PathBuf {
+    let mut backup_path = file_path.as_os_str().to_owned();
+    backup_path.push(format!(".v{schema_version}.bak"));
+    PathBuf::from(backup_path)
+}
+
+/// Writes a project file with the current schema version.
+///
+/// If the file being replaced was written with an older schema version, it is first copied next
+/// to itself, see `backup_path`, such that it can still be opened by older versions.
+pub(crate) fn write_project_file(
+    file_path: &Path,
+    project_file: &ProjectFile,
+) -> Result<(), ExpenseTrackerError> {
+    if file_path.exists() {
+        // A file which cannot be read is backed up as well, as it cannot be upgraded
+        let previous_version = read_schema_version(file_path).unwrap_or(0);
+        if previous_version < SCHEMA_VERSION {
+            std::fs::copy(file_path, backup_path(file_path, previous_version))
+                .map_err(config_error("Failed to back up config file"))?;
+        }
+    }
+
+    // The file is written next to the original one, which is only replaced once it is complete
+    let mut temporary_path = file_path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+
+    let file =
+        File::create(&temporary_path).map_err(config_error("Failed to create config file"))?;
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        data: project_file,
+    };
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &envelope).map_err(|e| {
+        ExpenseTrackerError::Config(format!("Failed to write project to config: {e}"))
+    })?;
+    writer
+        .flush()
+        .map_err(config_error("Failed to write project to config"))?;
+    std::fs::rename(&temporary_path, file_path)
+        .map_err(config_error("Failed to replace config file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATEGORIES_V0: &str = concat!(
+        r#"{"valid_categories": "#,
+        r#"[{"name": "nourriture", "subcategories": [], "date_added": "2023-02-06"}]"#
+    );
+
+    #[test]
+    fn upgrade_older_project_files() {
+        let tmp_dir = tempdir::TempDir::new("project").unwrap();
+        let config_path = tmp_dir.path().join("expenseTrackerConfig.json");
+
+        for content in [
+            format!("{CATEGORIES_V0}}}"),
+            format!(r#"{CATEGORIES_V0}, "schema_version": 1, "transactions": []}}"#),
+            format!(r#"{{"schema_version": 2, "data": {CATEGORIES_V0}}}}}"#),
+            format!(r#"{{"schema_version": 3, "data": {CATEGORIES_V0}}}}}"#),
+            format!(r#"{{"schema_version": 4, "data": {CATEGORIES_V0}}}}}"#),
+        ] {
+            std::fs::write(&config_path, &content).unwrap();
+            let project_file = read_project_file(&config_path).unwrap();
+            assert_eq!(project_file.valid_categories.len(), 1, "{content}");
+            assert!(project_file.transactions.is_empty(), "{content}");
+        }
+
+        std::fs::write(&config_path, r#"{"schema_version": 5, "data": {}}"#).unwrap();
+        assert!(matches!(
+            read_project_file(&config_path),
+            Err(ExpenseTrackerError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn keep_cleared_state_of_older_transfers_on_both_sides() {
+        let data = serde_json::json!({
+            "transactions": [
+                {"transfer_account": "Épargne", "cleared": "Reconciled"},
+                {"transfer_account": null, "cleared": "Cleared"},
+            ]
+        });
+        let Value::Object(data) = data else {
+            unreachable!()
+        };
+        let data = clear_both_sides_of_transfers(data).unwrap();
+        assert_eq!(data["transactions"][0]["transfer_cleared"], "Reconciled");
+        assert!(data["transactions"][1].get("transfer_cleared").is_none());
+    }
+
+    #[test]
+    fn back_up_older_project_file_before_overwriting_it() {
+        let tmp_dir = tempdir::TempDir::new("project").unwrap();
+        let config_path = tmp_dir.path().join("expenseTrackerConfig.json");
+        let original_content = format!(r#"{CATEGORIES_V0}, "schema_version": 1}}"#);
+        std::fs::write(&config_path, &original_content).unwrap();
+
+        let project_file = read_project_file(&config_path).unwrap();
+        write_project_file(&config_path, &project_file).unwrap();
+
+        let backup_path = backup_path(&config_path, 1);
+        assert_eq!(
+            std::fs::read_to_string(&backup_path).unwrap(),
+            original_content
+        );
+        assert_eq!(read_schema_version(&config_path).unwrap(), SCHEMA_VERSION);
+
+        // Files already in the current version are not backed up again
+        std::fs::remove_file(&backup_path).unwrap();
+        write_project_file(&config_path, &project_file).unwrap();
+        assert!(!backup_path.exists());
+    }
+}
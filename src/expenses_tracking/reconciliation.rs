@@ -0,0 +1,167 @@
+use chrono::NaiveDate;
+
+use crate::account::Account;
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::name_matching::NameMatching;
+use crate::transaction::{ClearedState, Transaction};
+
+/// The comparison of the balance of an account with the closing balance of a statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation {
+    pub account_name: String,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+    /// Balance of the account at the end of the statement date, from all its transactions.
+    pub ledger_balance: Money,
+    /// Balance from the cleared and reconciled transactions only, which must be equal to the
+    /// statement balance for the account to be reconciled.
+    pub cleared_balance: Money,
+    /// Transactions of the account up to the statement date which are not cleared, in order of
+    /// date.
+    pub uncleared: Vec<Transaction>,
+}
+
+impl Reconciliation {
+    /// Compares the balance of an account at the end of a date with the closing balance of a
+    /// statement. Transactions of other accounts are left out.
+    ///
+    /// Fails if the date is before the opening date of the account.
+    pub fn new<'a>(
+        account: &Account,
+        transactions: impl IntoIterator<Item = &'a Transaction> + Clone,
+        statement_date: NaiveDate,
+        statement_balance: Money,
+        matching: NameMatching,
+    ) -> Result<Self, ExpenseTrackerError> {
+        let before_opening = || {
+            ExpenseTrackerError::Reconciliation(format!(
+                "the statement of {statement_date} is before the opening of '{}'",
+                account.name
+            ))
+        };
+        let ledger_balance = account
+            .balance_on(transactions.clone(), statement_date, matching)?
+            .ok_or_else(before_opening)?;
+        let cleared_balance = account
+            .balance_on(
+                transactions.clone().into_iter().filter(|transaction| {
+                    account
+                        .cleared_state(transaction, matching)
+                        .is_some_and(|cleared| cleared != ClearedState::Uncleared)
+                }),
+                statement_date,
+                matching,
+            )?
+            .ok_or_else(before_opening)?;
+
+        let mut uncleared: Vec<Transaction> = transactions
+            .into_iter()
+            .filter(|transaction| {
+                account.cleared_state(transaction, matching) == Some(ClearedState::Uncleared)
+                    && (account.opening_date..=statement_date).contains(&transaction.date)
+            })
+            .cloned()
+            .collect();
+        uncleared.sort_by_key(|transaction| transaction.date);
+
+        Ok(Reconciliation {
+            account_name: account.name.clone(),
+            statement_date,
+            statement_balance,
+            ledger_balance,
+            cleared_balance,
+            uncleared,
+        })
+    }
+
+    /// Returns the statement balance minus the ledger balance, which the uncleared transactions
+    /// or missing ones account for.
+    pub fn difference(&self) -> Result<Money, ExpenseTrackerError> {
+        self.statement_balance
+            .checked_sub(self.ledger_balance)
+            .ok_or_else(|| {
+                ExpenseTrackerError::AmountOutOfRange(format!(
+                    "difference with the statement of '{}'",
+                    self.account_name
+                ))
+            })
+    }
+
+    /// Returns whether the cleared transactions match the statement.
+    pub fn is_balanced(&self) -> bool {
+        self.cleared_balance == self.statement_balance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountKind;
+    use crate::currency::Currency;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 2, day).unwrap()
+    }
+
+    fn transaction(day: u32, amount: i64, cleared: ClearedState) -> Transaction {
+        let mut transaction = Transaction::new();
+        transaction.date = date(day);
+        transaction.amount = Money::from_minor_units(amount);
+        transaction.account = Some("Compte courant".to_string());
+        transaction.cleared = cleared;
+        transaction
+    }
+
+    #[test]
+    fn compare_balances_with_statement() {
+        let account = Account::new(
+            "Compte courant",
+            AccountKind::Current,
+            Currency::CHF,
+            Money::from_minor_units(100_000),
+            date(1),
+        );
+        let transactions = [
+            transaction(10, -2_000, ClearedState::Uncleared),
+            transaction(3, -5_000, ClearedState::Reconciled),
+            transaction(6, -1_500, ClearedState::Cleared),
+            transaction(4, -700, ClearedState::Uncleared),
+            // After the statement
+            transaction(20, -300, ClearedState::Uncleared),
+        ];
+
+        let reconciliation = Reconciliation::new(
+            &account,
+            &transactions,
+            date(15),
+            Money::from_minor_units(93_500),
+            NameMatching::default(),
+        )
+        .unwrap();
+        assert_eq!(reconciliation.ledger_balance.minor_units(), 90_800);
+        assert_eq!(reconciliation.cleared_balance.minor_units(), 93_500);
+        assert_eq!(
+            reconciliation.difference(),
+            Ok(Money::from_minor_units(2_700))
+        );
+        assert!(reconciliation.is_balanced());
+        let uncleared_ids: Vec<_> = reconciliation
+            .uncleared
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        assert_eq!(uncleared_ids, [transactions[3].id, transactions[0].id]);
+
+        assert!(matches!(
+            Reconciliation::new(
+                &account,
+                &transactions,
+                NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+                Money::ZERO,
+                NameMatching::default(),
+            ),
+            Err(ExpenseTrackerError::Reconciliation(_))
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::transaction::Transaction;
+
+/// The conditions of a `CategorizationRule`, which must all hold for the rule to match.
+///
+/// Conditions which are not set always hold. The description of a transaction is its note, which
+/// is where import profiles put the payee or description of bank exports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleConditions {
+    /// Text which the description must contain, ignoring case.
+    #[serde(default)]
+    pub description_contains: Option<String>,
+    /// Regular expression which must match a part of the description, ignoring case.
+    #[serde(default)]
+    pub description_regex: Option<String>,
+    /// Smallest amount, included. Expenses are negative.
+    #[serde(default)]
+    pub min_amount: Option<Money>,
+    /// Largest amount, included. Expenses are negative.
+    #[serde(default)]
+    pub max_amount: Option<Money>,
+    /// First date, included.
+    #[serde(default)]
+    pub start_date: Option<NaiveDate>,
+    /// Last date, included.
+    #[serde(default)]
+    pub end_date: Option<NaiveDate>,
+    /// Days of the week on which the transaction must be, any day if empty.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+/// A rule which assigns a category, and optionally a sub-category and a tag, to the
+/// transactions matching its conditions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategorizationRule {
+    /// Name of the rule, which explains why a transaction was categorized.
+    pub name: String,
+    /// Rules with a higher priority are tried first, and rules with the same priority are tried
+    /// in the order in which they were added.
+    #[serde(default)]
+    pub priority: i32,
+    pub conditions: RuleConditions,
+    /// Category assigned to the transaction, which may be a path such as
+    /// "voyages/nourriture/restaurant".
+    pub category_name: String,
+    #[serde(default)]
+    pub subcategory_name: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// The rule which matched a transaction, along with the conditions which held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub rule_name: String,
+    pub priority: i32,
+    /// Descriptions of the conditions of the rule, e.g. "description contains 'migros'".
+    pub matched_conditions: Vec<String>,
+}
+
+impl fmt::Display for RuleMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rule '{}'", self.rule_name)?;
+        if self.matched_conditions.is_empty() {
+            return write!(f, " matches every transaction");
+        }
+        write!(f, " matched: {}", self.matched_conditions.join(", "))
+    }
+}
+
+/// A rule whose regular expression is compiled.
+#[derive(Debug)]
+struct CompiledRule {
+    rule: CategorizationRule,
+    description_regex: Option<Regex>,
+}
+
+impl CompiledRule {
+    /// Returns the descriptions of the conditions of the rule if they all hold for a transaction.
+    fn matched_conditions(&self, transaction: &Transaction) -> Option<Vec<String>> {
+        let conditions = &self.rule.conditions;
+        let description = transaction.note.as_deref().unwrap_or_default();
+        let mut matched_conditions = Vec::new();
+
+        if let Some(text) = &conditions.description_contains {
+            if !description.to_lowercase().contains(&text.to_lowercase()) {
+                return None;
+            }
+            matched_conditions.push(format!("description contains '{text}'"));
+        }
+        if let Some(regex) = &self.description_regex {
+            if !regex.is_match(description) {
+                return None;
+            }
+            matched_conditions.push(format!("description matches '{}'", regex.as_str()));
+        }
+        if let Some(min_amount) = conditions.min_amount {
+            if transaction.amount < min_amount {
+                return None;
+            }
+            matched_conditions.push(format!("amount >= {min_amount}"));
+        }
+        if let Some(max_amount) = conditions.max_amount {
+            if transaction.amount > max_amount {
+                return None;
+            }
+            matched_conditions.push(format!("amount <= {max_amount}"));
+        }
+        if let Some(start_date) = conditions.start_date {
+            if transaction.date < start_date {
+                return None;
+            }
+            matched_conditions.push(format!(
+                "date on or after {}",
+                start_date.format("%d.%m.%Y")
+            ));
+        }
+        if let Some(end_date) = conditions.end_date {
+            if transaction.date > end_date {
+                return None;
+            }
+            matched_conditions.push(format!("date on or before {}", end_date.format("%d.%m.%Y")));
+        }
+        if !conditions.weekdays.is_empty() {
+            let weekday = transaction.date.weekday();
+            if !conditions.weekdays.contains(&weekday) {
+                return None;
+            }
+            matched_conditions.push(format!("on a {weekday}"));
+        }
+
+        Some(matched_conditions)
+    }
+}
+
+/// Assigns categories to transactions with a set of `CategorizationRule`, trying them by
+/// decreasing priority until one matches.
+#[derive(Debug)]
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleEngine {
+    /// Creates an engine from rules, failing if a regular expression is invalid.
+    pub fn new(rules: &[CategorizationRule]) -> Result<Self, ExpenseTrackerError> {
+        let mut compiled_rules = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let description_regex = match &rule.conditions.description_regex {
+                None => None,
+                Some(pattern) => Some(
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| {
+                            ExpenseTrackerError::Config(format!(
+                                "Invalid regular expression in rule '{}': {e}",
+                                rule.name
+                            ))
+                        })?,
+                ),
+            };
+            compiled_rules.push(CompiledRule {
+                rule: rule.clone(),
+                description_regex,
+            });
+        }
+        // The sort is stable, so rules with the same priority keep their order
+        compiled_rules.sort_by_key(|compiled_rule| std::cmp::Reverse(compiled_rule.rule.priority));
+        Ok(RuleEngine {
+            rules: compiled_rules,
+        })
+    }
+
+    /// Returns the first rule matching a transaction, along with an explanation of the match.
+    pub fn find_match(
+        &self,
+        transaction: &Transaction,
+    ) -> Option<(&CategorizationRule, RuleMatch)> {
+        self.rules.iter().find_map(|compiled_rule| {
+            let matched_conditions = compiled_rule.matched_conditions(transaction)?;
+            Some((
+                &compiled_rule.rule,
+                RuleMatch {
+                    rule_name: compiled_rule.rule.name.clone(),
+                    priority: compiled_rule.rule.priority,
+                    matched_conditions,
+                },
+            ))
+        })
+    }
+
+    /// Assigns the category, sub-category and tag of the first matching rule to a transaction,
+    /// and returns which rule matched. A tag already set is kept.
+    pub fn categorize(&self, transaction: &mut Transaction) -> Option<RuleMatch> {
+        let (rule, rule_match) = self.find_match(transaction)?;
+        transaction.category_name = rule.category_name.clone();
+        transaction.subcategory_name = rule.subcategory_name.clone();
+        if transaction.tag.is_none() {
+            transaction.tag = rule.tag.clone();
+        }
+        Some(rule_match)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(day: u32, amount: i64, description: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2023, 2, day).unwrap(),
+            amount: Money::from_minor_units(amount),
+            note: Some(description.to_string()),
+            ..Transaction::new()
+        }
+    }
+
+    fn rule(name: &str, priority: i32, conditions: RuleConditions) -> CategorizationRule {
+        CategorizationRule {
+            name: name.to_string(),
+            priority,
+            conditions,
+            category_name: name.to_string(),
+            subcategory_name: None,
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn rules_are_tried_by_priority() {
+        let rule_engine = RuleEngine::new(&[
+            rule(
+                "Courses",
+                0,
+                RuleConditions {
+                    description_regex: Some(r"\b(migros|coop)\b".to_string()),
+                    ..RuleConditions::default()
+                },
+            ),
+            rule(
+                "Gros achats",
+                10,
+                RuleConditions {
+                    description_contains: Some("MIGROS".to_string()),
+                    max_amount: Some(Money::from_minor_units(-10000)),
+                    ..RuleConditions::default()
+                },
+            ),
+            rule(
+                "Lunch",
+                0,
+                RuleConditions {
+                    max_amount: Some(Money::from_minor_units(0)),
+                    min_amount: Some(Money::from_minor_units(-3000)),
+                    // The 6th of February 2023 is a Monday
+                    weekdays: vec![Weekday::Mon, Weekday::Tue],
+                    ..RuleConditions::default()
+                },
+            ),
+        ])
+        .unwrap();
+
+        let mut large_purchase = transaction(6, -103240, "KAUF 09.02.2023 Migros");
+        let rule_match = rule_engine.categorize(&mut large_purchase).unwrap();
+        assert_eq!(large_purchase.category_name, "Gros achats");
+        assert_eq!(
+            rule_match.to_string(),
+            "Rule 'Gros achats' matched: description contains 'MIGROS', amount <= -100.00"
+        );
+
+        let mut groceries = transaction(6, -4550, "Coop Pronto");
+        rule_engine.categorize(&mut groceries).unwrap();
+        assert_eq!(groceries.category_name, "Courses");
+
+        let mut lunch = transaction(7, -1850, "Restaurant");
+        rule_engine.categorize(&mut lunch).unwrap();
+        assert_eq!(lunch.category_name, "Lunch");
+        assert!(rule_engine
+            .find_match(&transaction(8, -1850, "Restaurant"))
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_regex_is_a_config_error() {
+        let invalid_rule = rule(
+            "Invalid",
+            0,
+            RuleConditions {
+                description_regex: Some("(migros".to_string()),
+                ..RuleConditions::default()
+            },
+        );
+        assert!(matches!(
+            RuleEngine::new(&[invalid_rule]),
+            Err(ExpenseTrackerError::Config(_))
+        ));
+    }
+}
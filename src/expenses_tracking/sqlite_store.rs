@@ -0,0 +1,1175 @@
+use chrono::NaiveDate;
+use rusqlite::types::{Type, Value};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::store::{
+    add_sum_to_category_sums, add_to_category_sums, invalid_index, CategorySum, TransactionStore,
+};
+use crate::transaction::{
+    split_path, Category, ClearedState, SubCategory, Transaction, TransactionId, PATH_SEPARATOR,
+};
+
+/// Version of the layout of the tables, stored in the `user_version` pragma of the database.
+///
+/// - Version 1 holds categories, sub-categories and transactions.
+/// - Version 2 adds the aliases of categories and sub-categories, as JSON arrays.
+/// - Version 3 stores sub-categories of any depth, named by their path below their category.
+/// - Version 4 adds the stable IDs of transactions, next to the id of their row.
+/// - Version 5 adds the accounts of transactions.
+/// - Version 6 adds the receiving accounts of transfers.
+/// - Version 7 adds the cleared states of transactions.
+/// - Version 8 adds the lines of split transactions, as JSON arrays.
+/// - Version 9 adds the positions of transactions, such that a removed transaction can be put
+///   back at its place without rewriting the following rows.
+/// - Version 10 adds the cleared states of transfers on their receiving accounts.
+const SQLITE_SCHEMA_VERSION: i64 = 10;
+
+// Category names are compared case insensitively, which SQLite's `lower()` only does for ASCII.
+// The lowercase name is therefore computed in Rust and stored in its own indexed column.
+const CREATE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS categories (
+        name TEXT PRIMARY KEY,
+        aliases TEXT NOT NULL DEFAULT '[]',
+        date_added TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS subcategories (
+        category_name TEXT NOT NULL REFERENCES categories(name) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        aliases TEXT NOT NULL DEFAULT '[]',
+        date_added TEXT NOT NULL,
+        PRIMARY KEY (category_name, name)
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        category_name TEXT NOT NULL,
+        category_key TEXT NOT NULL,
+        subcategory_name TEXT,
+        subcategory_key TEXT,
+        tag TEXT,
+        note TEXT,
+        transaction_id TEXT,
+        account TEXT,
+        transfer_account TEXT,
+        cleared TEXT NOT NULL DEFAULT 'Uncleared',
+        transfer_cleared TEXT NOT NULL DEFAULT 'Uncleared',
+        splits TEXT NOT NULL DEFAULT '[]',
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS transactions_by_id ON transactions (transaction_id);
+    CREATE INDEX IF NOT EXISTS transactions_by_position ON transactions (position);
+    CREATE INDEX IF NOT EXISTS transactions_by_date ON transactions (date);
+    CREATE INDEX IF NOT EXISTS transactions_by_category
+        ON transactions (category_key, subcategory_key, date);
+    CREATE INDEX IF NOT EXISTS transactions_by_account ON transactions (account);
+    CREATE INDEX IF NOT EXISTS transactions_by_transfer_account ON transactions (transfer_account);
+";
+
+const ADD_ALIASES: &str = "
+    ALTER TABLE categories ADD COLUMN aliases TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE subcategories ADD COLUMN aliases TEXT NOT NULL DEFAULT '[]';
+";
+
+const ADD_ACCOUNTS: &str = "ALTER TABLE transactions ADD COLUMN account TEXT;";
+
+const ADD_TRANSFER_ACCOUNTS: &str = "ALTER TABLE transactions ADD COLUMN transfer_account TEXT;";
+
+const ADD_CLEARED_STATES: &str =
+    "ALTER TABLE transactions ADD COLUMN cleared TEXT NOT NULL DEFAULT 'Uncleared';";
+
+const ADD_SPLITS: &str = "ALTER TABLE transactions ADD COLUMN splits TEXT NOT NULL DEFAULT '[]';";
+
+// The cleared state of a transfer applied to both of its accounts before version 10
+const ADD_TRANSFER_CLEARED_STATES: &str = "
+    ALTER TABLE transactions ADD COLUMN transfer_cleared TEXT NOT NULL DEFAULT 'Uncleared';
+    UPDATE transactions SET transfer_cleared = cleared WHERE transfer_account IS NOT NULL;
+";
+
+// Transactions were kept in the order of their row IDs before they had positions
+const ADD_POSITIONS: &str = "
+    ALTER TABLE transactions ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    UPDATE transactions SET position =
+        (SELECT COUNT(*) FROM transactions AS previous WHERE previous.id < transactions.id);
+";
+
+const SELECT_TRANSACTIONS: &str = "SELECT date, amount, currency, category_name, \
+                                   subcategory_name, tag, note, transaction_id, account, \
+                                   transfer_account, cleared, transfer_cleared, splits, \
+                                   position FROM transactions";
+
+fn storage_error(context: &str) -> impl Fn(rusqlite::Error) -> ExpenseTrackerError + '_ {
+    move |e| ExpenseTrackerError::Storage(format!("{context}: {e}"))
+}
+
+/// Reads the JSON array of aliases in a column of a row.
+fn aliases_from_row(row: &Row, index: usize) -> rusqlite::Result<BTreeSet<String>> {
+    let aliases: String = row.get(index)?;
+    serde_json::from_str(&aliases)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into()))
+}
+
+/// Writes aliases as a JSON array.
+fn aliases_to_json(aliases: &BTreeSet<String>) -> String {
+    serde_json::to_string(aliases).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Reads a row selected with `SELECT_TRANSACTIONS` into a `Transaction`.
+fn transaction_from_row(row: &Row) -> rusqlite::Result<Transaction> {
+    let currency: String = row.get(2)?;
+    let id: String = row.get(7)?;
+    let cleared: String = row.get(10)?;
+    let transfer_cleared: String = row.get(11)?;
+    let splits: String = row.get(12)?;
+    Ok(Transaction {
+        id: id.parse().map_err(|e: Box<dyn std::error::Error>| {
+            rusqlite::Error::FromSqlConversionFailure(7, Type::Text, e.to_string().into())
+        })?,
+        date: row.get(0)?,
+        amount: Money::from_minor_units(row.get(1)?),
+        currency: currency.parse().map_err(|e: Box<dyn std::error::Error>| {
+            rusqlite::Error::FromSqlConversionFailure(2, Type::Text, e.to_string().into())
+        })?,
+        category_name: row.get(3)?,
+        subcategory_name: row.get(4)?,
+        tag: row.get(5)?,
+        note: row.get(6)?,
+        account: row.get(8)?,
+        transfer_account: row.get(9)?,
+        cleared: cleared_state_from_row(&cleared, 10)?,
+        transfer_cleared: cleared_state_from_row(&transfer_cleared, 11)?,
+        splits: serde_json::from_str(&splits)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(12, Type::Text, e.into()))?,
+    })
+}
+
+/// Reads a row selected with `SELECT_TRANSACTIONS` into a `Transaction` along with its index.
+fn indexed_transaction_from_row(row: &Row) -> rusqlite::Result<(usize, Transaction)> {
+    let position: i64 = row.get(13)?;
+    Ok((position as usize, transaction_from_row(row)?))
+}
+
+/// Names of the cleared states in the `cleared` column.
+fn cleared_state_to_text(cleared: ClearedState) -> &'static str {
+    match cleared {
+        ClearedState::Uncleared => "Uncleared",
+        ClearedState::Cleared => "Cleared",
+        ClearedState::Reconciled => "Reconciled",
+    }
+}
+
+fn cleared_state_from_text(text: &str) -> Option<ClearedState> {
+    [
+        ClearedState::Uncleared,
+        ClearedState::Cleared,
+        ClearedState::Reconciled,
+    ]
+    .into_iter()
+    .find(|&cleared| cleared_state_to_text(cleared) == text)
+}
+
+/// Reads the cleared state in a column of a row.
+fn cleared_state_from_row(text: &str, index: usize) -> rusqlite::Result<ClearedState> {
+    cleared_state_from_text(text).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            index,
+            Type::Text,
+            format!("Invalid cleared state '{text}'").into(),
+        )
+    })
+}
+
+/// Returns the values of the columns of a transaction, from `date` to `splits`.
+fn transaction_params(transaction: &Transaction) -> [Value; 15] {
+    let optional_text = |text: &Option<String>| match text {
+        Some(text) => Value::Text(text.clone()),
+        None => Value::Null,
+    };
+    [
+        Value::Text(transaction.date.format("%F").to_string()),
+        Value::Integer(transaction.amount.minor_units()),
+        Value::Text(transaction.currency.code().to_string()),
+        Value::Text(transaction.category_name.clone()),
+        Value::Text(transaction.category_name.to_lowercase()),
+        optional_text(&transaction.subcategory_name),
+        optional_text(
+            &transaction
+                .subcategory_name
+                .as_ref()
+                .map(|name| name.to_lowercase()),
+        ),
+        optional_text(&transaction.tag),
+        optional_text(&transaction.note),
+        Value::Text(transaction.id.to_string()),
+        optional_text(&transaction.account),
+        optional_text(&transaction.transfer_account),
+        Value::Text(cleared_state_to_text(transaction.cleared).to_string()),
+        Value::Text(cleared_state_to_text(transaction.transfer_cleared).to_string()),
+        Value::Text(
+            serde_json::to_string(&transaction.splits).unwrap_or_else(|_| "[]".to_string()),
+        ),
+    ]
+}
+
+/// Inserts the row of a transaction at a position, and returns its row ID.
+fn insert_transaction_row(
+    connection: &Connection,
+    transaction: &Transaction,
+    position: usize,
+) -> Result<i64, ExpenseTrackerError> {
+    let values = transaction_params(transaction)
+        .into_iter()
+        .chain([Value::Integer(position as i64)]);
+    connection
+        .execute(
+            "INSERT INTO transactions (date, amount, currency, category_name, category_key, \
+             subcategory_name, subcategory_key, tag, note, transaction_id, account, \
+             transfer_account, cleared, transfer_cleared, splits, position) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params_from_iter(values),
+        )
+        .map_err(storage_error("Failed to insert a transaction"))?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Returns whether the database has a table of transactions, which databases of version 1 may
+/// not have yet.
+fn has_transactions_table(connection: &Connection) -> Result<bool, ExpenseTrackerError> {
+    connection
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('transactions')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(storage_error("Failed to read the SQLite tables"))
+}
+
+/// Adds the column of IDs to the transactions of a database created before version 4, and gives
+/// a new ID to every transaction.
+fn add_transaction_ids(connection: &Connection) -> Result<(), ExpenseTrackerError> {
+    if !has_transactions_table(connection)? {
+        return Ok(());
+    }
+
+    let db_transaction = connection
+        .unchecked_transaction()
+        .map_err(storage_error("Failed to start an SQLite transaction"))?;
+    db_transaction
+        .execute(
+            "ALTER TABLE transactions ADD COLUMN transaction_id TEXT",
+            [],
+        )
+        .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+    let row_ids: Vec<i64> = db_transaction
+        .prepare("SELECT id FROM transactions")
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| row.get(0))
+                .and_then(|rows| rows.collect())
+        })
+        .map_err(storage_error("Failed to read transactions"))?;
+    for row_id in row_ids {
+        db_transaction
+            .execute(
+                "UPDATE transactions SET transaction_id = ?1 WHERE id = ?2",
+                params![TransactionId::new().to_string(), row_id],
+            )
+            .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+    }
+    db_transaction
+        .commit()
+        .map_err(storage_error("Failed to commit the SQLite transaction"))
+}
+
+/// Stores categories, sub-categories and transactions in a local SQLite file.
+///
+/// Every change is written to the database immediately. Only the categories are also kept in
+/// memory, the transactions being read by the queries of `TransactionStore`, which go through
+/// indexes. Settings such as import profiles are still stored in the JSON project file.
+pub struct SqliteStore {
+    connection: Connection,
+    categories: BTreeSet<Category>,
+}
+
+impl SqliteStore {
+    /// Opens the database at the given path, creating it and its tables if needed.
+    pub fn open(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let connection = Connection::open(file_path)
+            .map_err(storage_error("Failed to open the SQLite database"))?;
+        Self::from_connection(connection)
+    }
+
+    /// Opens a database which only lives in memory, e.g. for tests.
+    pub fn open_in_memory() -> Result<Self, ExpenseTrackerError> {
+        let connection = Connection::open_in_memory()
+            .map_err(storage_error("Failed to open the SQLite database"))?;
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, ExpenseTrackerError> {
+        let schema_version: i64 = connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(storage_error("Failed to read the SQLite schema version"))?;
+        if schema_version > SQLITE_SCHEMA_VERSION {
+            return Err(ExpenseTrackerError::Storage(format!(
+                "SQLite database has schema version {schema_version}, but only versions up to \
+                 {SQLITE_SCHEMA_VERSION} are supported"
+            )));
+        }
+
+        // Databases created before version 2 have no column for aliases
+        if schema_version == 1 {
+            connection
+                .execute_batch(ADD_ALIASES)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 4 have no IDs for transactions
+        if (1..4).contains(&schema_version) {
+            add_transaction_ids(&connection)?;
+        }
+        // Databases created before version 5 have no accounts for transactions
+        if (1..5).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_ACCOUNTS)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 6 have no transfers
+        if (1..6).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_TRANSFER_ACCOUNTS)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 7 have no cleared states
+        if (1..7).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_CLEARED_STATES)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 8 have no split transactions
+        if (1..8).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_SPLITS)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 9 keep transactions in the order of their row IDs
+        if (1..9).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_POSITIONS)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        // Databases created before version 10 have a single cleared state for both sides of a
+        // transfer
+        if (1..10).contains(&schema_version) && has_transactions_table(&connection)? {
+            connection
+                .execute_batch(ADD_TRANSFER_CLEARED_STATES)
+                .map_err(storage_error("Failed to upgrade the SQLite tables"))?;
+        }
+        connection
+            .execute_batch(CREATE_SCHEMA)
+            .map_err(storage_error("Failed to create the SQLite tables"))?;
+        connection
+            .pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)
+            .map_err(storage_error("Failed to write the SQLite schema version"))?;
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .map_err(storage_error("Failed to enable SQLite foreign keys"))?;
+
+        let mut sqlite_store = SqliteStore {
+            connection,
+            categories: BTreeSet::new(),
+        };
+        sqlite_store.load_categories()?;
+        Ok(sqlite_store)
+    }
+
+    /// Reads all the categories of the database into memory.
+    fn load_categories(&mut self) -> Result<(), ExpenseTrackerError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, aliases, date_added FROM categories")
+            .map_err(storage_error("Failed to read categories"))?;
+        let categories = statement
+            .query_map([], |row| {
+                Ok(Category {
+                    name: row.get(0)?,
+                    aliases: aliases_from_row(row, 1)?,
+                    subcategories: BTreeSet::new(),
+                    date_added: row.get(2)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<Category>>>())
+            .map_err(storage_error("Failed to read categories"))?;
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, aliases, date_added FROM subcategories WHERE category_name = ?1")
+            .map_err(storage_error("Failed to read sub-categories"))?;
+        for mut category in categories {
+            // Sub-categories are stored under their path, and inserted below their parent
+            let mut subcategories = statement
+                .query_map([&category.name], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        SubCategory {
+                            name: String::new(),
+                            aliases: aliases_from_row(row, 1)?,
+                            subcategories: BTreeSet::new(),
+                            date_added: row.get(2)?,
+                        },
+                    ))
+                })
+                .and_then(|rows| rows.collect::<rusqlite::Result<Vec<(String, SubCategory)>>>())
+                .map_err(storage_error("Failed to read sub-categories"))?;
+            subcategories.sort_by_key(|(path, _)| split_path(path).len());
+            for (path, mut subcategory) in subcategories {
+                let mut names: Vec<String> =
+                    split_path(&path).into_iter().map(String::from).collect();
+                subcategory.name = names.pop().unwrap_or_default();
+                category
+                    .edit_subcategories(&names, |subcategories| subcategories.insert(subcategory));
+            }
+            self.categories.insert(category);
+        }
+        Ok(())
+    }
+
+    fn query_transactions(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        Ok(self
+            .query_indexed_transactions(query, params)?
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .collect())
+    }
+
+    fn query_indexed_transactions(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let mut statement = self
+            .connection
+            .prepare_cached(query)
+            .map_err(storage_error("Failed to prepare a transaction query"))?;
+        statement
+            .query_map(params, indexed_transaction_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(storage_error("Failed to read transactions"))
+    }
+
+    /// Returns the id of the row of the transaction at an index.
+    fn row_id(&self, index: usize) -> Result<i64, ExpenseTrackerError> {
+        let row_id: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT id FROM transactions WHERE position = ?1",
+                [index as i64],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage_error("Failed to read transactions"))?;
+        match row_id {
+            Some(row_id) => Ok(row_id),
+            None => Err(invalid_index(index, self.n_transactions()?)),
+        }
+    }
+}
+
+impl TransactionStore for SqliteStore {
+    fn categories(&self) -> &BTreeSet<Category> {
+        &self.categories
+    }
+
+    fn set_category(&mut self, category: Category) -> Result<(), ExpenseTrackerError> {
+        let category_name = category.name.to_lowercase();
+        let previous_name = self
+            .categories
+            .iter()
+            .find(|existing_category| existing_category.name.to_lowercase() == category_name)
+            .map(|existing_category| existing_category.name.clone());
+        let db_transaction = self
+            .connection
+            .transaction()
+            .map_err(storage_error("Failed to start an SQLite transaction"))?;
+        // The row of a category whose name only differs by its capitalization is replaced
+        if let Some(previous_name) = previous_name.filter(|name| *name != category.name) {
+            db_transaction
+                .execute("DELETE FROM categories WHERE name = ?1", [&previous_name])
+                .map_err(storage_error("Failed to write a category"))?;
+        }
+        db_transaction
+            .execute(
+                "INSERT INTO categories (name, aliases, date_added) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (name) DO UPDATE SET aliases = excluded.aliases, \
+                 date_added = excluded.date_added",
+                params![
+                    category.name,
+                    aliases_to_json(&category.aliases),
+                    category.date_added
+                ],
+            )
+            .map_err(storage_error("Failed to write a category"))?;
+        db_transaction
+            .execute(
+                "DELETE FROM subcategories WHERE category_name = ?1",
+                [&category.name],
+            )
+            .map_err(storage_error("Failed to write a category"))?;
+        for (path, subcategory) in category.subcategories_with_paths() {
+            db_transaction
+                .execute(
+                    "INSERT INTO subcategories (category_name, name, aliases, date_added) \
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        category.name,
+                        path,
+                        aliases_to_json(&subcategory.aliases),
+                        subcategory.date_added
+                    ],
+                )
+                .map_err(storage_error("Failed to write a sub-category"))?;
+        }
+        db_transaction
+            .commit()
+            .map_err(storage_error("Failed to commit the SQLite transaction"))?;
+
+        // Categories are ordered by all their fields in the `BTreeSet`, so the previous category
+        // with the same name must be removed explicitly
+        self.categories
+            .retain(|existing_category| existing_category.name.to_lowercase() != category_name);
+        self.categories.insert(category);
+        Ok(())
+    }
+
+    fn remove_category(
+        &mut self,
+        category_name: &str,
+    ) -> Result<Option<Category>, ExpenseTrackerError> {
+        let category_name = category_name.to_lowercase();
+        let Some(found_category) = self
+            .categories
+            .iter()
+            .find(|category| category.name.to_lowercase() == category_name)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        // Sub-categories are deleted along with their category
+        self.connection
+            .execute(
+                "DELETE FROM categories WHERE name = ?1",
+                [&found_category.name],
+            )
+            .map_err(storage_error("Failed to delete a category"))?;
+        Ok(self.categories.take(&found_category))
+    }
+
+    fn n_transactions(&self) -> Result<usize, ExpenseTrackerError> {
+        let n_transactions: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))
+            .map_err(storage_error("Failed to count transactions"))?;
+        Ok(n_transactions as usize)
+    }
+
+    fn transactions_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Cow<'_, [Transaction]>, ExpenseTrackerError> {
+        let bound = |index: usize| i64::try_from(index).unwrap_or(i64::MAX);
+        Ok(Cow::Owned(self.query_transactions(
+            &format!(
+                "{SELECT_TRANSACTIONS} WHERE position >= ?1 AND position < ?2 ORDER BY position"
+            ),
+            params![bound(range.start), bound(range.end)],
+        )?))
+    }
+
+    fn transaction_index(&self, id: TransactionId) -> Result<Option<usize>, ExpenseTrackerError> {
+        let position: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT position FROM transactions WHERE transaction_id = ?1",
+                [id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage_error("Failed to read transactions"))?;
+        Ok(position.map(|position| position as usize))
+    }
+
+    fn push_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError> {
+        insert_transaction_row(&self.connection, &transaction, self.n_transactions()?)?;
+        Ok(())
+    }
+
+    fn insert_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<(), ExpenseTrackerError> {
+        let n_transactions = self.n_transactions()?;
+        if index > n_transactions {
+            return Err(invalid_index(index, n_transactions));
+        }
+        let db_transaction = self
+            .connection
+            .transaction()
+            .map_err(storage_error("Failed to start an SQLite transaction"))?;
+        db_transaction
+            .execute(
+                "UPDATE transactions SET position = position + 1 WHERE position >= ?1",
+                [index as i64],
+            )
+            .map_err(storage_error("Failed to insert a transaction"))?;
+        insert_transaction_row(&db_transaction, &transaction, index)?;
+        db_transaction
+            .commit()
+            .map_err(storage_error("Failed to commit the SQLite transaction"))
+    }
+
+    fn replace_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        let row_id = self.row_id(index)?;
+        let previous = self.transaction(index)?.into_owned();
+        let values = transaction_params(&transaction)
+            .into_iter()
+            .chain([Value::Integer(row_id)]);
+        self.connection
+            .execute(
+                "UPDATE transactions SET date = ?1, amount = ?2, currency = ?3, \
+                 category_name = ?4, category_key = ?5, subcategory_name = ?6, \
+                 subcategory_key = ?7, tag = ?8, note = ?9, transaction_id = ?10, \
+                 account = ?11, transfer_account = ?12, cleared = ?13, \
+                 transfer_cleared = ?14, splits = ?15 WHERE id = ?16",
+                params_from_iter(values),
+            )
+            .map_err(storage_error("Failed to update a transaction"))?;
+        Ok(previous)
+    }
+
+    fn remove_transaction(&mut self, index: usize) -> Result<Transaction, ExpenseTrackerError> {
+        let row_id = self.row_id(index)?;
+        let transaction = self.transaction(index)?.into_owned();
+        let db_transaction = self
+            .connection
+            .transaction()
+            .map_err(storage_error("Failed to start an SQLite transaction"))?;
+        db_transaction
+            .execute("DELETE FROM transactions WHERE id = ?1", [row_id])
+            .map_err(storage_error("Failed to delete a transaction"))?;
+        db_transaction
+            .execute(
+                "UPDATE transactions SET position = position - 1 WHERE position > ?1",
+                [index as i64],
+            )
+            .map_err(storage_error("Failed to delete a transaction"))?;
+        db_transaction
+            .commit()
+            .map_err(storage_error("Failed to commit the SQLite transaction"))?;
+        Ok(transaction)
+    }
+
+    fn transactions_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        self.query_indexed_transactions(
+            &format!("{SELECT_TRANSACTIONS} WHERE date BETWEEN ?1 AND ?2 ORDER BY date, position"),
+            params![start, end],
+        )
+    }
+
+    fn transactions_in_category(
+        &self,
+        category_name: &str,
+        subcategory_name: Option<&str>,
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        match subcategory_name {
+            // Transactions of the sub-categories below are selected by the start of their path
+            Some(subcategory_name) => self.query_transactions(
+                &format!(
+                    "{SELECT_TRANSACTIONS} WHERE category_key = ?1 AND (subcategory_key = ?2 \
+                     OR substr(subcategory_key, 1, length(?3)) = ?3) ORDER BY date, position"
+                ),
+                params![
+                    category_name.to_lowercase(),
+                    subcategory_name.to_lowercase(),
+                    format!("{}{PATH_SEPARATOR}", subcategory_name.to_lowercase())
+                ],
+            ),
+            None => self.query_transactions(
+                &format!("{SELECT_TRANSACTIONS} WHERE category_key = ?1 ORDER BY date, position"),
+                params![category_name.to_lowercase()],
+            ),
+        }
+    }
+
+    fn transactions_of_account(
+        &self,
+        account_name: &str,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        self.query_indexed_transactions(
+            &format!(
+                "{SELECT_TRANSACTIONS} WHERE account = ?1 OR transfer_account = ?1 \
+                 ORDER BY position"
+            ),
+            [account_name],
+        )
+    }
+
+    fn split_transactions(&self) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        self.query_transactions(
+            &format!("{SELECT_TRANSACTIONS} WHERE splits != '[]' ORDER BY position"),
+            [],
+        )
+    }
+
+    /// Sums the transactions which are not split in the database, and adds the lines of the split
+    /// transactions to these sums.
+    fn category_sums(&self) -> Result<Vec<CategorySum>, ExpenseTrackerError> {
+        let mut statement = self
+            .connection
+            .prepare_cached(
+                "SELECT MIN(category_name), MIN(subcategory_name), currency, SUM(amount), \
+                 COUNT(*) FROM transactions WHERE transfer_account IS NULL AND splits = '[]' \
+                 GROUP BY category_key, subcategory_key, currency",
+            )
+            .map_err(storage_error("Failed to prepare a transaction query"))?;
+        let sums = statement
+            .query_map([], |row| {
+                let currency: String = row.get(2)?;
+                let n_transactions: i64 = row.get(4)?;
+                Ok(CategorySum {
+                    category_name: row.get(0)?,
+                    subcategory_name: row.get(1)?,
+                    currency: currency.parse().map_err(|e: Box<dyn std::error::Error>| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            2,
+                            Type::Text,
+                            e.to_string().into(),
+                        )
+                    })?,
+                    total: Money::from_minor_units(row.get(3)?),
+                    n_transactions: n_transactions as usize,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<CategorySum>>>())
+            .map_err(|e| match e {
+                // SQLite fails instead of wrapping around when a sum overflows
+                rusqlite::Error::SqliteFailure(_, Some(message))
+                    if message.contains("integer overflow") =>
+                {
+                    ExpenseTrackerError::AmountOutOfRange(
+                        "total of the income and expenses".to_string(),
+                    )
+                }
+                e => ExpenseTrackerError::Storage(format!("Failed to sum transactions: {e}")),
+            })?;
+
+        let mut category_sums = Default::default();
+        for sum in sums {
+            add_sum_to_category_sums(&mut category_sums, sum)?;
+        }
+        for transaction in self.split_transactions()? {
+            for line in transaction.category_lines() {
+                add_to_category_sums(&mut category_sums, &line)?;
+            }
+        }
+        Ok(category_sums.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::expense_tracker::ExpenseTracker;
+    use crate::import_report::ImportMode;
+    use crate::transaction::{AsCategory, SplitLine};
+
+    fn date(day: u32, month: u32, year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn transaction_in(category_name: &str) -> Transaction {
+        Transaction {
+            amount: Money::from_minor_units(-1000),
+            category_name: category_name.to_string(),
+            ..Transaction::new()
+        }
+    }
+
+    fn load_example(expense_tracker: &mut ExpenseTracker) {
+        expense_tracker
+            .load_transactions_from_file(
+                Path::new("test_data/transactions_example.csv"),
+                true,
+                ImportMode::Lenient,
+            )
+            .unwrap();
+    }
+
+    /// Reads all the transactions of a store, a page at a time.
+    fn all_transactions(sqlite_store: &SqliteStore) -> Vec<Transaction> {
+        sqlite_store
+            .iter_transactions()
+            .map(|transaction| transaction.map(Cow::into_owned))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    /// Copies the categories and transactions of an expense tracker into a store, keeping the
+    /// IDs of the transactions.
+    fn copy_into(expense_tracker: &ExpenseTracker, sqlite_store: &mut SqliteStore) {
+        for category in expense_tracker.categories() {
+            sqlite_store.set_category(category.clone()).unwrap();
+        }
+        for transaction in expense_tracker.transactions() {
+            sqlite_store.push_transaction(transaction.clone()).unwrap();
+        }
+    }
+
+    #[test]
+    fn save_and_load_sqlite_file() {
+        let mut expense_tracker = ExpenseTracker::new();
+        load_example(&mut expense_tracker);
+
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let mut sqlite_store = SqliteStore::open(&database_path).unwrap();
+        copy_into(&expense_tracker, &mut sqlite_store);
+        drop(sqlite_store);
+
+        let mut sqlite_store = SqliteStore::open(&database_path).unwrap();
+        assert_eq!(
+            all_transactions(&sqlite_store),
+            expense_tracker.transactions()
+        );
+        assert_eq!(sqlite_store.categories(), expense_tracker.categories());
+        assert_eq!(
+            sqlite_store.n_transactions().unwrap(),
+            expense_tracker.transactions().len()
+        );
+
+        // Changes are written immediately
+        let mut transaction = sqlite_store.transaction(1).unwrap().into_owned();
+        transaction.note = Some("Updated".to_string());
+        transaction.account = Some("Carte de crédit".to_string());
+        transaction.transfer_account = Some("Compte courant".to_string());
+        transaction.cleared = ClearedState::Reconciled;
+        transaction.transfer_cleared = ClearedState::Cleared;
+        sqlite_store.replace_transaction(1, transaction).unwrap();
+        let mut transaction = sqlite_store.transaction(2).unwrap().into_owned();
+        transaction.splits = vec![SplitLine {
+            amount: transaction.amount,
+            category_name: transaction.category_name.clone(),
+            subcategory_name: None,
+            tag: None,
+            note: Some("Line".to_string()),
+        }];
+        let splits = transaction.splits.clone();
+        sqlite_store.replace_transaction(2, transaction).unwrap();
+        let removed = sqlite_store.remove_transaction(0).unwrap();
+        assert_eq!(&removed, &expense_tracker.transactions()[0]);
+        drop(sqlite_store);
+
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        assert_eq!(
+            sqlite_store.n_transactions().unwrap(),
+            expense_tracker.transactions().len() - 1
+        );
+        assert_eq!(
+            sqlite_store.transaction(0).unwrap().note.as_deref(),
+            Some("Updated")
+        );
+        assert_eq!(
+            sqlite_store.transaction(0).unwrap().account.as_deref(),
+            Some("Carte de crédit")
+        );
+        assert!(sqlite_store.transaction(0).unwrap().is_transfer());
+        assert_eq!(
+            sqlite_store.transaction(0).unwrap().cleared,
+            ClearedState::Reconciled
+        );
+        assert_eq!(
+            sqlite_store.transaction(0).unwrap().transfer_cleared,
+            ClearedState::Cleared
+        );
+        assert_eq!(sqlite_store.transaction(1).unwrap().splits, splits);
+    }
+
+    #[test]
+    fn query_transactions_by_date_and_category() {
+        let mut expense_tracker = ExpenseTracker::new();
+        load_example(&mut expense_tracker);
+        let mut sqlite_store = SqliteStore::open_in_memory().unwrap();
+        copy_into(&expense_tracker, &mut sqlite_store);
+        let sqlite_expense_tracker = ExpenseTracker::with_store(sqlite_store);
+
+        let start = date(1, 2, 2023);
+        let end = date(28, 2, 2023);
+        let expected = expense_tracker.transactions_between(start, end).unwrap();
+        let in_february = sqlite_expense_tracker
+            .transactions_between(start, end)
+            .unwrap();
+        assert_eq!(in_february.len(), expected.len());
+        assert!(in_february
+            .iter()
+            .all(|transaction| expected.contains(transaction)));
+
+        let category_name = expense_tracker.transactions()[0]
+            .category_name
+            .to_uppercase();
+        let expected = expense_tracker
+            .transactions_in_category(&category_name, None)
+            .unwrap();
+        let in_category = sqlite_expense_tracker
+            .transactions_in_category(&category_name, None)
+            .unwrap();
+        assert!(!in_category.is_empty());
+        assert_eq!(in_category.len(), expected.len());
+        assert!(in_category
+            .iter()
+            .any(|transaction| transaction.currency == Currency::CHF));
+    }
+
+    #[test]
+    fn sum_transactions_like_in_memory() {
+        let mut expense_tracker = ExpenseTracker::new();
+        load_example(&mut expense_tracker);
+        let mut sqlite_store = SqliteStore::open_in_memory().unwrap();
+        copy_into(&expense_tracker, &mut sqlite_store);
+        // Lines of split transactions are added to the sums of the database
+        let mut split = expense_tracker.transactions()[0].clone();
+        split.splits = vec![SplitLine {
+            amount: split.amount,
+            category_name: split.category_name.to_uppercase(),
+            subcategory_name: split.subcategory_name.take(),
+            tag: None,
+            note: None,
+        }];
+        split.category_name = String::new();
+        expense_tracker.update_transaction(split.id, split).unwrap();
+        sqlite_store
+            .replace_transaction(0, expense_tracker.transactions()[0].clone())
+            .unwrap();
+
+        assert_eq!(
+            sqlite_store.category_sums().unwrap(),
+            expense_tracker.store().category_sums().unwrap()
+        );
+        let sqlite_expense_tracker = ExpenseTracker::with_store(sqlite_store);
+        assert_eq!(
+            sqlite_expense_tracker.category_totals().unwrap(),
+            expense_tracker.category_totals().unwrap()
+        );
+        assert_eq!(
+            sqlite_expense_tracker.totals().unwrap(),
+            expense_tracker.totals().unwrap()
+        );
+    }
+
+    #[test]
+    fn save_and_load_category_tree() {
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let mut expense_tracker =
+            ExpenseTracker::with_store(SqliteStore::open(&database_path).unwrap());
+        for path in [
+            "Voyages/Nourriture/Restaurant",
+            "Voyages/Nourriture/Courses",
+        ] {
+            expense_tracker.add_category_path(path, None).unwrap();
+        }
+        let mut transaction = transaction_in("Voyages/Nourriture/Restaurant");
+        transaction.date = date(10, 2, 2023);
+        expense_tracker.add_transaction(transaction).unwrap();
+        let categories = expense_tracker.categories().clone();
+        drop(expense_tracker);
+
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        let category = sqlite_store.categories().first().unwrap();
+        assert_eq!(category.subcategories_with_paths().len(), 3);
+        assert_eq!(
+            format!("{:?}", sqlite_store.categories()),
+            format!("{categories:?}")
+        );
+        assert_eq!(
+            sqlite_store
+                .transactions_in_category("voyages", Some("nourriture"))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(sqlite_store
+            .transactions_in_category("voyages", Some("nourr"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn put_back_removed_transaction() {
+        let mut expense_tracker = ExpenseTracker::new();
+        load_example(&mut expense_tracker);
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let mut sqlite_store = SqliteStore::open(&database_path).unwrap();
+        copy_into(&expense_tracker, &mut sqlite_store);
+
+        let removed = sqlite_store.remove_transaction(1).unwrap();
+        sqlite_store.insert_transaction(1, removed).unwrap();
+        assert_eq!(
+            all_transactions(&sqlite_store),
+            expense_tracker.transactions()
+        );
+        let n_transactions = sqlite_store.n_transactions().unwrap();
+        assert!(sqlite_store
+            .insert_transaction(n_transactions + 1, transaction_in("Nourriture"))
+            .is_err());
+        drop(sqlite_store);
+
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        assert_eq!(
+            all_transactions(&sqlite_store),
+            expense_tracker.transactions()
+        );
+    }
+
+    #[test]
+    fn replace_category_with_other_capitalization() {
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let mut sqlite_store = SqliteStore::open(&database_path).unwrap();
+        sqlite_store
+            .set_category("nourriture".as_category())
+            .unwrap();
+        sqlite_store
+            .set_category("Nourriture".as_category())
+            .unwrap();
+        assert_eq!(sqlite_store.categories().len(), 1);
+        drop(sqlite_store);
+
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        let names: Vec<&str> = sqlite_store
+            .categories()
+            .iter()
+            .map(|category| category.name.as_str())
+            .collect();
+        assert_eq!(names, ["Nourriture"]);
+    }
+
+    #[test]
+    fn upgrade_database_without_aliases() {
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let connection = Connection::open(&database_path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE categories (name TEXT PRIMARY KEY, date_added TEXT NOT NULL);
+                 CREATE TABLE subcategories (category_name TEXT NOT NULL, name TEXT NOT NULL, \
+                 date_added TEXT NOT NULL, PRIMARY KEY (category_name, name));
+                 INSERT INTO categories VALUES ('nourriture', '2023-02-06');
+                 PRAGMA user_version = 1;",
+            )
+            .unwrap();
+        drop(connection);
+
+        let mut sqlite_store = SqliteStore::open(&database_path).unwrap();
+        let mut category = sqlite_store.categories().first().unwrap().clone();
+        assert!(category.aliases.is_empty());
+        category.aliases.insert("Alimentation".to_string());
+        sqlite_store.set_category(category).unwrap();
+        drop(sqlite_store);
+
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        let category = sqlite_store.categories().first().unwrap();
+        assert!(category.aliases.contains("Alimentation"));
+    }
+
+    #[test]
+    fn upgrade_database_without_transaction_ids() {
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        let connection = Connection::open(&database_path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE transactions (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 date TEXT NOT NULL, amount INTEGER NOT NULL, currency TEXT NOT NULL, \
+                 category_name TEXT NOT NULL, category_key TEXT NOT NULL, \
+                 subcategory_name TEXT, subcategory_key TEXT, tag TEXT, note TEXT);
+                 INSERT INTO transactions (date, amount, currency, category_name, category_key) \
+                 VALUES ('2023-02-06', -850, 'CHF', 'Abonnements', 'abonnements');
+                 PRAGMA user_version = 3;",
+            )
+            .unwrap();
+        drop(connection);
+
+        // Every transaction gets an ID, which is kept from then on, and no account
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        let id = sqlite_store.transaction(0).unwrap().id;
+        assert_eq!(sqlite_store.transaction(0).unwrap().account, None);
+        drop(sqlite_store);
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        assert_eq!(sqlite_store.transaction_index(id).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn upgrade_database_with_shared_cleared_states() {
+        let tmp_dir = tempdir::TempDir::new("sqlite").unwrap();
+        let database_path = tmp_dir.path().join("expenses.sqlite");
+        drop(SqliteStore::open(&database_path).unwrap());
+        let connection = Connection::open(&database_path).unwrap();
+        connection
+            .execute_batch(
+                "ALTER TABLE transactions DROP COLUMN transfer_cleared;
+                 INSERT INTO transactions (date, amount, currency, category_name, category_key, \
+                 transaction_id, account, transfer_account, cleared) \
+                 VALUES ('2023-02-06', -10000, 'CHF', '', '', \
+                 'b0a4a62e-6c3a-4a4b-9f3e-1c0d2f5e7a11', 'Compte courant', 'Épargne', \
+                 'Reconciled');
+                 PRAGMA user_version = 9;",
+            )
+            .unwrap();
+        drop(connection);
+
+        // The state of a transfer applied to both of its accounts
+        let sqlite_store = SqliteStore::open(&database_path).unwrap();
+        assert_eq!(
+            sqlite_store.transaction(0).unwrap().transfer_cleared,
+            ClearedState::Reconciled
+        );
+    }
+
+    #[test]
+    fn queries_use_indexes() {
+        let sqlite_store = SqliteStore::open_in_memory().unwrap();
+        for query in [
+            format!("{SELECT_TRANSACTIONS} WHERE date BETWEEN '2023-01-01' AND '2023-12-31'"),
+            format!("{SELECT_TRANSACTIONS} WHERE category_key = 'nourriture'"),
+            format!("{SELECT_TRANSACTIONS} WHERE account = 'Épargne'"),
+            format!("{SELECT_TRANSACTIONS} WHERE transfer_account = 'Épargne'"),
+        ] {
+            let plan: String = sqlite_store
+                .connection
+                .query_row(&format!("EXPLAIN QUERY PLAN {query}"), [], |row| row.get(3))
+                .unwrap();
+            assert!(plan.contains("USING INDEX"), "{plan}");
+        }
+    }
+}
@@ -0,0 +1,735 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::currency::Currency;
+use crate::error::ExpenseTrackerError;
+use crate::money::Money;
+use crate::transaction::{Category, Transaction, TransactionId, PATH_SEPARATOR};
+
+/// Number of transactions read at once by `Transactions`.
+const PAGE_LEN: usize = 1000;
+
+/// The income and expenses of a category and sub-category in a currency, as summed by
+/// `TransactionStore::category_sums`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySum {
+    /// Name of the category as written in the transactions, which may differ in case.
+    pub category_name: String,
+    /// Path of the sub-category as written in the transactions, which may differ in case.
+    pub subcategory_name: Option<String>,
+    pub currency: Currency,
+    pub total: Money,
+    /// Number of transactions, or of lines of split transactions.
+    pub n_transactions: usize,
+}
+
+/// Sums of the amounts of income and expenses, by lowercase category, sub-category and currency.
+type CategorySums = BTreeMap<(String, Option<String>, Currency), CategorySum>;
+
+/// Adds the amount of a transaction, or of a line of a split transaction, to the sum of its
+/// category, sub-category and currency.
+pub(crate) fn add_to_category_sums(
+    sums: &mut CategorySums,
+    line: &Transaction,
+) -> Result<(), ExpenseTrackerError> {
+    add_sum_to_category_sums(
+        sums,
+        CategorySum {
+            category_name: line.category_name.clone(),
+            subcategory_name: line.subcategory_name.clone(),
+            currency: line.currency,
+            total: line.amount,
+            n_transactions: 1,
+        },
+    )
+}
+
+/// Adds a sum to the sum of the same category, sub-category and currency.
+pub(crate) fn add_sum_to_category_sums(
+    sums: &mut CategorySums,
+    sum: CategorySum,
+) -> Result<(), ExpenseTrackerError> {
+    let key = (
+        sum.category_name.to_lowercase(),
+        sum.subcategory_name.as_deref().map(str::to_lowercase),
+        sum.currency,
+    );
+    match sums.get_mut(&key) {
+        None => {
+            sums.insert(key, sum);
+        }
+        Some(existing) => {
+            existing.total = existing.total.checked_add(sum.total).ok_or_else(|| {
+                ExpenseTrackerError::AmountOutOfRange(format!(
+                    "total of '{}' in {}",
+                    existing.category_name, existing.currency
+                ))
+            })?;
+            existing.n_transactions += sum.n_transactions;
+        }
+    }
+    Ok(())
+}
+
+/// Where an `ExpenseTracker` keeps its categories and transactions.
+///
+/// Categories are kept in memory such that they can be borrowed. Transactions are queried
+/// instead, such that a store may keep them in a database only: they are read by index, in
+/// insertion order, by ID, by date, by category or by account, and summed by category.
+///
+/// Names of categories are compared case insensitively only, the `ExpenseTracker` resolving
+/// aliases and Unicode variants of a name before calling the store.
+pub trait TransactionStore {
+    /// Returns the valid categories along with their sub-categories.
+    fn categories(&self) -> &BTreeSet<Category>;
+
+    /// Inserts a category, replacing the one with the same name along with its sub-categories.
+    fn set_category(&mut self, category: Category) -> Result<(), ExpenseTrackerError>;
+
+    /// Removes a category along with its sub-categories, and returns it if it existed.
+    fn remove_category(
+        &mut self,
+        category_name: &str,
+    ) -> Result<Option<Category>, ExpenseTrackerError>;
+
+    /// Returns the number of transactions.
+    fn n_transactions(&self) -> Result<usize, ExpenseTrackerError>;
+
+    /// Returns the transactions at the indices of a range in insertion order, leaving out the
+    /// indices past the last transaction. A store keeping the transactions in memory borrows them.
+    fn transactions_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Cow<'_, [Transaction]>, ExpenseTrackerError>;
+
+    /// Returns an iterator over all the transactions in insertion order, which reads them from
+    /// the store a page at a time.
+    fn iter_transactions(&self) -> Transactions<'_, Self> {
+        Transactions {
+            store: self,
+            next_index: 0,
+            page: Vec::new().into_iter(),
+            is_done: false,
+        }
+    }
+
+    /// Returns the transaction at an index.
+    fn transaction(&self, index: usize) -> Result<Cow<'_, Transaction>, ExpenseTrackerError> {
+        let transaction = match self.transactions_in_range(index..index.saturating_add(1))? {
+            Cow::Borrowed(transactions) => transactions.first().map(Cow::Borrowed),
+            Cow::Owned(transactions) => transactions.into_iter().next().map(Cow::Owned),
+        };
+        match transaction {
+            Some(transaction) => Ok(transaction),
+            None => Err(invalid_index(index, self.n_transactions()?)),
+        }
+    }
+
+    /// Returns the index of the transaction with an ID.
+    fn transaction_index(&self, id: TransactionId) -> Result<Option<usize>, ExpenseTrackerError> {
+        for (index, transaction) in self.iter_transactions().enumerate() {
+            if transaction?.id == id {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Appends a transaction.
+    fn push_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError>;
+
+    /// Inserts a transaction at an index, shifting the following ones, e.g. to put back a
+    /// removed transaction. Either the whole insertion happens or nothing does.
+    fn insert_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<(), ExpenseTrackerError>;
+
+    /// Replaces the transaction at an index and returns the previous one.
+    fn replace_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError>;
+
+    /// Removes the transaction at an index and returns it.
+    fn remove_transaction(&mut self, index: usize) -> Result<Transaction, ExpenseTrackerError>;
+
+    /// Writes pending changes to the durable location of the store, if any.
+    fn flush(&mut self) -> Result<(), ExpenseTrackerError> {
+        Ok(())
+    }
+
+    /// Returns the transactions whose date is between `start` and `end`, both included, along
+    /// with their indices, in order of date and then of index.
+    fn transactions_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let mut transactions = Vec::new();
+        for (index, transaction) in self.iter_transactions().enumerate() {
+            let transaction = transaction?;
+            if transaction.date >= start && transaction.date <= end {
+                transactions.push((index, transaction.into_owned()));
+            }
+        }
+        transactions.sort_by_key(|(index, transaction)| (transaction.date, *index));
+        Ok(transactions)
+    }
+
+    /// Returns the transactions of a category, and of the sub-category at a path if set,
+    /// including the ones of the sub-categories below it.
+    fn transactions_in_category(
+        &self,
+        category_name: &str,
+        subcategory_name: Option<&str>,
+    ) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        let category_name = category_name.to_lowercase();
+        let subcategory_name = subcategory_name.map(str::to_lowercase);
+        let is_in_subcategory = |name: &str| match &subcategory_name {
+            None => true,
+            Some(subcategory_name) => name
+                .strip_prefix(subcategory_name.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(PATH_SEPARATOR)),
+        };
+        let mut transactions = Vec::new();
+        for transaction in self.iter_transactions() {
+            let transaction = transaction?;
+            if transaction.category_name.to_lowercase() == category_name
+                && (subcategory_name.is_none()
+                    || transaction
+                        .subcategory_name
+                        .as_ref()
+                        .is_some_and(|name| is_in_subcategory(&name.to_lowercase())))
+            {
+                transactions.push(transaction.into_owned());
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// Returns the transactions made with or received by the account with a name, along with
+    /// their indices, in insertion order.
+    ///
+    /// The name is compared exactly, as the `ExpenseTracker` writes the name of the account into
+    /// every transaction made with it.
+    fn transactions_of_account(
+        &self,
+        account_name: &str,
+    ) -> Result<Vec<(usize, Transaction)>, ExpenseTrackerError> {
+        let mut transactions = Vec::new();
+        for (index, transaction) in self.iter_transactions().enumerate() {
+            let transaction = transaction?;
+            if [&transaction.account, &transaction.transfer_account]
+                .into_iter()
+                .any(|name| name.as_deref() == Some(account_name))
+            {
+                transactions.push((index, transaction.into_owned()));
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// Returns the split transactions in insertion order.
+    fn split_transactions(&self) -> Result<Vec<Transaction>, ExpenseTrackerError> {
+        let mut transactions = Vec::new();
+        for transaction in self.iter_transactions() {
+            let transaction = transaction?;
+            if transaction.is_split() {
+                transactions.push(transaction.into_owned());
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// Returns the sums of the income and expenses by category, sub-category and currency,
+    /// ordered by lowercase name. Split transactions are counted through their lines, and
+    /// transfers are left out.
+    fn category_sums(&self) -> Result<Vec<CategorySum>, ExpenseTrackerError> {
+        let mut sums = CategorySums::new();
+        for transaction in self.iter_transactions() {
+            let transaction = transaction?;
+            if !transaction.is_transfer() {
+                for line in transaction.category_lines() {
+                    add_to_category_sums(&mut sums, &line)?;
+                }
+            }
+        }
+        Ok(sums.into_values().collect())
+    }
+}
+
+/// An iterator over the transactions of a store in insertion order, returned by
+/// `TransactionStore::iter_transactions`.
+///
+/// The transactions are read a page at a time, such that a store in a database never holds all
+/// of them in memory. The iterator ends after the first error.
+pub struct Transactions<'a, S: ?Sized> {
+    store: &'a S,
+    next_index: usize,
+    page: std::vec::IntoIter<Cow<'a, Transaction>>,
+    is_done: bool,
+}
+
+impl<'a, S: TransactionStore + ?Sized> Iterator for Transactions<'a, S> {
+    type Item = Result<Cow<'a, Transaction>, ExpenseTrackerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(transaction) = self.page.next() {
+            return Some(Ok(transaction));
+        }
+        if self.is_done {
+            return None;
+        }
+        let page = match self
+            .store
+            .transactions_in_range(self.next_index..self.next_index + PAGE_LEN)
+        {
+            Ok(page) => page,
+            Err(e) => {
+                self.is_done = true;
+                return Some(Err(e));
+            }
+        };
+        self.is_done = page.len() < PAGE_LEN;
+        self.next_index += page.len();
+        let page: Vec<Cow<'a, Transaction>> = match page {
+            Cow::Borrowed(transactions) => transactions.iter().map(Cow::Borrowed).collect(),
+            Cow::Owned(transactions) => transactions.into_iter().map(Cow::Owned).collect(),
+        };
+        self.page = page.into_iter();
+        self.page.next().map(Ok)
+    }
+}
+
+/// Returns the error of a transaction index which is out of bounds.
+pub(crate) fn invalid_index(index: usize, n_transactions: usize) -> ExpenseTrackerError {
+    ExpenseTrackerError::Storage(format!(
+        "No transaction at index {index}, the store holds {n_transactions} transactions"
+    ))
+}
+
+/// A store which only keeps the data in memory, which is the default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryStore {
+    #[serde(rename = "valid_categories")]
+    categories: BTreeSet<Category>,
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+}
+
+impl MemoryStore {
+    /// Creates an empty `MemoryStore`.
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// Creates a `MemoryStore` holding existing categories and transactions.
+    pub fn from_parts(categories: BTreeSet<Category>, transactions: Vec<Transaction>) -> Self {
+        MemoryStore {
+            categories,
+            transactions,
+        }
+    }
+
+    /// Returns all the transactions in insertion order.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+}
+
+impl TransactionStore for MemoryStore {
+    fn categories(&self) -> &BTreeSet<Category> {
+        &self.categories
+    }
+
+    fn set_category(&mut self, category: Category) -> Result<(), ExpenseTrackerError> {
+        // Categories are ordered by all their fields in the `BTreeSet`, so a category with the
+        // same name but other sub-categories must be removed explicitly
+        self.remove_category(&category.name)?;
+        self.categories.insert(category);
+        Ok(())
+    }
+
+    fn remove_category(
+        &mut self,
+        category_name: &str,
+    ) -> Result<Option<Category>, ExpenseTrackerError> {
+        let category_name = category_name.to_lowercase();
+        let found_category = self
+            .categories
+            .iter()
+            .find(|category| category.name.to_lowercase() == category_name)
+            .cloned();
+        Ok(found_category.and_then(|category| self.categories.take(&category)))
+    }
+
+    fn n_transactions(&self) -> Result<usize, ExpenseTrackerError> {
+        Ok(self.transactions.len())
+    }
+
+    fn transactions_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Cow<'_, [Transaction]>, ExpenseTrackerError> {
+        let end = range.end.min(self.transactions.len());
+        let start = range.start.min(end);
+        Ok(Cow::Borrowed(&self.transactions[start..end]))
+    }
+
+    fn push_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError> {
+        self.transactions.push(transaction);
+        Ok(())
+    }
+
+    fn insert_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<(), ExpenseTrackerError> {
+        if index > self.transactions.len() {
+            return Err(invalid_index(index, self.transactions.len()));
+        }
+        self.transactions.insert(index, transaction);
+        Ok(())
+    }
+
+    fn replace_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        let n_transactions = self.transactions.len();
+        let previous = self
+            .transactions
+            .get_mut(index)
+            .ok_or_else(|| invalid_index(index, n_transactions))?;
+        Ok(std::mem::replace(previous, transaction))
+    }
+
+    fn remove_transaction(&mut self, index: usize) -> Result<Transaction, ExpenseTrackerError> {
+        if index >= self.transactions.len() {
+            return Err(invalid_index(index, self.transactions.len()));
+        }
+        Ok(self.transactions.remove(index))
+    }
+}
+
+/// Version of the layout of the files written by `JsonFileStore`, the second one allowing
+/// sub-categories of any depth.
+///
+/// Files of version 1 are read as they are, as `SubCategory::subcategories` defaults to an empty
+/// set. The version only stops older versions of the program from reading files with nested
+/// sub-categories, which they would drop.
+const JSON_STORE_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct JsonStoreFile<T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    store: T,
+}
+
+/// A store which keeps the data in memory and writes it to a JSON file when flushed.
+#[derive(Debug)]
+pub struct JsonFileStore {
+    file_path: PathBuf,
+    memory: MemoryStore,
+    is_modified: bool,
+}
+
+impl JsonFileStore {
+    /// Opens the store saved at the given path, or creates an empty one if the file does not
+    /// exist yet.
+    pub fn open(file_path: &Path) -> Result<Self, ExpenseTrackerError> {
+        let memory = if file_path.exists() {
+            let file = File::open(file_path).map_err(|e| {
+                ExpenseTrackerError::Storage(format!("Failed to open the JSON store: {e}"))
+            })?;
+            let store_file: JsonStoreFile<MemoryStore> =
+                serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| {
+                    ExpenseTrackerError::Storage(format!("Failed to read the JSON store: {e}"))
+                })?;
+            if store_file.schema_version > JSON_STORE_SCHEMA_VERSION {
+                return Err(ExpenseTrackerError::Storage(format!(
+                    "JSON store has schema version {}, but only versions up to \
+                     {JSON_STORE_SCHEMA_VERSION} are supported",
+                    store_file.schema_version
+                )));
+            }
+            store_file.store
+        } else {
+            MemoryStore::new()
+        };
+
+        Ok(JsonFileStore {
+            file_path: file_path.to_path_buf(),
+            memory,
+            is_modified: false,
+        })
+    }
+
+    /// Returns the path of the JSON file.
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Returns all the transactions in insertion order.
+    pub fn transactions(&self) -> &[Transaction] {
+        self.memory.transactions()
+    }
+}
+
+impl TransactionStore for JsonFileStore {
+    fn categories(&self) -> &BTreeSet<Category> {
+        self.memory.categories()
+    }
+
+    fn set_category(&mut self, category: Category) -> Result<(), ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.set_category(category)
+    }
+
+    fn remove_category(
+        &mut self,
+        category_name: &str,
+    ) -> Result<Option<Category>, ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.remove_category(category_name)
+    }
+
+    fn n_transactions(&self) -> Result<usize, ExpenseTrackerError> {
+        self.memory.n_transactions()
+    }
+
+    fn transactions_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Cow<'_, [Transaction]>, ExpenseTrackerError> {
+        self.memory.transactions_in_range(range)
+    }
+
+    fn push_transaction(&mut self, transaction: Transaction) -> Result<(), ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.push_transaction(transaction)
+    }
+
+    fn insert_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<(), ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.insert_transaction(index, transaction)
+    }
+
+    fn replace_transaction(
+        &mut self,
+        index: usize,
+        transaction: Transaction,
+    ) -> Result<Transaction, ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.replace_transaction(index, transaction)
+    }
+
+    fn remove_transaction(&mut self, index: usize) -> Result<Transaction, ExpenseTrackerError> {
+        self.is_modified = true;
+        self.memory.remove_transaction(index)
+    }
+
+    /// Writes the whole store to a temporary file which then replaces the JSON file, such that
+    /// the file is never left half written.
+    fn flush(&mut self) -> Result<(), ExpenseTrackerError> {
+        if !self.is_modified {
+            return Ok(());
+        }
+
+        let temporary_path = self.file_path.with_extension("json.tmp");
+        let file = File::create(&temporary_path).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to create the JSON store: {e}"))
+        })?;
+        let store_file = JsonStoreFile {
+            schema_version: JSON_STORE_SCHEMA_VERSION,
+            store: &self.memory,
+        };
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &store_file).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to write the JSON store: {e}"))
+        })?;
+        std::fs::rename(&temporary_path, &self.file_path).map_err(|e| {
+            ExpenseTrackerError::Storage(format!("Failed to replace the JSON store: {e}"))
+        })?;
+
+        self.is_modified = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::SplitLine;
+
+    fn category(name: &str) -> Category {
+        Category {
+            name: name.to_string(),
+            aliases: BTreeSet::new(),
+            subcategories: BTreeSet::new(),
+            date_added: NaiveDate::default(),
+        }
+    }
+
+    fn transaction(day: u32, category_name: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2023, 2, day).unwrap(),
+            amount: crate::money::Money::from_minor_units(-1000),
+            category_name: category_name.to_string(),
+            ..Transaction::new()
+        }
+    }
+
+    #[test]
+    fn memory_store_queries() {
+        let mut store = MemoryStore::new();
+        store.set_category(category("nourriture")).unwrap();
+        store
+            .push_transaction(transaction(1, "Nourriture"))
+            .unwrap();
+        store
+            .push_transaction(transaction(10, "transports"))
+            .unwrap();
+        store
+            .push_transaction(transaction(20, "nourriture"))
+            .unwrap();
+
+        let in_category = store.transactions_in_category("NOURRITURE", None).unwrap();
+        assert_eq!(in_category.len(), 2);
+        let start = NaiveDate::from_ymd_opt(2023, 2, 5).unwrap();
+        let end = NaiveDate::from_ymd_opt(2023, 2, 20).unwrap();
+        assert_eq!(store.transactions_between(start, end).unwrap().len(), 2);
+
+        let removed = store.remove_transaction(1).unwrap();
+        assert_eq!(removed.category_name, "transports");
+        assert!(matches!(
+            store.remove_transaction(5),
+            Err(ExpenseTrackerError::Storage(_))
+        ));
+        assert!(store.remove_category("Nourriture").unwrap().is_some());
+        assert!(store.categories().is_empty());
+    }
+
+    #[test]
+    fn read_transactions_a_page_at_a_time() {
+        let mut store = MemoryStore::new();
+        for index in 0..=PAGE_LEN {
+            store
+                .push_transaction(transaction(index as u32 % 28 + 1, "nourriture"))
+                .unwrap();
+        }
+        let last_id = store.transactions()[PAGE_LEN].id;
+
+        let transactions: Vec<Transaction> = store
+            .iter_transactions()
+            .map(|transaction| transaction.map(Cow::into_owned))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(transactions, store.transactions());
+        assert_eq!(store.transaction_index(last_id).unwrap(), Some(PAGE_LEN));
+        assert_eq!(store.transaction(PAGE_LEN).unwrap().id, last_id);
+        assert!(matches!(
+            store.transaction(PAGE_LEN + 1),
+            Err(ExpenseTrackerError::Storage(_))
+        ));
+        assert!(store
+            .transactions_in_range(PAGE_LEN + 5..PAGE_LEN + 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn query_accounts_and_sums() {
+        let mut store = MemoryStore::new();
+        let mut lunch = transaction(1, "Nourriture");
+        lunch.account = Some("Compte courant".to_string());
+        let mut transfer = transaction(2, "");
+        transfer.account = Some("Épargne".to_string());
+        transfer.transfer_account = Some("Compte courant".to_string());
+        let mut groceries = transaction(3, "");
+        groceries.splits = vec![
+            SplitLine {
+                amount: crate::money::Money::from_minor_units(-600),
+                category_name: "nourriture".to_string(),
+                subcategory_name: None,
+                tag: None,
+                note: None,
+            },
+            SplitLine {
+                amount: crate::money::Money::from_minor_units(-400),
+                category_name: "Ménage".to_string(),
+                subcategory_name: None,
+                tag: None,
+                note: None,
+            },
+        ];
+        for transaction in [lunch, transfer, groceries] {
+            store.push_transaction(transaction).unwrap();
+        }
+
+        let indices: Vec<usize> = store
+            .transactions_of_account("Compte courant")
+            .unwrap()
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(indices, [0, 1]);
+        assert!(store
+            .transactions_of_account("compte courant")
+            .unwrap()
+            .is_empty());
+
+        // Transfers are left out and split transactions are counted through their lines
+        let sums: Vec<(String, i64, usize)> = store
+            .category_sums()
+            .unwrap()
+            .into_iter()
+            .map(|sum| {
+                (
+                    sum.category_name,
+                    sum.total.minor_units(),
+                    sum.n_transactions,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sums,
+            [
+                ("Ménage".to_string(), -400, 1),
+                ("Nourriture".to_string(), -1600, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn json_file_store_is_written_on_flush() {
+        let tmp_dir = tempdir::TempDir::new("store").unwrap();
+        let store_path = tmp_dir.path().join("store.json");
+
+        let mut store = JsonFileStore::open(&store_path).unwrap();
+        store.set_category(category("nourriture")).unwrap();
+        store
+            .push_transaction(transaction(1, "nourriture"))
+            .unwrap();
+        assert!(!store_path.exists());
+        store.flush().unwrap();
+
+        let reopened_store = JsonFileStore::open(&store_path).unwrap();
+        assert_eq!(reopened_store.transactions(), store.transactions());
+        assert_eq!(reopened_store.categories(), store.categories());
+    }
+}
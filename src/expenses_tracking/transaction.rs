@@ -1,65 +1,375 @@
 use chrono::NaiveDate;
-use core::f32;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::currency::Currency;
+use crate::import_profile::ImportProfile;
+use crate::money::{AmountOutOfRange, Money};
+use crate::name_matching::NameMatching;
+use crate::parsing::{Parsed, ParsedFormats, ValueParser};
+
+/// A stable identifier of a transaction, assigned when the transaction is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TransactionId(Uuid);
+
+impl TransactionId {
+    /// Creates a new random ID.
+    pub fn new() -> Self {
+        TransactionId(Uuid::new_v4())
+    }
+}
+
+impl Default for TransactionId {
+    /// Creates a new random ID, such that transactions saved before IDs existed get one.
+    fn default() -> Self {
+        TransactionId::new()
+    }
+}
+
+impl fmt::Display for TransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.hyphenated())
+    }
+}
+
+impl FromStr for TransactionId {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(TransactionId(Uuid::parse_str(s.trim())?))
+    }
+}
+
+/// Whether a transaction was checked against a statement of its account.
+///
+/// A transfer has a state for each of its accounts, as they are on different statements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClearedState {
+    /// The transaction was not seen on a statement yet.
+    #[default]
+    Uncleared,
+    /// The transaction was seen on a statement which is not reconciled yet.
+    Cleared,
+    /// The transaction is part of a reconciled statement, and cannot be changed anymore.
+    Reconciled,
+}
+
+/// A line of a split transaction, e.g. the household goods on a supermarket receipt which also
+/// holds groceries.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SplitLine {
+    pub amount: Money,
+    pub category_name: String,
+    pub subcategory_name: Option<String>,
+    pub tag: Option<String>,
+    pub note: Option<String>,
+}
 
 /// A struct that represents a transaction
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Transaction {
+    #[serde(default)]
+    pub id: TransactionId,
     pub date: NaiveDate,
-    pub amount: f32,
+    pub amount: Money,
+    pub currency: Currency,
     pub category_name: String,
     pub subcategory_name: Option<String>,
     pub tag: Option<String>,
     pub note: Option<String>,
+    /// Name of the account the transaction was made with, if known.
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Name of the account receiving the opposite of the amount if the transaction is a transfer
+    /// from `account`. Transfers have no category and are left out of income and expenses.
+    #[serde(default)]
+    pub transfer_account: Option<String>,
+    /// State of the transaction on the statements of `account`.
+    #[serde(default)]
+    pub cleared: ClearedState,
+    /// State of a transfer on the statements of `transfer_account`, which is always uncleared if
+    /// the transaction is not a transfer.
+    #[serde(default)]
+    pub transfer_cleared: ClearedState,
+    /// Lines of a split transaction, whose amounts add up to `amount`. A split transaction has no
+    /// category of its own, and is counted in reports through its lines.
+    #[serde(default)]
+    pub splits: Vec<SplitLine>,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct TransactionCsv {
-    date: String,
-    amount_out: String,
-    amount_in: String,
-    category: String,
-    subcategory: String,
-    tag: String,
-    note: String,
+    pub(crate) date: String,
+    pub(crate) amount_out: String,
+    pub(crate) amount_in: String,
+    // The currency column is optional, in which case the default currency is used
+    #[serde(default)]
+    pub(crate) currency: String,
+    pub(crate) category: String,
+    pub(crate) subcategory: String,
+    pub(crate) tag: String,
+    pub(crate) note: String,
+    // The ID column is optional, in which case a new ID is assigned
+    #[serde(default)]
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) account: String,
+    #[serde(default)]
+    pub(crate) transfer_account: String,
+}
+
+/// A field of a `TransactionCsv` which needs to be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvField {
+    Date,
+    AmountOut,
+    AmountIn,
+    Currency,
+    Id,
+}
+
+impl fmt::Display for CsvField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CsvField::Date => "date",
+            CsvField::AmountOut => "amount_out",
+            CsvField::AmountIn => "amount_in",
+            CsvField::Currency => "currency",
+            CsvField::Id => "id",
+        };
+        f.write_str(name)
+    }
 }
 
+/// An error raised when a field of a `TransactionCsv` cannot be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvFieldError {
+    pub field: CsvField,
+    pub message: String,
+}
+
+impl fmt::Display for CsvFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to parse {} from CSV transaction: {}",
+            self.field, self.message
+        )
+    }
+}
+
+impl Error for CsvFieldError {}
+
 impl TryFrom<TransactionCsv> for Transaction {
     type Error = Box<dyn Error>;
 
     fn try_from(transaction_csv: TransactionCsv) -> Result<Self, Self::Error> {
-        let formatted_date =
-            chrono::NaiveDate::parse_from_str(&transaction_csv.date, "%d.%m.%Y")
-                .map_err(|e| format!("Failed to parse date from CSV transaction: {e}"))?;
-        let parsed_amount_in = parse_amount(&transaction_csv.amount_in)
-            .map_err(|e| format!("Failed to parse amount_in from CSV transaction: {e}"))?;
-        let parsed_amount_out = parse_amount(&transaction_csv.amount_out)
-            .map_err(|e| format!("Failed to parse amount_out from CSV transaction: {e}"))?;
+        Transaction::from_csv(transaction_csv, Currency::default())
+    }
+}
+
+impl Transaction {
+    /// Converts a `TransactionCsv` into a `Transaction`, using `default_currency` if the CSV
+    /// transaction does not specify one.
+    pub fn from_csv(
+        transaction_csv: TransactionCsv,
+        default_currency: Currency,
+    ) -> Result<Self, Box<dyn Error>> {
+        let import_profile = ImportProfile::default();
+        let (transaction, _) = Transaction::from_csv_with_parsers(
+            transaction_csv,
+            default_currency,
+            &import_profile.date_parser(),
+            &import_profile.amount_parser(),
+        )?;
+        Ok(transaction)
+    }
+
+    /// Converts a `TransactionCsv` into a `Transaction` with the given date and amount parsers,
+    /// and returns the formats which matched.
+    pub fn from_csv_with_parsers(
+        transaction_csv: TransactionCsv,
+        default_currency: Currency,
+        date_parser: &dyn ValueParser<NaiveDate>,
+        amount_parser: &dyn ValueParser<Money>,
+    ) -> Result<(Self, ParsedFormats), CsvFieldError> {
+        let field_error = |field: CsvField| {
+            move |e: Box<dyn Error>| CsvFieldError {
+                field,
+                message: e.to_string(),
+            }
+        };
+
+        let parsed_date = date_parser
+            .parse(&transaction_csv.date)
+            .map_err(field_error(CsvField::Date))?;
+        let parsed_amount_in = parse_optional_amount(&transaction_csv.amount_in, amount_parser)
+            .map_err(field_error(CsvField::AmountIn))?;
+        let parsed_amount_out = parse_optional_amount(&transaction_csv.amount_out, amount_parser)
+            .map_err(field_error(CsvField::AmountOut))?;
+        let currency = if transaction_csv.currency.is_empty() {
+            default_currency
+        } else {
+            transaction_csv
+                .currency
+                .parse()
+                .map_err(field_error(CsvField::Currency))?
+        };
+        let id = if transaction_csv.id.is_empty() {
+            TransactionId::new()
+        } else {
+            transaction_csv
+                .id
+                .parse()
+                .map_err(field_error(CsvField::Id))?
+        };
+
+        let amount_in = parsed_amount_in.as_ref().map_or(Money::ZERO, |p| p.value);
+        let amount_out = parsed_amount_out.as_ref().map_or(Money::ZERO, |p| p.value);
+        let amount = amount_in.checked_sub(amount_out).ok_or_else(|| {
+            field_error(CsvField::AmountOut)(
+                AmountOutOfRange(transaction_csv.amount_out.clone()).into(),
+            )
+        })?;
 
         let transaction = Transaction {
-            date: formatted_date,
-            amount: parsed_amount_in - parsed_amount_out,
+            id,
+            date: parsed_date.value,
+            amount,
+            currency,
             category_name: transaction_csv.category,
             subcategory_name: string_to_option(transaction_csv.subcategory),
             tag: string_to_option(transaction_csv.tag),
             note: string_to_option(transaction_csv.note),
+            account: string_to_option(transaction_csv.account),
+            transfer_account: string_to_option(transaction_csv.transfer_account),
+            cleared: ClearedState::Uncleared,
+            transfer_cleared: ClearedState::Uncleared,
+            splits: Vec::new(),
+        };
+        let parsed_formats = ParsedFormats {
+            date: parsed_date.format,
+            amount_out: parsed_amount_out.map(|parsed| parsed.format),
+            amount_in: parsed_amount_in.map(|parsed| parsed.format),
         };
 
-        Ok(transaction)
+        Ok((transaction, parsed_formats))
     }
-}
 
-impl Transaction {
-    fn to_csv_row() {
-        todo!();
+    /// Returns whether the transaction is reconciled with a statement of one of its accounts, in
+    /// which case it cannot be changed anymore.
+    pub fn is_reconciled(&self) -> bool {
+        self.cleared == ClearedState::Reconciled
+            || self.transfer_cleared == ClearedState::Reconciled
+    }
+
+    /// Returns whether the transaction moves money between two accounts.
+    pub fn is_transfer(&self) -> bool {
+        self.transfer_account.is_some()
+    }
+
+    /// Returns whether the transaction is split into lines of different categories.
+    pub fn is_split(&self) -> bool {
+        !self.splits.is_empty()
+    }
+
+    /// Returns the transactions holding the categories of this one, which are the transaction
+    /// itself, or one transaction per line of a split transaction. The transaction of a line has
+    /// the amount, categories, tag and note of the line, and the other fields of this one.
+    pub fn category_lines(&self) -> Vec<Transaction> {
+        if !self.is_split() {
+            return vec![self.clone()];
+        }
+        self.splits
+            .iter()
+            .map(|line| Transaction {
+                amount: line.amount,
+                category_name: line.category_name.clone(),
+                subcategory_name: line.subcategory_name.clone(),
+                tag: line.tag.clone(),
+                note: line.note.clone(),
+                splits: Vec::new(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Returns this transaction with the categories of its lines replaced, from the transactions
+    /// returned by `category_lines`.
+    pub fn with_category_lines(&self, lines: Vec<Transaction>) -> Transaction {
+        if !self.is_split() {
+            return lines.into_iter().next().unwrap_or_else(|| self.clone());
+        }
+        Transaction {
+            splits: lines
+                .into_iter()
+                .map(|line| SplitLine {
+                    amount: line.amount,
+                    category_name: line.category_name,
+                    subcategory_name: line.subcategory_name,
+                    tag: line.tag,
+                    note: line.note,
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the fields of the transaction in the same order as the columns of `TransactionCsv`
+    /// which are always present.
+    pub fn to_csv_row(&self) -> [String; 7] {
+        // Amounts are always written as positive numbers in either the "out" or the "in" column
+        let (amount_out, amount_in) = if self.amount.is_negative() {
+            let amount = self.amount.to_string();
+            (amount.trim_start_matches('-').to_string(), String::new())
+        } else {
+            (String::new(), self.amount.to_string())
+        };
+
+        [
+            self.date.format("%d.%m.%Y").to_string(),
+            amount_out,
+            amount_in,
+            self.category_name.clone(),
+            self.subcategory_name.clone().unwrap_or_default(),
+            self.tag.clone().unwrap_or_default(),
+            self.note.clone().unwrap_or_default(),
+        ]
     }
 }
 
+/// Separator between the levels of a path of categories, such as "voyages/nourriture/restaurant".
+pub const PATH_SEPARATOR: char = '/';
+
+/// Splits a path of categories into the names of its levels, ignoring empty levels.
+pub fn split_path(path: &str) -> Vec<&str> {
+    path.split(PATH_SEPARATOR)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Joins the names of levels of categories into a path.
+pub fn join_path<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    names
+        .into_iter()
+        .collect::<Vec<&str>>()
+        .join(&PATH_SEPARATOR.to_string())
+}
+
+/// A top-level category, holding a tree of sub-categories of any depth.
 #[derive(Debug, Clone, Serialize, Deserialize, Ord, PartialOrd)]
 pub struct Category {
+    /// Name displayed to the user, with its original capitalization.
     pub name: String,
+    /// Other names under which the category is known, e.g. in the exports of a bank.
+    #[serde(default)]
+    pub aliases: BTreeSet<String>,
     pub subcategories: BTreeSet<SubCategory>,
     pub date_added: NaiveDate,
 }
@@ -77,27 +387,120 @@ impl Category {
     /// Creates a default `Category` object from a name.
     pub fn from_name(name: &str) -> Category {
         Category {
-            name: name.to_lowercase(),
+            name: name.to_string(),
+            aliases: BTreeSet::new(),
             date_added: NaiveDate::default(),
             subcategories: BTreeSet::new(),
         }
     }
+
+    /// Returns true if the name or one of the aliases of the category matches `name`.
+    pub fn is_named(&self, name: &str, matching: NameMatching) -> bool {
+        is_named(&self.name, &self.aliases, name, matching)
+    }
+
+    /// Returns the sub-categories along a path such as "nourriture/restaurant", from the first
+    /// level down, or `None` if a level does not exist. Each level may be named by an alias.
+    pub fn subcategory_path(
+        &self,
+        subcategory_path: &str,
+        matching: NameMatching,
+    ) -> Option<Vec<&SubCategory>> {
+        let mut subcategories = &self.subcategories;
+        let mut found_subcategories = Vec::new();
+        for name in split_path(subcategory_path) {
+            let subcategory = subcategories
+                .iter()
+                .find(|subcategory| subcategory.is_named(name, matching))?;
+            subcategories = &subcategory.subcategories;
+            found_subcategories.push(subcategory);
+        }
+        (!found_subcategories.is_empty()).then_some(found_subcategories)
+    }
+
+    /// Returns the sub-category at the end of a path such as "nourriture/restaurant".
+    pub fn subcategory(
+        &self,
+        subcategory_path: &str,
+        matching: NameMatching,
+    ) -> Option<&SubCategory> {
+        self.subcategory_path(subcategory_path, matching)?.pop()
+    }
+
+    /// Returns every sub-category of the tree along with its path, parents before children.
+    pub fn subcategories_with_paths(&self) -> Vec<(String, &SubCategory)> {
+        let mut subcategories_with_paths = Vec::new();
+        let mut pending: Vec<(String, &SubCategory)> = self
+            .subcategories
+            .iter()
+            .rev()
+            .map(|subcategory| (subcategory.name.clone(), subcategory))
+            .collect();
+        while let Some((path, subcategory)) = pending.pop() {
+            for child in subcategory.subcategories.iter().rev() {
+                pending.push((join_path([path.as_str(), child.name.as_str()]), child));
+            }
+            subcategories_with_paths.push((path, subcategory));
+        }
+        subcategories_with_paths
+    }
+
+    /// Applies `edit` to the sub-categories directly below the sub-category at `parent_names`,
+    /// given by their display names, or directly below the category if `parent_names` is empty.
+    ///
+    /// Returns `None` without calling `edit` if the parent does not exist.
+    pub(crate) fn edit_subcategories<R>(
+        &mut self,
+        parent_names: &[String],
+        edit: impl FnOnce(&mut BTreeSet<SubCategory>) -> R,
+    ) -> Option<R> {
+        edit_subcategories(&mut self.subcategories, parent_names, edit)
+    }
 }
+
+fn edit_subcategories<R>(
+    subcategories: &mut BTreeSet<SubCategory>,
+    parent_names: &[String],
+    edit: impl FnOnce(&mut BTreeSet<SubCategory>) -> R,
+) -> Option<R> {
+    let Some((name, other_names)) = parent_names.split_first() else {
+        return Some(edit(subcategories));
+    };
+    // Elements of a `BTreeSet` cannot be modified in place, so the parent is taken out and
+    // inserted back
+    let mut parent = subcategories
+        .iter()
+        .find(|subcategory| &subcategory.name == name)
+        .cloned()?;
+    subcategories.remove(&parent);
+    let result = edit_subcategories(&mut parent.subcategories, other_names, edit);
+    subcategories.insert(parent);
+    result
+}
+
 pub trait AsCategory {
-    fn as_category(self) -> Category;
+    fn as_category(&self) -> Category;
 }
 
-// For convenience we add a trait to `&str` objects such that they can be used to create default
+// For convenience we add a trait to `str` objects such that they can be used to create default
 // `Category` objects easily
-impl AsCategory for &str {
-    fn as_category(self) -> Category {
+impl AsCategory for str {
+    fn as_category(&self) -> Category {
         Category::from_name(self)
     }
 }
 
+/// A level of the tree of a category, which may hold sub-categories of its own.
 #[derive(Debug, Clone, Serialize, Deserialize, Ord, PartialOrd)]
 pub struct SubCategory {
+    /// Name displayed to the user, with its original capitalization.
     pub name: String,
+    /// Other names under which the sub-category is known.
+    #[serde(default)]
+    pub aliases: BTreeSet<String>,
+    /// The sub-categories one level below, which files written before the tree existed lack.
+    #[serde(default)]
+    pub subcategories: BTreeSet<SubCategory>,
     pub date_added: NaiveDate,
 }
 
@@ -113,33 +516,50 @@ impl SubCategory {
     /// Creates a default `SubCategory` object from a name.
     fn from_name(name: &str) -> SubCategory {
         SubCategory {
-            name: name.to_lowercase(),
+            name: name.to_string(),
+            aliases: BTreeSet::new(),
+            subcategories: BTreeSet::new(),
             date_added: NaiveDate::default(),
         }
     }
+
+    /// Returns true if the name or one of the aliases of the sub-category matches `name`.
+    pub fn is_named(&self, name: &str, matching: NameMatching) -> bool {
+        is_named(&self.name, &self.aliases, name, matching)
+    }
+}
+
+fn is_named(
+    display_name: &str,
+    aliases: &BTreeSet<String>,
+    name: &str,
+    matching: NameMatching,
+) -> bool {
+    let key = matching.key(name);
+    std::iter::once(display_name)
+        .chain(aliases.iter().map(String::as_str))
+        .any(|candidate| matching.key(candidate) == key)
 }
 
 pub trait AsSubCategory {
-    fn as_subcategory(self) -> SubCategory;
+    fn as_subcategory(&self) -> SubCategory;
 }
 
-impl AsSubCategory for &str {
-    fn as_subcategory(self) -> SubCategory {
+impl AsSubCategory for str {
+    fn as_subcategory(&self) -> SubCategory {
         SubCategory::from_name(self)
     }
 }
 
-fn parse_amount(amount: &str) -> Result<f32, Box<dyn Error>> {
-    let mut numeric_amount = 0.0;
-    // If an amount has no value, we set it to zero
-    if !amount.is_empty() {
-        // The ' character is used to delimit thousands from hundreds in CHF, so we remove it if
-        // present
-        let formatted_amount = amount.replace('\'', "");
-        numeric_amount = formatted_amount.parse()?;
+/// Parses an amount, which is `None` if it has no value.
+fn parse_optional_amount(
+    amount: &str,
+    amount_parser: &dyn ValueParser<Money>,
+) -> Result<Option<Parsed<Money>>, Box<dyn Error>> {
+    if amount.trim().is_empty() {
+        return Ok(None);
     }
-
-    Ok(numeric_amount)
+    Ok(Some(amount_parser.parse(amount)?))
 }
 
 fn string_to_option(s: String) -> Option<String> {
@@ -168,10 +588,14 @@ mod tests {
             date: "01.01.1970".to_string(),
             amount_out: "30".to_string(),
             amount_in: "".to_string(),
+            currency: "EUR".to_string(),
             category: "Food".to_string(),
             subcategory: "".to_string(),
             tag: "Invited others".to_string(),
             note: "This is a note".to_string(),
+            id: "".to_string(),
+            account: "".to_string(),
+            transfer_account: "".to_string(),
         };
 
         // Note that the date is the order of keys is on purpose not the same as in TransactionCsv
@@ -183,6 +607,7 @@ mod tests {
             "tag",
             "date",
             "note",
+            "currency",
         ]);
         let transaction_record = StringRecord::from(vec![
             transaction_csv.amount_out.clone(),
@@ -192,6 +617,7 @@ mod tests {
             transaction_csv.tag.clone(),
             transaction_csv.date.clone(),
             transaction_csv.note.clone(),
+            transaction_csv.currency.clone(),
         ]);
 
         let transaction_csv_deserialized: TransactionCsv =
@@ -200,5 +626,63 @@ mod tests {
         assert_eq!(transaction_csv_deserialized, transaction_csv);
     }
 
+    #[test]
+    fn convert_transaction_without_currency_column() {
+        let header = StringRecord::from(vec![
+            "date",
+            "amount_out",
+            "amount_in",
+            "category",
+            "subcategory",
+            "tag",
+            "note",
+        ]);
+        let transaction_record =
+            StringRecord::from(vec!["05.02.2023", "207.47", "", "Voyages", "", "", ""]);
+        let transaction_csv: TransactionCsv =
+            transaction_record.deserialize(Some(&header)).unwrap();
+        assert!(transaction_csv.currency.is_empty());
+
+        let transaction = Transaction::from_csv(transaction_csv, Currency::EUR).unwrap();
+        assert_eq!(transaction.currency, Currency::EUR);
+        assert_eq!(transaction.amount, Money::from_minor_units(-20747));
+    }
+
+    #[test]
+    fn convert_transaction_with_parsers() {
+        use crate::parsing::{AmountFormat, AmountParser, DateParser};
+
+        let transaction_csv = TransactionCsv {
+            date: "2023-02-06".to_string(),
+            amount_out: "CHF 1 234,50".to_string(),
+            amount_in: "".to_string(),
+            currency: "".to_string(),
+            category: "Nourriture".to_string(),
+            subcategory: "".to_string(),
+            tag: "".to_string(),
+            note: "".to_string(),
+            id: "".to_string(),
+            account: "".to_string(),
+            transfer_account: "".to_string(),
+        };
+        let date_parser = DateParser::new(vec!["%d.%m.%Y".to_string(), "%Y-%m-%d".to_string()]);
+        let amount_parser = AmountParser::new(vec![AmountFormat {
+            decimal_separator: ',',
+            thousands_separators: vec![' '],
+        }]);
+
+        let (transaction, parsed_formats) = Transaction::from_csv_with_parsers(
+            transaction_csv,
+            Currency::CHF,
+            &date_parser,
+            &amount_parser,
+        )
+        .unwrap();
+        assert_eq!(transaction.amount, Money::from_minor_units(-123450));
+        assert_eq!(parsed_formats.date, "%Y-%m-%d");
+        assert_eq!(parsed_formats.amount_out.as_deref(), Some("1 234,50"));
+        assert_eq!(parsed_formats.amount_in, None);
+    }
+
     // Add tests about conversions from TransactionCsv to Transaction and how it can fail
 }
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+use crate::money::Money;
+use crate::name_matching::NameMatching;
+use crate::transaction::Transaction;
+
+/// How imported rows are paired with transactions of other accounts to record transfers.
+///
+/// A row and an existing transaction are the two sides of a transfer when they are made with
+/// different accounts and have opposite amounts, such as the withdrawal on the statement of a
+/// current account and the deposit on the statement of a savings account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferMatching {
+    /// Whether rows are paired on import. Disabled by default, as an expense and an income of
+    /// the same amount may happen to be close in time.
+    pub enabled: bool,
+    /// Largest number of days between the dates of the two sides, since the receiving bank may
+    /// book a transfer a few days after it was sent.
+    pub date_tolerance_days: u32,
+}
+
+impl TransferMatching {
+    /// Returns whether two transactions are the two sides of a transfer. Transactions which are
+    /// already transfers, or which have no account, are never paired.
+    pub fn are_transfer_sides(
+        &self,
+        transaction: &Transaction,
+        other: &Transaction,
+        matching: NameMatching,
+    ) -> bool {
+        let (Some(account), Some(other_account)) = (&transaction.account, &other.account) else {
+            return false;
+        };
+
+        !transaction.is_transfer()
+            && !other.is_transfer()
+            && !matching.matches(account, other_account)
+            && transaction.amount != Money::ZERO
+            && other.amount.checked_neg() == Some(transaction.amount)
+            && transaction.currency == other.currency
+            && (transaction.date - other.date).num_days().unsigned_abs()
+                <= u64::from(self.date_tolerance_days)
+    }
+
+    /// Returns the index of the first of the indexed transactions which is the other side of a
+    /// transfer with the given one, skipping the ones for which `is_excluded` returns `true`.
+    /// Returns `None` if pairing is disabled.
+    pub fn find_other_side<'a>(
+        &self,
+        transaction: &Transaction,
+        transactions: impl IntoIterator<Item = (usize, &'a Transaction)>,
+        matching: NameMatching,
+        is_excluded: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        if !self.enabled {
+            return None;
+        }
+        transactions
+            .into_iter()
+            .find(|(index, other)| {
+                !is_excluded(*index) && self.are_transfer_sides(transaction, other, matching)
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+/// Returns the transfer made of the two sides of a transfer, from the account of the negative
+/// side to the account of the positive one, on the date it was sent.
+///
+/// The transfer keeps the ID of `existing`, the cleared state of each side on its own account, and
+/// the tag and note of `existing` if it has some.
+pub fn transfer_between(existing: &Transaction, other: &Transaction) -> Transaction {
+    let (from, to) = if existing.amount.is_negative() {
+        (existing, other)
+    } else {
+        (other, existing)
+    };
+    Transaction {
+        id: existing.id,
+        date: from.date,
+        amount: from.amount,
+        currency: from.currency,
+        category_name: String::new(),
+        subcategory_name: None,
+        tag: existing.tag.clone().or_else(|| other.tag.clone()),
+        note: existing.note.clone().or_else(|| other.note.clone()),
+        account: from.account.clone(),
+        transfer_account: to.account.clone(),
+        cleared: from.cleared,
+        transfer_cleared: to.cleared,
+        splits: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn transaction(day: u32, amount: i64, account: &str) -> Transaction {
+        let mut transaction = Transaction::new();
+        transaction.date = NaiveDate::from_ymd_opt(2023, 2, day).unwrap();
+        transaction.amount = Money::from_minor_units(amount);
+        transaction.category_name = "Épargne".to_string();
+        transaction.account = Some(account.to_string());
+        transaction
+    }
+
+    #[test]
+    fn pair_sides_of_transfers() {
+        let mut transfer_matching = TransferMatching {
+            enabled: true,
+            date_tolerance_days: 0,
+        };
+        let matching = NameMatching::default();
+        let deposit = transaction(7, 50_000, "Épargne");
+        let transactions = [
+            // Same account, same sign, or too late
+            transaction(6, -50_000, "épargne"),
+            transaction(6, 50_000, "Compte courant"),
+            transaction(9, -50_000, "Compte courant"),
+            transaction(6, -50_000, "Compte courant"),
+        ];
+        assert_eq!(
+            transfer_matching.find_other_side(
+                &deposit,
+                transactions.iter().enumerate(),
+                matching,
+                |_| false
+            ),
+            None
+        );
+
+        transfer_matching.date_tolerance_days = 2;
+        assert_eq!(
+            transfer_matching.find_other_side(
+                &deposit,
+                transactions.iter().enumerate(),
+                matching,
+                |_| false
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            transfer_matching.find_other_side(
+                &deposit,
+                transactions.iter().enumerate(),
+                matching,
+                |index| index == 2
+            ),
+            Some(3)
+        );
+
+        let transfer = transfer_between(&deposit, &transactions[3]);
+        assert_eq!(transfer.id, deposit.id);
+        assert_eq!(transfer.date, transactions[3].date);
+        assert_eq!(transfer.amount, Money::from_minor_units(-50_000));
+        assert_eq!(transfer.account.as_deref(), Some("Compte courant"));
+        assert_eq!(transfer.transfer_account.as_deref(), Some("Épargne"));
+        assert!(transfer.category_name.is_empty());
+        assert!(!transfer_matching.are_transfer_sides(&transfer, &deposit, matching));
+
+        transfer_matching.enabled = false;
+        assert_eq!(
+            transfer_matching.find_other_side(
+                &deposit,
+                transactions.iter().enumerate(),
+                matching,
+                |_| false
+            ),
+            None
+        );
+    }
+}
@@ -1,6 +1,8 @@
 // Based on https://github.com/emilk/egui/blob/master/crates/egui_demo_lib/src/demo/table_demo.rs
 
-use expenses_tracking::{expense_tracker::ExpenseTracker, transaction::Category};
+use expenses_tracking::{
+    expense_tracker::ExpenseTracker, import_report::ImportMode, money::Money, transaction::Category,
+};
 
 use std::{path::PathBuf, str::FromStr};
 
@@ -59,6 +61,9 @@ impl Widget for TransactionTable {
     fn ui(&mut self, ui: &mut eframe::egui::Ui) {
         let mut reset = false;
         let mut load_transactions = false;
+        let mut import_transactions = false;
+        let mut undo = false;
+        let mut redo = false;
 
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
@@ -66,9 +71,44 @@ impl Widget for TransactionTable {
                 ui.checkbox(&mut self.resizable, "Resizable columns");
             });
             reset = ui.button("Reset").clicked();
-            load_transactions = ui.button("Load transactions").clicked();
+            load_transactions = ui
+                .button("Load transactions")
+                .on_hover_text("Replaces the transactions, and clears the history of undo and redo")
+                .clicked();
+            import_transactions = ui
+                .button("Import transactions")
+                .on_hover_text("Adds the transactions to the current ones, which can be undone")
+                .clicked();
+            ui.horizontal(|ui| {
+                let history = self.expense_tracker.history();
+                let undo_button =
+                    ui.add_enabled(history.can_undo(), eframe::egui::Button::new("⟲ Undo"));
+                undo = match history.undo_stack().last() {
+                    Some(command) => undo_button.on_hover_text(&command.description),
+                    None => undo_button,
+                }
+                .clicked();
+                let redo_button =
+                    ui.add_enabled(history.can_redo(), eframe::egui::Button::new("⟳ Redo"));
+                redo = match history.redo_stack().last() {
+                    Some(command) => redo_button.on_hover_text(&command.description),
+                    None => redo_button,
+                }
+                .clicked();
+            });
         });
 
+        let result = if undo {
+            self.expense_tracker.undo()
+        } else if redo {
+            self.expense_tracker.redo()
+        } else {
+            Ok(None)
+        };
+        if let Err(e) = result {
+            log::error!("{e}");
+        }
+
         ui.horizontal(|ui| {
             eframe::egui::ComboBox::from_label("Category filter")
                 .selected_text(match &self.transaction_category_filter {
@@ -84,7 +124,7 @@ impl Widget for TransactionTable {
                     );
 
                     // Add valid categories as options
-                    for category in &self.expense_tracker.valid_categories {
+                    for category in self.expense_tracker.categories() {
                         ui.selectable_value(
                             &mut self.transaction_category_filter,
                             CategoryFilter::CategorySelected(category.clone()),
@@ -94,7 +134,7 @@ impl Widget for TransactionTable {
                 });
         });
 
-        if load_transactions {
+        if load_transactions || import_transactions {
             let transactions_file_path =
                 PathBuf::from_str("/Users/eric/Desktop/transactions_short.csv")
                     .map_err(|e| {
@@ -102,12 +142,24 @@ impl Widget for TransactionTable {
                     })
                     .unwrap();
 
-            let mut expense_tracker = ExpenseTracker::new();
-            expense_tracker
-                .load_transactions_from_file(&transactions_file_path, true)
-                .unwrap();
+            if load_transactions {
+                let mut expense_tracker = ExpenseTracker::new();
+                expense_tracker
+                    .load_transactions_from_file(&transactions_file_path, true, ImportMode::Lenient)
+                    .unwrap();
 
-            self.expense_tracker = expense_tracker;
+                self.expense_tracker = expense_tracker;
+            }
+            if import_transactions {
+                // The import is a command of the history, which the undo button reverts
+                if let Err(e) = self.expense_tracker.load_transactions_from_file(
+                    &transactions_file_path,
+                    true,
+                    ImportMode::Lenient,
+                ) {
+                    log::error!("{e}");
+                }
+            }
         }
 
         ui.separator();
@@ -148,6 +200,7 @@ impl TransactionTable {
             .column(Column::auto())
             .column(Column::auto())
             .column(Column::auto())
+            .column(Column::auto())
             .column(
                 Column::remainder()
                     .at_least(40.0)
@@ -161,22 +214,37 @@ impl TransactionTable {
             table.reset();
         }
 
-        // TODO: Improve this. It's inefficient to clone all the transactions for each frame...
-        let transactions = if self.transaction_category_filter == CategoryFilter::NoneSelected {
-            self.expense_tracker.transactions.clone()
-        } else {
-            self.expense_tracker
-                .transactions
-                .iter()
-                .filter(|transaction| match &self.transaction_category_filter {
-                    CategoryFilter::NoneSelected => true,
-                    CategoryFilter::CategorySelected(category) => {
-                        // Check if the transaction has the same category as the filter
-                        &Category::from_name(transaction.category_name.as_str()) == category
+        // Only the visible rows are read from the store, through the indices of the transactions
+        // shown when filtering by category
+        let filtered_indices = match &self.transaction_category_filter {
+            CategoryFilter::NoneSelected => None,
+            CategoryFilter::CategorySelected(category) => {
+                let mut indices = Vec::new();
+                for (index, transaction) in self.expense_tracker.iter_transactions().enumerate() {
+                    let transaction = match transaction {
+                        Ok(transaction) => transaction,
+                        Err(e) => {
+                            log::error!("{e}");
+                            break;
+                        }
+                    };
+                    // Check if the transaction, or one of the lines of a split transaction,
+                    // has the same category as the filter
+                    if transaction.category_lines().iter().any(|line| {
+                        category.is_named(&line.category_name, self.expense_tracker.name_matching)
+                    }) {
+                        indices.push(index);
                     }
-                })
-                .cloned()
-                .collect()
+                }
+                Some(indices)
+            }
+        };
+        let n_rows = match &filtered_indices {
+            None => self.expense_tracker.n_transactions().unwrap_or_else(|e| {
+                log::error!("{e}");
+                0
+            }),
+            Some(indices) => indices.len(),
         };
 
         table
@@ -193,6 +261,9 @@ impl TransactionTable {
                 header.col(|ui| {
                     ui.strong("Amount received");
                 });
+                header.col(|ui| {
+                    ui.strong("Currency");
+                });
                 header.col(|ui| {
                     ui.strong("Category");
                 });
@@ -207,28 +278,44 @@ impl TransactionTable {
                 });
             })
             .body(|body| {
-                body.rows(text_height, transactions.len(), |mut row| {
+                body.rows(text_height, n_rows, |mut row| {
                     let row_index = row.index();
-                    let transaction = &transactions[row_index];
-                    let amount = transaction.amount;
-                    let (mut amount_in, mut amount_out) = (0.0, 0.0);
-                    if amount > 0.0 {
-                        amount_in = amount;
+                    let index = filtered_indices
+                        .as_ref()
+                        .map_or(row_index, |indices| indices[row_index]);
+                    let transaction = match self.expense_tracker.transaction_at(index) {
+                        Ok(transaction) => transaction,
+                        Err(e) => {
+                            log::error!("{e}");
+                            return;
+                        }
+                    };
+                    let amount = transaction.amount.to_string();
+                    let zero = Money::ZERO.to_string();
+                    // Amounts out are shown without their sign
+                    let (amount_in, amount_out) = if transaction.amount > Money::ZERO {
+                        (amount, zero)
                     } else {
-                        amount_out = -amount;
-                    }
+                        (zero, amount.trim_start_matches('-').to_string())
+                    };
 
                     row.col(|ui| {
-                        ui.label(row_index.to_string());
+                        // The ID is shortened to its first group of digits, and shown in full on
+                        // hover
+                        let id = transaction.id.to_string();
+                        ui.label(&id[..8]).on_hover_text(id);
                     });
                     row.col(|ui| {
                         ui.label(transaction.date.to_string());
                     });
                     row.col(|ui| {
-                        ui.label(amount_out.to_string());
+                        ui.label(&amount_out);
+                    });
+                    row.col(|ui| {
+                        ui.label(&amount_in);
                     });
                     row.col(|ui| {
-                        ui.label(amount_in.to_string());
+                        ui.label(transaction.currency.code());
                     });
                     row.col(|ui| {
                         ui.label(transaction.category_name.as_str());
@@ -1,6 +1,28 @@
 pub mod expenses_tracking;
 
+pub use expenses_tracking::account;
+pub use expenses_tracking::category_totals;
+pub use expenses_tracking::classifier;
+pub use expenses_tracking::currency;
+pub use expenses_tracking::duplicates;
+pub use expenses_tracking::error;
+pub use expenses_tracking::exchange_rate;
 pub use expenses_tracking::expense_tracker;
+pub use expenses_tracking::export;
+pub use expenses_tracking::history;
+pub use expenses_tracking::import_profile;
+pub use expenses_tracking::import_report;
+pub use expenses_tracking::journal;
+pub use expenses_tracking::money;
+pub use expenses_tracking::name_matching;
+pub use expenses_tracking::parsing;
+pub use expenses_tracking::project_file;
+pub use expenses_tracking::reconciliation;
+pub use expenses_tracking::rules;
+#[cfg(feature = "sqlite")]
+pub use expenses_tracking::sqlite_store;
+pub use expenses_tracking::store;
 pub use expenses_tracking::transaction;
+pub use expenses_tracking::transfers;
 
 //pub use expenses_tracking::transaction;